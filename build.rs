@@ -1,4 +1,99 @@
+use std::{env, fs, path::Path};
+
 fn main() {
     println!("cargo:rerun-if-changed=js/");
     println!("cargo:rerun-if-changed=vendor/");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let mut generated = String::new();
+    for (name, paths, cfg) in [
+        (
+            "PHYSICS_COMMANDS",
+            &["vendor/temml/contrib/physics/physics.js"][..],
+            r#"any(feature = "temml", feature = "physics")"#,
+        ),
+        (
+            "TEXVC_COMMANDS",
+            &[
+                "vendor/temml/contrib/texvc/texvc.js",
+                "js/texvc-functions.js",
+            ][..],
+            r#"any(feature = "temml", feature = "texvc")"#,
+        ),
+    ] {
+        let src: Vec<String> = paths
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        let commands = defined_macros(&src.join("\n"));
+        generated.push_str(&format!(
+            "#[cfg({cfg})]\nconst {name}: &[&str] = &{commands:?};\n"
+        ));
+    }
+    fs::write(Path::new(&out_dir).join("extension_commands.rs"), generated).unwrap();
+
+    #[cfg(feature = "quick-js")]
+    {
+        // An empty file makes the engine evaluate the source instead.
+        let bytecode = bundle_bytecode().unwrap_or_else(|e| {
+            println!("cargo:warning=failed to precompile the KaTeX bundle: {e}");
+            Vec::new()
+        });
+        fs::write(Path::new(&out_dir).join("bundle.qjsbc"), bytecode).unwrap();
+    }
+}
+
+/// The bundle `JS_SRC` compiled to QuickJS bytecode.
+///
+/// rquickjs only (de)serializes ES modules, so the scripts making up the
+/// bundle are evaluated by a module, in a function called with the global
+/// object as `this` for the UMD header of KaTeX to find it. `entry.js`
+/// publishes what has to be global explicitly, as top-level declarations of
+/// a module are not.
+#[cfg(feature = "quick-js")]
+fn bundle_bytecode() -> Result<Vec<u8>, rquickjs::Error> {
+    // Keep in sync with `JS_SRC` in `src/lib.rs`.
+    let mut paths = vec!["js/node-hack.js", "vendor/katex/katex.min.js"];
+    if cfg!(feature = "temml") {
+        paths.push("vendor/temml/dist/temml.min.js");
+    }
+    paths.extend(["js/post-node-hack.js", "js/entry.js"]);
+
+    let mut src = String::from("(function () {\n");
+    for path in paths {
+        src.push_str(&fs::read_to_string(path).unwrap());
+        src.push('\n');
+    }
+    src.push_str("}).call(globalThis);\n");
+
+    // The build script runs on the host, which may not share the byte order
+    // of the target.
+    let host_endian = if cfg!(target_endian = "little") {
+        "little"
+    } else {
+        "big"
+    };
+    let swap_endianness = env::var("CARGO_CFG_TARGET_ENDIAN").unwrap() != host_endian;
+
+    let runtime = rquickjs::Runtime::new()?;
+    let context = rquickjs::Context::full(&runtime)?;
+    context.with(|ctx| rquickjs::Module::declare(ctx, "katex", src)?.write(swap_endianness))
+}
+
+/// Names of the macros defined through `__defineMacro("\\name", ..)` in the
+/// given contrib script, skipping commented out lines.
+fn defined_macros(src: &str) -> Vec<String> {
+    const PREFIX: &str = "__defineMacro(\"";
+    let mut commands: Vec<String> = src
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .filter_map(|line| {
+            let start = line.find(PREFIX)? + PREFIX.len();
+            let end = start + line[start..].find('"')?;
+            Some(line[start..end].replace(r"\\", r"\"))
+        })
+        .collect();
+    commands.sort();
+    commands.dedup();
+    commands
 }
@@ -10,5 +10,5 @@ URL="https://github.com/KaTeX/KaTeX/releases/download/v${VERSION}/katex.tar.gz"
 mkdir -p "$VENDOR_DIR"
 echo "download ${URL}..."
 curl -L https://raw.githubusercontent.com/KaTeX/KaTeX/master/LICENSE -o "$VENDOR_DIR/KATEX-LICENSE"
-curl -L "$URL" | tar -x -z -C "$VENDOR_DIR" --strip-components 1 -f - katex/katex.min.js katex/contrib/mhchem.min.js
+curl -L "$URL" | tar -x -z -C "$VENDOR_DIR" --strip-components 1 -f - katex/katex.min.js katex/katex.min.css katex/contrib/mhchem.min.js
 
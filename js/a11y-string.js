@@ -0,0 +1,205 @@
+// Spoken descriptions of math, in the style of KaTeX's `render-a11y-string`
+// contrib (e.g. "start fraction, a, divided by, b, end fraction"). Built on
+// the parse tree returned by `katex.__parse`. Node types without a dedicated
+// phrase are described through their children.
+(function () {
+    var symbols = {
+        "+": "plus",
+        "-": "minus",
+        "−": "minus",
+        "=": "equals",
+        "<": "less than",
+        ">": "greater than",
+        "\\lt": "less than",
+        "\\gt": "greater than",
+        "\\le": "less than or equal to",
+        "\\leq": "less than or equal to",
+        "\\ge": "greater than or equal to",
+        "\\geq": "greater than or equal to",
+        "\\ne": "not equal to",
+        "\\neq": "not equal to",
+        "\\approx": "approximately equal to",
+        "\\equiv": "equivalent to",
+        "\\sim": "similar to",
+        "\\times": "times",
+        "\\cdot": "dot",
+        "\\div": "divided by",
+        "\\pm": "plus or minus",
+        "\\mp": "minus or plus",
+        "\\infty": "infinity",
+        "\\to": "right arrow",
+        "\\rightarrow": "right arrow",
+        "\\leftarrow": "left arrow",
+        "\\Rightarrow": "implies",
+        "\\iff": "if and only if",
+        "\\in": "in",
+        "\\notin": "not in",
+        "\\subset": "subset of",
+        "\\cup": "union",
+        "\\cap": "intersection",
+        "\\partial": "partial",
+        "\\nabla": "nabla",
+        "\\ldots": "dots",
+        "\\cdots": "dots",
+        "\\dots": "dots",
+        ",": "comma",
+        ".": "point",
+        ";": "semicolon",
+        "!": "factorial",
+        "'": "prime",
+        "\\prime": "prime",
+        "/": "slash",
+        "|": "vertical bar",
+        "\\vert": "vertical bar",
+        "\\|": "double vertical bar",
+        "(": "left parenthesis",
+        ")": "right parenthesis",
+        "[": "open bracket",
+        "]": "close bracket",
+        "\\{": "left brace",
+        "\\}": "right brace",
+        "\\lbrace": "left brace",
+        "\\rbrace": "right brace",
+        "\\langle": "left angle bracket",
+        "\\rangle": "right angle bracket",
+        "\\sum": "sum",
+        "\\prod": "product",
+        "\\int": "integral",
+        "\\iint": "double integral",
+        "\\iiint": "triple integral",
+        "\\oint": "contour integral",
+        "\\lim": "limit"
+    };
+
+    var powers = { "2": "squared", "3": "cubed" };
+
+    // The phrase for a symbol or command name.
+    function word(text) {
+        if (Object.prototype.hasOwnProperty.call(symbols, text)) {
+            return symbols[text];
+        }
+        return text.charAt(0) === "\\" ? text.slice(1) : text;
+    }
+
+    // The characters of a text run, joined into words.
+    function plainText(nodes) {
+        var out = "";
+        for (var i = 0; i < nodes.length; i++) {
+            var node = nodes[i];
+            if (node.type === "spacing") {
+                out += " ";
+            } else if (typeof node.text === "string") {
+                out += node.text;
+            } else if (node.body) {
+                out += plainText([].concat(node.body));
+            }
+        }
+        return out.replace(/\s+/g, " ").trim();
+    }
+
+    function describeGroup(phrases, start, node, end) {
+        phrases.push(start);
+        describe(node, phrases);
+        phrases.push(end);
+    }
+
+    function describe(node, phrases) {
+        if (node === null || node === undefined) {
+            return;
+        }
+        if (Array.isArray(node)) {
+            for (var i = 0; i < node.length; i++) {
+                describe(node[i], phrases);
+            }
+            return;
+        }
+        switch (node.type) {
+        case "mathord":
+        case "textord":
+        case "atom":
+            phrases.push(word(node.text));
+            break;
+        case "op":
+            phrases.push(node.body ? plainText(node.body) : word(node.name));
+            break;
+        case "operatorname":
+            phrases.push(plainText(node.body));
+            break;
+        case "genfrac":
+            if (node.hasBarLine) {
+                describeGroup(phrases, "start fraction", node.numer, "divided by");
+                describe(node.denom, phrases);
+                phrases.push("end fraction");
+            } else {
+                describeGroup(phrases, "start binomial", node.numer, "over");
+                describe(node.denom, phrases);
+                phrases.push("end binomial");
+            }
+            break;
+        case "sqrt":
+            if (node.index) {
+                describeGroup(phrases, "root, start index", node.index, "end index");
+                describeGroup(phrases, "of", node.body, "end root");
+            } else {
+                describeGroup(phrases, "square root of", node.body, "end square root");
+            }
+            break;
+        case "supsub":
+            describe(node.base, phrases);
+            if (node.sub) {
+                describeGroup(phrases, "start subscript", node.sub, "end subscript");
+            }
+            if (node.sup) {
+                var power = plainText([node.sup]);
+                if (Object.prototype.hasOwnProperty.call(powers, power)) {
+                    phrases.push(powers[power]);
+                } else {
+                    describeGroup(phrases, "start superscript", node.sup, "end superscript");
+                }
+            }
+            break;
+        case "leftright":
+            if (node.left !== ".") {
+                phrases.push(word(node.left));
+            }
+            describe(node.body, phrases);
+            if (node.right !== ".") {
+                phrases.push(word(node.right));
+            }
+            break;
+        case "middle":
+            phrases.push(word(node.delim));
+            break;
+        case "text":
+            phrases.push("start text", plainText(node.body), "end text");
+            break;
+        case "accent":
+            describe(node.base, phrases);
+            phrases.push("with " + word(node.label) + " on top");
+            break;
+        case "array":
+            phrases.push("start matrix");
+            for (var row = 0; row < node.body.length; row++) {
+                describeGroup(phrases, "start row", node.body[row], "end row");
+            }
+            phrases.push("end matrix");
+            break;
+        case "htmlmathml":
+            describe(node.mathml, phrases);
+            break;
+        case "spacing":
+        case "kern":
+            break;
+        default:
+            describe(node.body || node.base, phrases);
+        }
+    }
+
+    global.katexRenderA11yString = __katexRecordErrors(function (input, options) {
+        var phrases = [];
+        describe(katex.__parse(input, options), phrases);
+        return phrases.filter(function (phrase) {
+            return phrase !== "";
+        }).join(", ");
+    });
+})();
@@ -4,8 +4,274 @@ try {
 } catch (e) {
     global = window;
 }
-global.katexRenderToString = katex.renderToString;
 
-if (global.temml) {
-    global.temmlRenderToString = temml.renderToString;
+// The error thrown by the last render call, inspected from Rust through
+// `katexErrorInfo` to classify failures.
+var __katexLastError = null;
+
+// Trust function forwarding to the Rust callback registered as
+// `katexTrustCallback`, used when the options set `trustCallback`.
+function __katexTrust(context) {
+    var optional = function (value) {
+        return value === undefined || value === null ? "" : String(value);
+    };
+    return katexTrustCallback(
+        String(context.command),
+        optional(context.url),
+        optional(context.protocol)
+    ) === "true";
+}
+
+// Trust function accepting only the listed `commands` (if an array), and
+// for commands with a URL only those with one of `protocols` (if an array).
+// Other commands without URL are accepted if `trustOthers` is set.
+function __katexTrustList(commands, protocols, trustOthers) {
+    return function (context) {
+        if (commands && commands.indexOf(context.command) === -1) {
+            return false;
+        }
+        if (context.url === undefined || !protocols) {
+            return commands ? true : trustOthers;
+        }
+        return protocols.indexOf(context.protocol) !== -1;
+    };
+}
+
+// Which strict-mode warnings are collected, see `katexCollectWarnings`.
+var __katexCollectWarnings = "";
+
+// The strict-mode warnings of the last render call, if collected.
+var __katexWarnings = [];
+
+// Strict handler collecting warnings instead of logging them.
+function __katexWarn(errorCode, errorMsg) {
+    __katexWarnings.push({ code: errorCode, message: errorMsg });
+    return "ignore";
 }
+
+// Macro making the command `name` fail with a parse error of `lib`.
+function __katexDisabledCommand(lib, name) {
+    return function () {
+        throw new lib.ParseError(name + " is disabled");
+    };
+}
+
+// `lib` is the library `render` belongs to, KaTeX if omitted.
+function __katexRecordErrors(render, reportsWarnings, lib) {
+    return function (input, options) {
+        __katexLastError = null;
+        __katexWarnings = [];
+        if (reportsWarnings && options && (__katexCollectWarnings === "all" ||
+            (__katexCollectWarnings === "warn" &&
+             (options.strict === undefined || options.strict === "warn")))) {
+            options.strict = __katexWarn;
+        }
+        if (options && options.trustCallback === true) {
+            options.trust = __katexTrust;
+        } else if (options && (Array.isArray(options.trustedCommands) ||
+                               Array.isArray(options.allowedProtocols))) {
+            options.trust = __katexTrustList(
+                options.trustedCommands,
+                options.allowedProtocols,
+                options.trust === true
+            );
+        }
+        if (options && !options.macros) {
+            options.macros = {};
+        }
+        if (options && options.disableMhchem) {
+            ["\\ce", "\\pu"].forEach(function (name) {
+                if (!Object.prototype.hasOwnProperty.call(options.macros, name)) {
+                    options.macros[name] = __katexDisabledCommand(lib || katex, name);
+                }
+            });
+        }
+        __katexLastMacros = options ? options.macros : null;
+        try {
+            return render(input, options);
+        } catch (e) {
+            __katexLastError = e;
+            throw e;
+        }
+    };
+}
+
+// The `macros` object of the last render call, which KaTeX updates with
+// global definitions (`\gdef`, or any with `globalGroup`).
+var __katexLastMacros = null;
+
+// The source of a macro definition, or null if it cannot be expressed as a
+// string macro (functions, delimited parameters).
+function __katexMacroSource(value) {
+    if (typeof value === "string") {
+        return value;
+    }
+    if (!value || typeof value !== "object" || !Array.isArray(value.tokens)) {
+        return null;
+    }
+    // `\def` records the tokens expected before each parameter.
+    var delimiters = value.delimiters || [];
+    for (var d = 0; d < delimiters.length; d++) {
+        if (delimiters[d].length > 0) {
+            return null;
+        }
+    }
+    // Tokens are stored in reverse order, and without the spaces ending
+    // control words.
+    var source = "";
+    var previous = "";
+    for (var i = value.tokens.length - 1; i >= 0; i--) {
+        var text = value.tokens[i].text;
+        if (/^\\[a-zA-Z@]+$/.test(previous) && /^[a-zA-Z@]/.test(text)) {
+            source += " ";
+        }
+        source += text;
+        previous = text;
+    }
+    // KaTeX infers the parameters of string macros from `#1`, `#2`, ...
+    var stripped = source.replace(/##/g, "");
+    var inferred = 0;
+    while (stripped.indexOf("#" + (inferred + 1)) !== -1) {
+        inferred++;
+    }
+    if (inferred < value.numArgs) {
+        var consumed = "";
+        for (var n = 1; n <= value.numArgs; n++) {
+            consumed += "#" + n;
+        }
+        source = "\\@firstoftwo{" + source + "}{" + consumed + "}";
+    }
+    return source;
+}
+
+// The `key` ("name" or "source") of the `index`-th macro of the last render
+// call which can be expressed as a string, "" past the end.
+global.katexLastMacro = function (index, key) {
+    var macros = __katexLastMacros || {};
+    var found = 0;
+    for (var name in macros) {
+        if (Object.prototype.hasOwnProperty.call(macros, name)) {
+            var source = __katexMacroSource(macros[name]);
+            if (source !== null && found++ === index) {
+                return key === "name" ? name : source;
+            }
+        }
+    }
+    return "";
+};
+
+global.katexErrorInfo = function (key) {
+    var e = __katexLastError;
+    if (e === null || typeof e !== "object" || e[key] === undefined || e[key] === null) {
+        return "";
+    }
+    return String(e[key]);
+};
+
+global.katexRenderToString = __katexRecordErrors(katex.renderToString, true);
+
+// The `<math>` element of the last `katexRenderSplit` call, "" if none.
+var __katexLastMathml = "";
+
+// Render the HTML and MathML halves of the output separately: returns the
+// `katex-html` element, and stores the `<math>` element for
+// `katexLastMathml`. Errors rendered inline are returned whole.
+global.katexRenderSplit = __katexRecordErrors(function (input, options) {
+    __katexLastMathml = "";
+    var tree = katex.__renderToDomTree(input, options);
+    var root = tree.hasClass("katex-display") ? tree.children[0] : tree;
+    var html = null;
+    for (var i = 0; root.hasClass("katex") && i < root.children.length; i++) {
+        var child = root.children[i];
+        if (child.hasClass("katex-mathml")) {
+            __katexLastMathml = child.children[0].toMarkup();
+        } else if (child.hasClass("katex-html")) {
+            html = child.toMarkup();
+        }
+    }
+    return html === null ? tree.toMarkup() : html;
+}, true);
+
+global.katexLastMathml = function () {
+    return __katexLastMathml;
+};
+
+// Render only the HTML half of the output, whatever `options.output`: the
+// markup of the tree built by `__renderToHTMLTree`, which `renderToString`
+// builds itself for `output: "html"`.
+global.katexRenderHtmlTree = __katexRecordErrors(function (input, options) {
+    return katex.__renderToHTMLTree(input, options).toMarkup();
+}, true);
+
+// Source locations refer to the lexer, which is not worth serializing.
+function __katexTreeReplacer(key, value) {
+    if (key === "loc" && value && typeof value === "object") {
+        return { start: value.start, end: value.end };
+    }
+    return value;
+}
+
+global.katexParseTree = __katexRecordErrors(function (input, options) {
+    return JSON.stringify(katex.__parse(input, options), __katexTreeReplacer);
+});
+
+// The number of rows of the outermost row-based environments (`aligned`,
+// `cases`, matrices, ...) in parse `nodes`.
+function __katexArrayRows(nodes) {
+    var rows = 0;
+    for (var key in nodes) {
+        var node = nodes[key];
+        if (key === "loc" || node === null || typeof node !== "object") {
+            continue;
+        }
+        rows += node.type === "array" ? node.body.length : __katexArrayRows(node);
+    }
+    return rows;
+}
+
+global.katexArrayRows = __katexRecordErrors(function (input, options) {
+    return String(__katexArrayRows(katex.__parse(input, options)));
+});
+
+global.katexValidate = __katexRecordErrors(function (input, options) {
+    katex.__parse(input, options);
+    return "";
+});
+
+// `mode` is "" to log warnings as configured, "warn" to collect them when
+// `strict` is unset or "warn", or "all" to collect them whatever `strict`.
+global.katexCollectWarnings = function (mode) {
+    __katexCollectWarnings = mode;
+    return "";
+};
+
+// The `key` ("code" or "message") of a collected warning, "" past the end.
+global.katexWarning = function (index, key) {
+    return index < __katexWarnings.length ? String(__katexWarnings[index][key]) : "";
+};
+
+global.katexVersion = function () {
+    return katex.version;
+};
+
+if (typeof temml !== "undefined") {
+    global.temml = temml;
+    global.temmlRenderToString = __katexRecordErrors(temml.renderToString, false, temml);
+}
+
+// Extensions loaded into this engine, see `src/extensions.rs`.
+var __katexExtensions = {};
+
+global.katexHasExtension = function (name) {
+    return __katexExtensions[name] === true ? "true" : "false";
+};
+
+global.katexAddExtension = function (name) {
+    __katexExtensions[name] = true;
+    return name;
+};
+
+// Used by the scripts of extensions, which are evaluated on their own: the
+// declarations above are not global if the bundle is loaded as a module.
+global.global = global;
+global.__katexRecordErrors = __katexRecordErrors;
@@ -0,0 +1 @@
+})(katex);
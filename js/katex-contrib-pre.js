@@ -0,0 +1,2 @@
+// Runs a Temml contrib script against KaTeX: both expose `__defineMacro`.
+(function (temml) {
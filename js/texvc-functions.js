@@ -0,0 +1,10 @@
+// Function names of MediaWiki's texvc which neither KaTeX nor Temml define
+// (the other texvc macros are built into KaTeX, and into Temml through its
+// `texvc` contrib). Written as a Temml contrib script so that it also runs
+// against KaTeX, see `katex-contrib-pre.js`.
+
+temml.__defineMacro("\\arccot", "\\operatorname{arccot}");
+temml.__defineMacro("\\arccsc", "\\operatorname{arccsc}");
+temml.__defineMacro("\\arcsec", "\\operatorname{arcsec}");
+temml.__defineMacro("\\sen", "\\operatorname{sen}");
+temml.__defineMacro("\\sgn", "\\operatorname{sgn}");
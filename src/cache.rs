@@ -0,0 +1,149 @@
+//! Memoization of rendered output.
+//!
+//! [`CachingRenderer`] keeps the output of recent renders keyed by input and
+//! [options](`Opts`), so documents repeating the same formulas only pay for
+//! rendering each of them once.
+
+use crate::{error::Result, opts::Opts, render_with_opts};
+use core::{
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+};
+use lru::LruCache;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+/// Hit and miss counts of a [`CachingRenderer`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Number of renders answered from the cache.
+    pub hits: u64,
+    /// Number of renders which had to go to the engine.
+    pub misses: u64,
+}
+
+/// Key of a cached render.
+struct CacheKey {
+    input: String,
+    opts: Opts,
+}
+
+impl Hash for CacheKey {
+    /// Hashes the input only, [`Opts`] is not `Hash`.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.input.hash(state);
+    }
+}
+
+impl PartialEq for CacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.input == other.input && self.opts == other.opts
+    }
+}
+
+/// Only options equal to themselves are cached, see
+/// [`CachingRenderer::render`].
+impl Eq for CacheKey {}
+
+/// A renderer memoizing the output of its most recently used inputs.
+///
+/// Rendering goes through the engine of the calling thread like
+/// [`render_with_opts`]; only the cache is shared. The renderer is `Send` and
+/// `Sync`, so one instance can serve all threads of e.g. a static-site
+/// generator, through a reference or an `Arc`.
+///
+/// # Examples
+///
+/// ```
+/// let renderer = katex::CachingRenderer::new(100);
+/// let opts = katex::Opts::default();
+/// let first = renderer.render("a + b", &opts).unwrap();
+/// let second = renderer.render("a + b", &opts).unwrap();
+/// assert_eq!(first, second);
+/// assert_eq!(renderer.stats().hits, 1);
+/// ```
+pub struct CachingRenderer {
+    cache: Mutex<LruCache<CacheKey, String>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachingRenderer {
+    /// Create a renderer keeping up to `capacity` (at least one) outputs,
+    /// evicting the least recently used one when full.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Render LaTeX equation to HTML with additional [options](`Opts`), or
+    /// return a clone of the output of an earlier identical render.
+    ///
+    /// Renders count as identical if both the input and the options are equal
+    /// (see [`Opts`] for how options compare). Errors are not cached, and
+    /// neither is output for options with a `NaN` field, which never compare
+    /// equal.
+    pub fn render(&self, input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+        let key = CacheKey {
+            input: input.to_owned(),
+            opts: opts.as_ref().clone(),
+        };
+        if let Some(html) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(html.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Render without holding the lock, so other threads are not blocked.
+        let html = render_with_opts(input, &key.opts)?;
+        // Options with `NaN` fields are unequal to themselves and must not be
+        // stored, as the cache relies on keys being equal to themselves.
+        if !key.opts.has_nan() {
+            self.lock().put(key, html.clone());
+        }
+        Ok(html)
+    }
+
+    /// Number of cache hits and misses so far.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of outputs currently cached.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no output is cached.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Drop all cached outputs, keeping the stats.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<CacheKey, String>> {
+        // The cache stays consistent even if a thread panicked holding it.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl core::fmt::Debug for CachingRenderer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CachingRenderer")
+            .field("len", &self.len())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
@@ -0,0 +1,161 @@
+//! CSS declarations for KaTeX markup.
+//!
+//! KaTeX markup relies on `katex.css`, which declares the fonts and styles
+//! the markup. The vendored copy is returned by [`stylesheet`]. Pages which
+//! already ship the styles (or a customized copy of them) only need the
+//! `@font-face` rules, generated here for the fonts of the vendored KaTeX
+//! version, which are listed by [`bundled_fonts`].
+
+use std::sync::OnceLock;
+
+/// The minified `katex.css` of the vendored KaTeX version.
+///
+/// The fonts are loaded from `fonts/` relative to the page, like from the
+/// KaTeX distribution; use [`font_faces_css_with_base`] to load them from
+/// elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// let css = katex::stylesheet();
+/// assert!(css.contains(".katex{"));
+/// ```
+pub fn stylesheet() -> &'static str {
+    include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/vendor/katex/katex.min.css"
+    ))
+}
+
+/// A font file shipped with KaTeX, see [`bundled_fonts`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FontInfo {
+    /// The `font-family`, e.g. `KaTeX_Main`.
+    pub family: &'static str,
+    /// The `font-weight`, `400` or `700`.
+    pub weight: &'static str,
+    /// The `font-style`, `normal` or `italic`.
+    pub style: &'static str,
+    /// The name of the `woff2` file, e.g. `KaTeX_Main-Regular.woff2`. The
+    /// `woff` and `ttf` files share its stem.
+    pub file: &'static str,
+}
+
+macro_rules! fonts {
+    ($(($family:literal, $variant:literal, $weight:literal, $style:literal)),+ $(,)?) => {
+        &[$(FontInfo {
+            family: $family,
+            weight: $weight,
+            style: $style,
+            file: concat!($family, "-", $variant, ".woff2"),
+        }),+]
+    };
+}
+
+/// The fonts of the vendored KaTeX version, as declared by its `katex.css`.
+const FONTS: &[FontInfo] = fonts![
+    ("KaTeX_AMS", "Regular", "400", "normal"),
+    ("KaTeX_Caligraphic", "Bold", "700", "normal"),
+    ("KaTeX_Caligraphic", "Regular", "400", "normal"),
+    ("KaTeX_Fraktur", "Bold", "700", "normal"),
+    ("KaTeX_Fraktur", "Regular", "400", "normal"),
+    ("KaTeX_Main", "Bold", "700", "normal"),
+    ("KaTeX_Main", "BoldItalic", "700", "italic"),
+    ("KaTeX_Main", "Italic", "400", "italic"),
+    ("KaTeX_Main", "Regular", "400", "normal"),
+    ("KaTeX_Math", "BoldItalic", "700", "italic"),
+    ("KaTeX_Math", "Italic", "400", "italic"),
+    ("KaTeX_SansSerif", "Bold", "700", "normal"),
+    ("KaTeX_SansSerif", "Italic", "400", "italic"),
+    ("KaTeX_SansSerif", "Regular", "400", "normal"),
+    ("KaTeX_Script", "Regular", "400", "normal"),
+    ("KaTeX_Size1", "Regular", "400", "normal"),
+    ("KaTeX_Size2", "Regular", "400", "normal"),
+    ("KaTeX_Size3", "Regular", "400", "normal"),
+    ("KaTeX_Size4", "Regular", "400", "normal"),
+    ("KaTeX_Typewriter", "Regular", "400", "normal"),
+];
+
+/// The font files of KaTeX, e.g. to emit `<link rel="preload">` tags for
+/// the ones a page needs.
+///
+/// # Examples
+///
+/// ```
+/// let preload: String = katex::bundled_fonts()
+///     .iter()
+///     .filter(|font| font.family == "KaTeX_Main")
+///     .map(|font| {
+///         format!(
+///             r#"<link rel="preload" href="/fonts/{}" as="font" type="font/woff2" crossorigin>"#,
+///             font.file
+///         )
+///     })
+///     .collect();
+/// assert!(preload.contains("/fonts/KaTeX_Main-Regular.woff2"));
+/// ```
+pub fn bundled_fonts() -> &'static [FontInfo] {
+    FONTS
+}
+
+/// The `@font-face` rules of `katex.css`, referring to the font files
+/// relative to the stylesheet as KaTeX does (`fonts/KaTeX_Main-Regular.woff2`,
+/// ...).
+///
+/// Use [`font_faces_css_with_base`] to load the files from elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// let css = katex::font_faces_css();
+/// assert!(css.contains("font-family:KaTeX_Main"));
+/// ```
+pub fn font_faces_css() -> &'static str {
+    static CSS: OnceLock<String> = OnceLock::new();
+    CSS.get_or_init(|| font_faces_css_with_base("fonts/"))
+}
+
+/// The `@font-face` rules of `katex.css`, loading the font files from
+/// `base_url`, e.g. a CDN or a local path.
+///
+/// The files keep the names KaTeX ships them with, e.g.
+/// `KaTeX_Main-Regular.woff2`, and are offered as `woff2`, `woff` and `ttf`.
+/// A `/` is added to a non-empty `base_url` not ending in one. Each rule sets
+/// `font-display: block` like KaTeX, so text waits for the fonts instead of
+/// showing with fallback glyphs and wrong metrics.
+///
+/// # Examples
+///
+/// ```
+/// let css = katex::font_faces_css_with_base("https://cdn.jsdelivr.net/npm/katex/dist/fonts");
+/// assert!(css.contains(r#"url("https://cdn.jsdelivr.net/npm/katex/dist/fonts/KaTeX_Main-Regular.woff2")"#));
+/// ```
+pub fn font_faces_css_with_base(base_url: &str) -> String {
+    let separator = if base_url.is_empty() || base_url.ends_with('/') {
+        ""
+    } else {
+        "/"
+    };
+    let base = format!("{base_url}{separator}")
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#);
+    let mut css = String::new();
+    for font in FONTS {
+        let stem = font.file.trim_end_matches(".woff2");
+        let url = |format: &str| format!(r#"url("{base}{stem}.{format}")"#);
+        css.push_str(&format!(
+            concat!(
+                "@font-face{{font-display:block;font-family:{};font-style:{};font-weight:{};",
+                r#"src:{} format("woff2"),{} format("woff"),{} format("truetype")}}"#,
+            ),
+            font.family,
+            font.style,
+            font.weight,
+            url("woff2"),
+            url("woff"),
+            url("ttf"),
+        ));
+    }
+    css
+}
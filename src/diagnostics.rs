@@ -0,0 +1,137 @@
+//! Lightweight diagnostics for editor integrations.
+//!
+//! [`first_error`] reports where rendering fails, without the markup, in a
+//! form suitable for underlining the offending part of the input.
+//! [`render_collecting_violations`] reports the input KaTeX accepts but
+//! LaTeX would not.
+
+use crate::{error::Result, error_info, opts::Opts, render_collecting, render_inner, with_engine};
+use core::fmt;
+
+/// Location and description of the first error in an input.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorLocation {
+    /// 1-based line of the error, if KaTeX reported a position.
+    pub line: Option<usize>,
+    /// 1-based column (in characters) of the error, if KaTeX reported a
+    /// position.
+    pub column: Option<usize>,
+    /// The error message, without KaTeX's position decoration.
+    pub message: String,
+    /// The name of the thrown JS error: `ParseError` for invalid input, or
+    /// e.g. `TypeError` for [internal errors](crate::Error::KatexInternal).
+    pub code: String,
+}
+
+/// Render LaTeX equation with `throw_on_error` enabled and report the first
+/// error, or `None` if the input renders fine.
+///
+/// Errors unrelated to the input, such as a failure to initialize the engine,
+/// are returned as `Err`. Positions refer to the input after pre-processing,
+/// so options rewriting the input (e.g. `big_inline_fractions`) may shift them.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::default();
+/// assert_eq!(katex::first_error("a + b", &opts).unwrap(), None);
+///
+/// let location = katex::first_error("a +\n b^", &opts).unwrap().unwrap();
+/// assert_eq!((location.line, location.column), (Some(2), Some(3)));
+/// assert_eq!(location.code, "ParseError");
+/// ```
+pub fn first_error(input: &str, opts: impl AsRef<Opts>) -> Result<Option<ErrorLocation>> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_throw_on_error(true);
+    with_engine(|engine| {
+        let error = match render_inner(engine, input, &opts) {
+            Ok(_) => return Ok(None),
+            Err(error) => error,
+        };
+        let code = error_info(engine, "name")?;
+        if code.is_empty() {
+            return Err(error);
+        }
+        let message = match error_info(engine, "rawMessage")? {
+            raw if raw.is_empty() => error_info(engine, "message")?,
+            raw => raw,
+        };
+        let (line, column) = error_info(engine, "position")?
+            .parse()
+            .ok()
+            .map(|position| line_column(input, position))
+            .unzip();
+        Ok(Some(ErrorLocation {
+            line,
+            column,
+            message,
+            code,
+        }))
+    })
+}
+
+/// Convert a position in UTF-16 code units, as reported by JS, into a 1-based
+/// line and column in characters.
+fn line_column(input: &str, position: usize) -> (usize, usize) {
+    let mut offset = 0;
+    let (mut line, mut column) = (1, 1);
+    for c in input.chars() {
+        if offset >= position {
+            break;
+        }
+        offset += c.len_utf16();
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+/// A construct KaTeX accepts although it is not valid LaTeX, reported by its
+/// [strict](crate::Opts::set_strict) mode.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    /// The KaTeX error code, e.g. `unicodeTextInMathMode`. See
+    /// <https://katex.org/docs/options.html> for the list.
+    pub code: String,
+    /// The description of the violation.
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+/// Render LaTeX equation to HTML, recording every strict-mode violation
+/// instead of warning about it or failing.
+///
+/// The [strictness](crate::Opts::set_strict) of `opts` is ignored for
+/// KaTeX: all violations are accepted, rendered as with
+/// [`Strictness::Ignore`](crate::opts::Strictness::Ignore), and returned in
+/// input order, so the caller can decide per [code](Violation::code) whether
+/// to fail. Temml reports no violations.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::builder()
+///     .strict(katex::opts::Strictness::Error)
+///     .build()
+///     .unwrap();
+/// let (html, violations) = katex::render_collecting_violations("é", &opts).unwrap();
+/// assert!(html.contains("katex"));
+/// assert_eq!(violations[0].code, "unicodeTextInMathMode");
+/// ```
+pub fn render_collecting_violations(
+    input: &str,
+    opts: impl AsRef<Opts>,
+) -> Result<(String, Vec<Violation>)> {
+    with_engine(|engine| render_collecting(engine, input, opts.as_ref(), "all"))
+}
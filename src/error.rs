@@ -11,7 +11,21 @@
 //! *value* conversion issues (usually a bug or unsupported type), or simply
 //! bubble them up with `?`.
 
+use std::sync::Arc;
+
+/// The original error reported by a JS backend, kept as the
+/// [`source`](std::error::Error::source) of an [`Error`].
+///
+/// To get at the backend's own error type, downcast the source to
+/// `BackendError` first, then the result to e.g. `rquickjs::Error`.
+pub type BackendError = Arc<dyn std::error::Error + Send + Sync>;
+
 /// Error type for this crate.
+///
+/// The `Js*Error` variants carry the original error of the backend as second
+/// field when it is available (currently with the `quick-js` backend), which
+/// is returned by [`source`](std::error::Error::source) so error reporters
+/// such as `anyhow` can show the whole chain.
 #[non_exhaustive]
 #[derive(thiserror::Error, Clone, Debug)]
 pub enum Error {
@@ -21,23 +35,161 @@ pub enum Error {
     /// setup errors, or platform limitations. Retrying is unlikely to succeed
     /// unless the underlying resource constraints change.
     #[error("failed to initialize js environment (detail: {0})")]
-    JsInitError(String),
+    JsInitError(String, #[source] Option<BackendError>),
     /// Failure reported while evaluating KaTeX / Temml code or executing a
     /// render call.
     ///
-    /// This encompasses both *logical* errors (such as KaTeX parse errors for
-    /// invalid LaTeX when `throw_on_error` is true) and *runtime* JS failures.
-    /// The string payload contains the (minified) message returned by the
-    /// underlying engine.
+    /// These are *runtime* JS failures, e.g. while loading the bundle or when
+    /// exceeding a [resource limit](crate::RuntimeConfig). Invalid
+    /// LaTeX is reported as [`Error::ParseError`] instead. The string payload
+    /// contains the (minified) message returned by the underlying engine.
     #[error("failed to execute js (detail: {0})")]
-    JsExecError(String),
+    JsExecError(String, #[source] Option<BackendError>),
+    /// KaTeX / Temml rejected the input as invalid LaTeX.
+    ///
+    /// Only returned when `throw_on_error` is true, otherwise the error is
+    /// rendered inline. Undefined commands are reported as
+    /// [`Error::UnsupportedCommand`] instead.
+    #[error("failed to parse input (detail: {raw})")]
+    ParseError {
+        /// The description of the error, e.g. `Expected group after '^'`.
+        message: String,
+        /// The offset of the offending token in the input, in UTF‑16 code
+        /// units as reported by JS. `None` if the error is not tied to a
+        /// token, e.g. at the end of the input for some errors.
+        ///
+        /// Options rewriting the input before rendering, such as
+        /// `big_inline_fractions`, may shift it.
+        position: Option<usize>,
+        /// The full message, as formatted by KaTeX, including the position
+        /// and an excerpt of the input around it.
+        raw: String,
+    },
+    /// KaTeX / Temml rejected the input because it uses a control sequence
+    /// they do not define, e.g. `\foobar`.
+    ///
+    /// A special case of [`Error::ParseError`], split off so applications can
+    /// react to it, e.g. by suggesting a [macro](crate::Opts::add_macro).
+    /// Only returned when `throw_on_error` is true.
+    #[error("unsupported command {command} (detail: {raw})")]
+    UnsupportedCommand {
+        /// The undefined control sequence, including the backslash.
+        command: String,
+        /// The offset of the command in the input, in UTF‑16 code units, as
+        /// for [`Error::ParseError`].
+        position: Option<usize>,
+        /// The full message, as formatted by KaTeX.
+        raw: String,
+    },
     /// Failure converting between host (Rust) values and JS values.
     ///
     /// Generally indicates a bug, unsupported type coercion, or encoding
     /// problem (e.g. invalid UTF‑8). These are not typically caused by user
     /// LaTeX input.
     #[error("failed to convert js value (detail: {0})")]
-    JsValueError(String),
+    JsValueError(String, #[source] Option<BackendError>),
+    /// The [options](crate::Opts) are invalid, e.g. an
+    /// [`OptsBuilder`](crate::OptsBuilder) failed to build.
+    #[error("invalid options (detail: {0})")]
+    InvalidOptions(String),
+    /// Failure writing rendered output to a caller supplied writer, see
+    /// [`render_to_writer`](crate::render_to_writer).
+    #[error("failed to write output")]
+    WriteError(#[source] core::fmt::Error),
+    /// Failure reading input from or writing output to a caller supplied
+    /// reader or writer, see [`render_lines`](crate::render_lines).
+    #[error("I/O error (detail: {0})")]
+    IoError(String, #[source] Arc<std::io::Error>),
+    /// KaTeX / Temml failed with an internal error instead of rejecting the
+    /// input.
+    ///
+    /// Raised when a render call throws a JS error whose `name` is anything
+    /// other than `ParseError` or the engine's `InternalError` (e.g.
+    /// `TypeError`), which usually points to a
+    /// KaTeX bug or an unsupported combination of inputs rather than invalid
+    /// LaTeX. Parse errors are reported as [`Error::ParseError`].
+    #[error("KaTeX internal error ({name}: {message})")]
+    KatexInternal {
+        /// The `name` of the thrown JS error.
+        name: String,
+        /// The `message` of the thrown JS error.
+        message: String,
+    },
+}
+
+impl Error {
+    /// The detail of the error, as included in its [`Display`](core::fmt::Display)
+    /// output, without formatting it.
+    ///
+    /// This is the message of the JS engine for the `Js*Error` variants, the
+    /// full message formatted by KaTeX for [`Error::ParseError`] and
+    /// [`Error::UnsupportedCommand`], and the `message` of the thrown error
+    /// for [`Error::KatexInternal`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder().throw_on_error(true).build().unwrap();
+    /// let error = katex::render_with_opts("x^", &opts).unwrap_err();
+    /// assert!(error.detail().starts_with("KaTeX parse error: "));
+    /// ```
+    pub fn detail(&self) -> &str {
+        match self {
+            Error::JsInitError(detail, _)
+            | Error::JsExecError(detail, _)
+            | Error::JsValueError(detail, _)
+            | Error::IoError(detail, _)
+            | Error::InvalidOptions(detail) => detail,
+            Error::ParseError { raw, .. } | Error::UnsupportedCommand { raw, .. } => raw,
+            Error::WriteError(_) => "an error occurred when formatting an argument",
+            Error::KatexInternal { message, .. } => message,
+        }
+    }
+
+    /// The description of the error without position, for
+    /// [`Error::ParseError`] and [`Error::KatexInternal`].
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Error::ParseError { message, .. } | Error::KatexInternal { message, .. } => {
+                Some(message)
+            }
+            _ => None,
+        }
+    }
+
+    /// The offset of the offending token in the input, in UTF‑16 code units,
+    /// for [`Error::ParseError`] and [`Error::UnsupportedCommand`] tied to a
+    /// token.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::ParseError { position, .. } | Error::UnsupportedCommand { position, .. } => {
+                *position
+            }
+            _ => None,
+        }
+    }
+
+    /// The undefined control sequence, for [`Error::UnsupportedCommand`].
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            Error::UnsupportedCommand { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+
+    /// The `name` of the thrown JS error, for [`Error::KatexInternal`].
+    pub fn js_error_name(&self) -> Option<&str> {
+        match self {
+            Error::KatexInternal { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::IoError(error.to_string(), Arc::new(error))
+    }
 }
 
 /// Convenient alias used throughout the crate.
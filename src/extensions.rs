@@ -0,0 +1,236 @@
+//! Optional KaTeX / Temml extensions (contrib scripts).
+//!
+//! Extensions are evaluated into the engine on top of the core bundle. By
+//! default all of them are loaded when an engine is initialized. With the
+//! `lazy-extensions` feature they are skipped at initialization instead, which
+//! lowers the cost of the first render on a thread, and loaded on demand:
+//!
+//! * automatically, right before rendering an input which uses one of the
+//!   commands an extension defines. The input (after pre-processing) and the
+//!   bodies of the custom macros are scanned for these control sequences, see
+//!   [`Extension::commands`]. Commands only reachable through other means,
+//!   e.g. built with `\csname`, are not detected;
+//! * explicitly, by calling [`enable_extension`].
+//!
+//! Loading an extension is a one-time cost per engine, paid by the render
+//! that first needs it.
+
+use crate::{error::Result, js_engine::JsEngine};
+#[cfg(feature = "lazy-extensions")]
+use crate::{macros::control_sequences, opts::Opts};
+use core::iter;
+
+#[cfg(any(feature = "temml", feature = "physics", feature = "texvc"))]
+include!(concat!(env!("OUT_DIR"), "/extension_commands.rs"));
+
+/// An optional extension of KaTeX / Temml.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Extension {
+    /// [mhchem](https://mhchem.github.io/MathJax-mhchem/): chemical equations
+    /// and physical units via `\ce` and `\pu`. Requires the `mhchem`
+    /// feature (enabled by default).
+    #[cfg(feature = "mhchem")]
+    Mhchem,
+    /// Temml's port of the LaTeX `physics` package (`\dv`, `\bra`, ...).
+    ///
+    /// Available to Temml with the `temml` feature, and to KaTeX with the
+    /// `physics` feature. It defines macros only, listed by
+    /// [`commands`](Extension::commands): delimiters (`\qty`, `\abs`,
+    /// `\norm`, ...), vector notation (`\vb`, `\grad`, `\curl`, ...),
+    /// derivatives (`\dd`, `\dv`, `\fdv`, ...), Dirac notation (`\ketbra`,
+    /// `\expval`, `\mel`, ...; `\bra` and `\ket` are built in), operators
+    /// (`\tr`, `\Res`, ...) and quick quad text (`\qq`, `\qif`, ...). Note
+    /// that `\div` becomes the divergence, while `\Re` and `\Im` keep their
+    /// usual meaning.
+    #[cfg(any(feature = "temml", feature = "physics"))]
+    Physics,
+    /// MediaWiki's `texvc` macros, for formulas written for Wikipedia.
+    ///
+    /// Available to Temml with the `temml` feature, and to KaTeX with the
+    /// `texvc` feature. KaTeX defines most `texvc` macros itself (`\R`,
+    /// `\Reals`, `\infin`, `\plusmn`, `\larr`, ...), which Temml gets
+    /// from its port of them. On top of these, the extension adds the
+    /// function names `\sgn`, `\arccot`, `\arcsec`, `\arccsc` and `\sen`,
+    /// which neither library defines. All of them are listed by
+    /// [`commands`](Extension::commands).
+    #[cfg(any(feature = "temml", feature = "texvc"))]
+    Texvc,
+}
+
+/// Source of an extension, wrapped in the same Node.js hack as the bundle.
+#[cfg_attr(
+    not(any(
+        feature = "mhchem",
+        feature = "temml",
+        feature = "physics",
+        feature = "texvc"
+    )),
+    allow(unused_macros)
+)]
+macro_rules! extension_source {
+    ($($path:literal),+ $(,)?) => {
+        concat!(
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/js/node-hack.js")),
+            $(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), $path)),)+
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/js/post-node-hack.js")),
+        )
+    };
+}
+
+impl Extension {
+    /// All extensions available in this build.
+    pub const ALL: &'static [Extension] = &[
+        #[cfg(feature = "mhchem")]
+        Extension::Mhchem,
+        #[cfg(any(feature = "temml", feature = "physics"))]
+        Extension::Physics,
+        #[cfg(any(feature = "temml", feature = "texvc"))]
+        Extension::Texvc,
+    ];
+
+    /// Short lowercase name of the extension.
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "mhchem")]
+            Extension::Mhchem => "mhchem",
+            #[cfg(any(feature = "temml", feature = "physics"))]
+            Extension::Physics => "physics",
+            #[cfg(any(feature = "temml", feature = "texvc"))]
+            Extension::Texvc => "texvc",
+        }
+    }
+
+    /// The commands defined by the extension, used to detect whether an input
+    /// needs it.
+    pub fn commands(self) -> &'static [&'static str] {
+        match self {
+            #[cfg(feature = "mhchem")]
+            Extension::Mhchem => &[r"\ce", r"\pu", r"\tripledash"],
+            #[cfg(any(feature = "temml", feature = "physics"))]
+            Extension::Physics => PHYSICS_COMMANDS,
+            #[cfg(any(feature = "temml", feature = "texvc"))]
+            Extension::Texvc => TEXVC_COMMANDS,
+        }
+    }
+
+    /// The JS code registering the extension.
+    fn source(self) -> &'static str {
+        match self {
+            #[cfg(all(feature = "mhchem", not(feature = "temml")))]
+            Extension::Mhchem => extension_source!("/vendor/katex/contrib/mhchem.min.js"),
+            #[cfg(all(feature = "mhchem", feature = "temml"))]
+            Extension::Mhchem => extension_source!(
+                "/vendor/katex/contrib/mhchem.min.js",
+                "/vendor/temml/contrib/mhchem/mhchem.min.js",
+            ),
+            #[cfg(all(feature = "temml", not(feature = "physics")))]
+            Extension::Physics => extension_source!("/vendor/temml/contrib/physics/physics.js"),
+            #[cfg(all(feature = "physics", not(feature = "temml")))]
+            Extension::Physics => extension_source!(
+                "/js/katex-contrib-pre.js",
+                "/vendor/temml/contrib/physics/physics.js",
+                "/js/katex-contrib-post.js",
+            ),
+            #[cfg(all(feature = "physics", feature = "temml"))]
+            Extension::Physics => extension_source!(
+                "/js/katex-contrib-pre.js",
+                "/vendor/temml/contrib/physics/physics.js",
+                "/js/katex-contrib-post.js",
+                "/vendor/temml/contrib/physics/physics.js",
+            ),
+            #[cfg(all(feature = "temml", not(feature = "texvc")))]
+            Extension::Texvc => extension_source!(
+                "/vendor/temml/contrib/texvc/texvc.js",
+                "/js/texvc-functions.js",
+            ),
+            #[cfg(all(feature = "texvc", not(feature = "temml")))]
+            Extension::Texvc => extension_source!(
+                "/js/katex-contrib-pre.js",
+                "/js/texvc-functions.js",
+                "/js/katex-contrib-post.js",
+            ),
+            #[cfg(all(feature = "texvc", feature = "temml"))]
+            Extension::Texvc => extension_source!(
+                "/js/katex-contrib-pre.js",
+                "/js/texvc-functions.js",
+                "/js/katex-contrib-post.js",
+                "/vendor/temml/contrib/texvc/texvc.js",
+                "/js/texvc-functions.js",
+            ),
+        }
+    }
+
+    /// Whether `input` (or one of the custom macros) uses a command of the
+    /// extension.
+    #[cfg(feature = "lazy-extensions")]
+    fn is_used(self, input: &str, opts: &Opts) -> bool {
+        let commands = self.commands();
+        iter::once(input)
+            .chain(opts.macro_definitions().values().map(String::as_str))
+            .flat_map(control_sequences)
+            .any(|cs| commands.contains(&cs))
+    }
+}
+
+/// Load `extension` into `engine`, unless it is already loaded.
+pub(crate) fn load<E>(engine: &E, extension: Extension) -> Result<()>
+where
+    E: JsEngine,
+{
+    load_script(engine, extension.name(), extension.source())
+}
+
+/// Evaluate `source` in `engine` under `name`, unless a script of that name
+/// is already loaded. Also used for internal helper scripts.
+pub(crate) fn load_script<E>(engine: &E, name: &str, source: &str) -> Result<()>
+where
+    E: JsEngine,
+{
+    let name_js = || engine.create_string_value(name.to_owned());
+    let loaded = engine.call_function("katexHasExtension", iter::once(name_js()?))?;
+    if engine.value_to_string(loaded)? != "true" {
+        engine.eval(source)?;
+        engine.call_function("katexAddExtension", iter::once(name_js()?))?;
+    }
+    Ok(())
+}
+
+/// Load every extension used by `input` into `engine`.
+#[cfg(feature = "lazy-extensions")]
+pub(crate) fn load_used<E>(engine: &E, input: &str, opts: &Opts) -> Result<()>
+where
+    E: JsEngine,
+{
+    for &extension in Extension::ALL {
+        #[cfg(feature = "mhchem")]
+        if extension == Extension::Mhchem && opts.is_mhchem_disabled() {
+            continue;
+        }
+        if extension.is_used(input, opts) {
+            load(engine, extension)?;
+        }
+    }
+    Ok(())
+}
+
+/// Load `extension` into the JS engine of the current thread.
+///
+/// Extensions are loaded at initialization unless the `lazy-extensions`
+/// feature is enabled, in which case this pays the one-time loading cost
+/// upfront instead of during the first render using the extension. Calling it
+/// for an extension which is already loaded is cheap.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "mhchem")]
+/// # {
+/// katex::enable_extension(katex::Extension::Mhchem).unwrap();
+/// let html = katex::render(r"\ce{H2O}").unwrap();
+/// assert!(html.contains("katex"));
+/// # }
+/// ```
+pub fn enable_extension(extension: Extension) -> Result<()> {
+    crate::with_engine(|engine| load(engine, extension))
+}
@@ -0,0 +1,99 @@
+//! Captioned equations.
+//!
+//! [`render_figure`] renders a display equation and wraps it together with a
+//! caption, the usual `<figure>` / `<figcaption>` pattern of documentation
+//! pages. The surrounding markup is described by a [`FigureTemplate`].
+
+use crate::{error::Result, opts::Opts, postprocess::escape_xml, render_with_opts};
+
+/// Markup wrapped around a captioned equation by [`render_figure_with_template`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FigureTemplate {
+    /// Element enclosing both the equation and its caption.
+    pub figure_tag: String,
+    /// Class attribute of the enclosing element, omitted if `None`.
+    pub figure_class: Option<String>,
+    /// Element enclosing the caption, placed after the equation.
+    pub caption_tag: String,
+    /// Class attribute of the caption element, omitted if `None`.
+    pub caption_class: Option<String>,
+}
+
+impl Default for FigureTemplate {
+    /// `<figure class="katex-figure">..<figcaption>..</figcaption></figure>`.
+    fn default() -> Self {
+        Self {
+            figure_tag: "figure".to_owned(),
+            figure_class: Some("katex-figure".to_owned()),
+            caption_tag: "figcaption".to_owned(),
+            caption_class: None,
+        }
+    }
+}
+
+impl FigureTemplate {
+    fn open_tag(out: &mut String, tag: &str, class: Option<&str>) {
+        out.push('<');
+        out.push_str(tag);
+        if let Some(class) = class {
+            out.push_str(" class=\"");
+            out.push_str(&escape_xml(class));
+            out.push('"');
+        }
+        out.push('>');
+    }
+
+    fn close_tag(out: &mut String, tag: &str) {
+        out.push_str("</");
+        out.push_str(tag);
+        out.push('>');
+    }
+}
+
+/// Render LaTeX equation in display mode followed by a caption, wrapped in
+/// the [default template](`FigureTemplate::default`).
+///
+/// `caption` is plain text and gets HTML-escaped.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_figure("E = mc^2", "Mass–energy equivalence", katex::Opts::default()).unwrap();
+/// assert!(html.starts_with(r#"<figure class="katex-figure"><span class="katex-display">"#));
+/// assert!(html.ends_with("<figcaption>Mass–energy equivalence</figcaption></figure>"));
+/// ```
+pub fn render_figure(input: &str, caption: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    render_figure_with_template(input, caption, &FigureTemplate::default(), opts)
+}
+
+/// Render LaTeX equation in display mode followed by a caption, wrapped in
+/// the markup described by `template`.
+///
+/// `caption` is plain text and gets HTML-escaped.
+pub fn render_figure_with_template(
+    input: &str,
+    caption: &str,
+    template: &FigureTemplate,
+    opts: impl AsRef<Opts>,
+) -> Result<String> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_display_mode(true);
+    let math = render_with_opts(input, opts)?;
+
+    let mut out = String::with_capacity(math.len() + caption.len() + 64);
+    FigureTemplate::open_tag(
+        &mut out,
+        &template.figure_tag,
+        template.figure_class.as_deref(),
+    );
+    out.push_str(&math);
+    FigureTemplate::open_tag(
+        &mut out,
+        &template.caption_tag,
+        template.caption_class.as_deref(),
+    );
+    out.push_str(&escape_xml(caption));
+    FigureTemplate::close_tag(&mut out, &template.caption_tag);
+    FigureTemplate::close_tag(&mut out, &template.figure_tag);
+    Ok(out)
+}
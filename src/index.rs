@@ -0,0 +1,55 @@
+//! Rendering for search indexing.
+//!
+//! [`render_indexed`] collects everything an indexer typically stores per
+//! formula from a single render call.
+
+use crate::{error::Result, macros::control_sequences, opts::Opts, postprocess, render_with_opts};
+
+/// A rendered formula together with metadata extracted from it.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexEntry {
+    /// The rendered markup, as returned by [`render_with_opts`].
+    pub html: String,
+    /// The `<math>` element of the markup.
+    ///
+    /// `None` when the [output type](crate::OutputType) has no MathML, i.e.
+    /// [`OutputType::Html`](crate::OutputType::Html).
+    pub mathml: Option<String>,
+    /// The LaTeX source, as passed to [`render_indexed`].
+    pub source: String,
+    /// The control words (e.g. `\frac`) appearing in the source, deduplicated
+    /// in order of first appearance. Control symbols such as `\{` are omitted.
+    pub commands: Vec<String>,
+}
+
+/// Render LaTeX equation and extract indexing metadata from the result.
+///
+/// Only one render call is made; the MathML is cut out of its output and the
+/// commands are scanned from the source, so the MathML is present for both
+/// `OutputType::Mathml` and `OutputType::HtmlAndMathml` (the default).
+///
+/// # Examples
+///
+/// ```
+/// let entry = katex::render_indexed(r"\frac{1}{\sqrt{x}}", katex::Opts::default()).unwrap();
+/// assert_eq!(entry.commands, [r"\frac", r"\sqrt"]);
+/// assert!(entry.mathml.unwrap().starts_with("<math"));
+/// ```
+pub fn render_indexed(input: &str, opts: impl AsRef<Opts>) -> Result<IndexEntry> {
+    let html = render_with_opts(input, opts)?;
+    let mathml = postprocess::math_element(&html).map(str::to_owned);
+    let mut commands: Vec<String> = Vec::new();
+    for command in control_sequences(input) {
+        let is_word = command[1..].starts_with(|c: char| c.is_ascii_alphabetic());
+        if is_word && !commands.iter().any(|c| c == command) {
+            commands.push(command.to_owned());
+        }
+    }
+    Ok(IndexEntry {
+        html,
+        mathml,
+        source: input.to_owned(),
+        commands,
+    })
+}
@@ -11,19 +11,74 @@
 //! * `duktape`
 //! * `wasm-js` (wasm targets only)
 //!
-//! This module is `pub(crate)` because the stability surface does not include
-//! custom user supplied engines. If you need alternative execution semantics,
-//! open an issue to discuss extending the abstraction.
+//! The module itself is private. With the `custom-engine` feature enabled the
+//! [`JsEngine`] trait and the default [`Engine`] are re-exported from the crate
+//! root so applications can own the engine lifecycle and render through
+//! `katex::render_on`. Without that feature the stability surface does not
+//! include custom user supplied engines.
 
 use crate::error::Result;
 use cfg_if::cfg_if;
 
+/// Resource limits of a JS engine, see [`Katex::with_runtime_config`].
+///
+/// Only the QuickJS backend (`quick-js` feature) supports them; the other
+/// backends ignore them.
+///
+/// [`Katex::with_runtime_config`]: crate::Katex::with_runtime_config
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RuntimeConfig {
+    /// Maximum number of bytes the engine may allocate, unlimited if `None`.
+    pub memory_limit: Option<usize>,
+    /// Maximum number of bytes of native stack the engine may use, QuickJS's
+    /// default of 1 MiB if `None`.
+    pub max_stack_size: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// Set the maximum number of bytes the engine may allocate.
+    ///
+    /// Loading the bundle alone takes a few megabytes, so the limit should
+    /// leave room for it on top of what rendering needs.
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Set the maximum number of bytes of native stack the engine may use.
+    ///
+    /// KaTeX parses and lays out nested groups recursively, so deeply nested
+    /// input (e.g. dozens of nested `\frac`, fewer in debug builds) exhausts
+    /// the default limit and fails with a `RangeError` reported as
+    /// [`Error::KatexInternal`](crate::Error::KatexInternal). Raising it lets
+    /// such input render, at the price of letting hostile input recurse
+    /// further before it is stopped.
+    ///
+    /// The stack is the one of the thread rendering, which is not covered by
+    /// the [memory limit](RuntimeConfig::memory_limit). The limit must stay
+    /// well below the size of that stack (2 MiB for threads spawned by `std`
+    /// unless configured otherwise, often 8 MiB for the main thread):
+    /// QuickJS only checks it, so a limit beyond the real stack overflows it
+    /// and aborts the process. Spawn rendering threads with a larger stack
+    /// through [`std::thread::Builder::stack_size`] to raise it safely.
+    pub fn max_stack_size(mut self, bytes: usize) -> Self {
+        self.max_stack_size = Some(bytes);
+        self
+    }
+}
+
+/// A Rust function callable from JS, see [`JsEngine::register_callback`].
+///
+/// It has to be `Send` as the `duktape` backend requires it of callbacks.
+pub type Callback = Box<dyn Fn(&[String]) -> String + Send>;
+
 /// Minimal interface a JS backend must implement.
 ///
 /// The trait deliberately avoids exposing lifetimes originating from backend
 /// internals except via the associated `JsValue` wrapper type to keep usage in
 /// the rest of the crate straightforward.
-pub(crate) trait JsEngine: Sized {
+pub trait JsEngine: Sized {
     /// The type of the JS value.
     type JsValue<'a>
     where
@@ -32,6 +87,24 @@ pub(crate) trait JsEngine: Sized {
     /// Construct a new engine instance ready to evaluate KaTeX bundles.
     fn new() -> Result<Self>;
 
+    /// Construct a new engine instance with the resource limits of `config`.
+    ///
+    /// Backends ignore the settings they do not support; by default all of
+    /// them are ignored and this is the same as [`JsEngine::new`].
+    fn with_config(config: &RuntimeConfig) -> Result<Self> {
+        let _ = config;
+        Self::new()
+    }
+
+    /// Evaluate the KaTeX bundle, whose source is `code`, in the engine.
+    ///
+    /// Backends may load the bundle of this crate faster than by parsing it,
+    /// e.g. from precompiled bytecode; by default `code` is
+    /// [evaluated](JsEngine::eval).
+    fn eval_bundle(&self, code: &str) -> Result<()> {
+        self.eval(code).map(drop)
+    }
+
     /// Evaluate arbitrary code in the engine (used once for bootstrapping).
     fn eval<'a>(&'a self, code: &str) -> Result<Self::JsValue<'a>>;
 
@@ -43,6 +116,14 @@ pub(crate) trait JsEngine: Sized {
         args: impl Iterator<Item = Self::JsValue<'a>>,
     ) -> Result<Self::JsValue<'a>>;
 
+    /// Create a JS `null` value.
+    ///
+    /// Note that KaTeX does not read `null` as "unset" for all options: e.g.
+    /// `maxSize: null` limits sizes to zero, so an unlimited
+    /// [`max_size`](crate::Opts::set_max_size) is sent by omitting the key.
+    #[cfg_attr(not(feature = "custom-engine"), allow(dead_code))]
+    fn create_null_value(&self) -> Result<Self::JsValue<'_>>;
+
     /// Create a new JS boolean value.
     fn create_bool_value(&self, input: bool) -> Result<Self::JsValue<'_>>;
 
@@ -61,6 +142,27 @@ pub(crate) trait JsEngine: Sized {
         input: impl Iterator<Item = (String, Self::JsValue<'a>)>,
     ) -> Result<Self::JsValue<'a>>;
 
+    /// Create a JS array holding `items` in order.
+    fn create_array_value<'a>(
+        &'a self,
+        items: impl Iterator<Item = Self::JsValue<'a>>,
+    ) -> Result<Self::JsValue<'a>>;
+
+    /// Define a global JS function `name` calling back into Rust.
+    ///
+    /// The arguments of the JS call are converted to strings and the string
+    /// returned by `callback` is returned to JS. Registering another function
+    /// with the same name replaces it. Backends without support for calling
+    /// back into Rust fail with [`Error::JsExecError`](crate::Error::JsExecError),
+    /// which is the default.
+    fn register_callback(&self, name: &str, callback: Callback) -> Result<()> {
+        let _ = callback;
+        Err(crate::error::Error::JsExecError(
+            format!("cannot register `{name}`: callbacks are not supported by this JS engine"),
+            None,
+        ))
+    }
+
     /// Convert a JS value to a UTF‑8 Rust `String`.
     fn value_to_string(&self, value: Self::JsValue<'_>) -> Result<String>;
 }
@@ -68,14 +170,25 @@ pub(crate) trait JsEngine: Sized {
 cfg_if! {
     if #[cfg(feature = "quick-js")] {
         mod quick_js;
+        pub(crate) use self::quick_js::{collect_garbage, with_deadline};
+        #[cfg(test)]
+        pub(crate) use self::quick_js::load_bundle;
 
-        pub(crate) type Engine = self::quick_js::Engine;
+        /// The JS engine selected by the enabled backend feature.
+        pub type Engine = self::quick_js::Engine;
+
+        /// The name of the backend feature of [`Engine`].
+        pub(crate) const BACKEND: &str = "quick-js";
     } else if #[cfg(feature = "duktape")] {
         cfg_if! {
             if #[cfg(any(unix, windows))] {
                 mod duktape;
 
-                pub(crate) type Engine = self::duktape::Engine;
+                /// The JS engine selected by the enabled backend feature.
+                pub type Engine = self::duktape::Engine;
+
+                /// The name of the backend feature of [`Engine`].
+                pub(crate) const BACKEND: &str = "duktape";
             } else {
                 compile_error!("duktape backend is not support in the current build target.");
             }
@@ -85,7 +198,11 @@ cfg_if! {
             if #[cfg(all(target_arch = "wasm32", target_os = "unknown"))] {
                 mod wasm_js;
 
-                pub(crate) type Engine = self::wasm_js::Engine;
+                /// The JS engine selected by the enabled backend feature.
+                pub type Engine = self::wasm_js::Engine;
+
+                /// The name of the backend feature of [`Engine`].
+                pub(crate) const BACKEND: &str = "wasm-js";
             } else {
                 compile_error!("wasm-js backend is not support in the current build target.");
             }
@@ -2,7 +2,7 @@
 
 use crate::{
     error::{Error, Result},
-    js_engine::JsEngine,
+    js_engine::{Callback, JsEngine},
 };
 use ducc::{FromValue, ToValue};
 
@@ -37,6 +37,10 @@ impl JsEngine for Engine {
         Ok(result)
     }
 
+    fn create_null_value(&self) -> Result<Self::JsValue<'_>> {
+        Ok(ducc::Value::Null)
+    }
+
     fn create_bool_value(&self, input: bool) -> Result<Self::JsValue<'_>> {
         Ok(input.to_value(&self.0)?)
     }
@@ -64,6 +68,30 @@ impl JsEngine for Engine {
         Ok(ducc::Value::Object(obj))
     }
 
+    fn create_array_value<'a>(
+        &'a self,
+        items: impl Iterator<Item = Self::JsValue<'a>>,
+    ) -> Result<Self::JsValue<'a>> {
+        let array = self.0.create_array();
+        for item in items {
+            array.push(item)?;
+        }
+        Ok(ducc::Value::Array(array))
+    }
+
+    fn register_callback(&self, name: &str, callback: Callback) -> Result<()> {
+        let function = self.0.create_function(move |invocation: ducc::Invocation| {
+            let args = invocation
+                .args
+                .into_iter()
+                .map(|arg| String::from_value(arg, invocation.ducc))
+                .collect::<ducc::Result<Vec<String>>>()?;
+            callback(&args).to_value(invocation.ducc)
+        });
+        self.0.globals().set(name.to_owned(), function)?;
+        Ok(())
+    }
+
     fn value_to_string(&self, value: Self::JsValue<'_>) -> Result<String> {
         Ok(String::from_value(value, &self.0)?)
     }
@@ -73,11 +101,12 @@ impl From<ducc::Error> for Error {
     fn from(e: ducc::Error) -> Self {
         use ducc::ErrorKind;
 
+        // `ducc::Error` is not `Send`, so it cannot be kept as source.
         match e.kind {
             ErrorKind::ToJsConversionError { .. } | ErrorKind::FromJsConversionError { .. } => {
-                Self::JsValueError(format!("{e}"))
+                Self::JsValueError(format!("{e}"), None)
             }
-            _ => Self::JsExecError(format!("{e}")),
+            _ => Self::JsExecError(format!("{e}"), None),
         }
     }
 }
@@ -1,15 +1,26 @@
 //! JS Engine implemented by [QuickJs](https://crates.io/crates/rquickjs).
+//!
+//! The bundle is loaded from QuickJS bytecode compiled by `build.rs`, which
+//! saves parsing it on every new engine. The source is evaluated instead if
+//! the bytecode is missing or cannot be loaded.
 
 use rquickjs::IteratorJs;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use crate::{
     error::{Error, Result},
-    js_engine::JsEngine,
+    js_engine::{Callback, JsEngine, RuntimeConfig},
 };
 
 /// Wrapper around a `rquickjs::Value` pinned for `'static` via a `Persistent`.
 #[derive(Debug)]
-pub(crate) struct Value(rquickjs::Persistent<rquickjs::Value<'static>>);
+pub struct Value(rquickjs::Persistent<rquickjs::Value<'static>>);
 
 /// QuickJS engine type alias (full context with standard objects loaded).
 pub type Engine = rquickjs::Context;
@@ -18,10 +29,27 @@ impl JsEngine for Engine {
     type JsValue<'a> = Value;
 
     fn new() -> Result<Self> {
+        Self::with_config(&RuntimeConfig::default())
+    }
+
+    fn with_config(config: &RuntimeConfig) -> Result<Self> {
         let runtime = rquickjs::Runtime::new()?;
+        if let Some(limit) = config.memory_limit {
+            runtime.set_memory_limit(limit);
+        }
+        if let Some(limit) = config.max_stack_size {
+            runtime.set_max_stack_size(limit);
+        }
         Ok(rquickjs::Context::full(&runtime)?)
     }
 
+    fn eval_bundle(&self, code: &str) -> Result<()> {
+        if code != crate::JS_SRC || !load_bundle(self)? {
+            self.eval(code)?;
+        }
+        Ok(())
+    }
+
     fn eval<'a>(&'a self, code: &str) -> Result<Self::JsValue<'a>> {
         self.with(|ctx| {
             Ok(Value(rquickjs::Persistent::<rquickjs::Value>::save(
@@ -49,6 +77,15 @@ impl JsEngine for Engine {
         })
     }
 
+    fn create_null_value(&self) -> Result<Self::JsValue<'_>> {
+        self.with(|ctx| {
+            let value = rquickjs::Value::new_null(ctx.clone());
+            Ok(Value(rquickjs::Persistent::<rquickjs::Value>::save(
+                &ctx, value,
+            )))
+        })
+    }
+
     fn create_bool_value(&self, input: bool) -> Result<Self::JsValue<'_>> {
         self.with(|ctx| {
             let value = rquickjs::Value::new_bool(ctx.clone(), input);
@@ -102,33 +139,117 @@ impl JsEngine for Engine {
         })
     }
 
+    fn create_array_value<'a>(
+        &'a self,
+        items: impl Iterator<Item = Self::JsValue<'a>>,
+    ) -> Result<Self::JsValue<'a>> {
+        let items: Vec<_> = items.collect(); // needed to avoid re-entrant borrow of `ctx`
+        self.with(|ctx| {
+            let array: rquickjs::Array = items
+                .into_iter()
+                .map(|val| val.0.restore(&ctx))
+                .collect_js(&ctx)?;
+            Ok(Value(rquickjs::Persistent::<rquickjs::Value>::save(
+                &ctx,
+                array.into_value(),
+            )))
+        })
+    }
+
+    fn register_callback(&self, name: &str, callback: Callback) -> Result<()> {
+        self.with(|ctx| {
+            let function = rquickjs::Function::new(
+                ctx.clone(),
+                move |args: rquickjs::function::Rest<rquickjs::convert::Coerced<String>>| {
+                    let args: Vec<String> = args.0.into_iter().map(|arg| arg.0).collect();
+                    callback(&args)
+                },
+            )?;
+            ctx.globals().set(name, function)?;
+            Ok(())
+        })
+    }
+
     fn value_to_string(&self, value: Self::JsValue<'_>) -> Result<String> {
         self.with(|ctx| {
             let v: rquickjs::Value = value.0.restore(&ctx)?;
             Ok(v.into_string()
-                .ok_or_else(|| Error::JsValueError("failed to convert value to string".to_owned()))?
+                .ok_or_else(|| {
+                    Error::JsValueError("failed to convert value to string".to_owned(), None)
+                })?
                 .to_string()?)
         })
     }
 }
 
-impl From<rquickjs::Error> for Error {
-    fn from(e: rquickjs::Error) -> Self {
-        (&e).into()
+/// `JS_SRC` compiled to the bytecode of a module by `build.rs`, empty if
+/// that failed.
+const BUNDLE_BYTECODE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/bundle.qjsbc"));
+
+/// Evaluate the bundle in `engine` from [`BUNDLE_BYTECODE`]. Returns whether
+/// it was loaded, `false` if the bytecode is missing or rejected by QuickJS.
+pub(crate) fn load_bundle(engine: &Engine) -> Result<bool> {
+    if BUNDLE_BYTECODE.is_empty() {
+        return Ok(false);
     }
+    engine.with(|ctx| {
+        // SAFETY: the bytecode was written by `Module::write` of the same
+        // rquickjs version, which Cargo resolves the build dependency to,
+        // for the byte order of the target.
+        #[allow(unsafe_code)]
+        let module = unsafe { rquickjs::Module::load(ctx, BUNDLE_BYTECODE) };
+        let Ok(module) = module else {
+            return Ok(false);
+        };
+        let (_, promise) = module.eval()?;
+        promise.finish::<()>()?;
+        Ok(true)
+    })
+}
+
+/// Run `f` with `engine` interrupting any JS code still running after
+/// `deadline`. Returns the result of `f` and whether JS code was actually
+/// interrupted, as opposed to merely finishing after the deadline.
+pub(crate) fn with_deadline<T>(
+    engine: &Engine,
+    deadline: Instant,
+    f: impl FnOnce() -> T,
+) -> (T, bool) {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let runtime = engine.runtime();
+    runtime.set_interrupt_handler(Some(Box::new({
+        let interrupted = Arc::clone(&interrupted);
+        move || {
+            let expired = Instant::now() >= deadline;
+            if expired {
+                interrupted.store(true, Ordering::Relaxed);
+            }
+            expired
+        }
+    })));
+    let result = f();
+    runtime.set_interrupt_handler(None);
+    (result, interrupted.load(Ordering::Relaxed))
 }
 
-impl From<&'_ rquickjs::Error> for Error {
-    fn from(e: &'_ rquickjs::Error) -> Self {
-        match e {
-            rquickjs::Error::Allocation => Error::JsInitError(e.to_string()),
+/// Run the garbage collector of the runtime of `engine`.
+pub(crate) fn collect_garbage(engine: &Engine) {
+    engine.runtime().run_gc();
+}
+
+impl From<rquickjs::Error> for Error {
+    fn from(e: rquickjs::Error) -> Self {
+        let message = e.to_string();
+        let variant = match e {
+            rquickjs::Error::Allocation => Error::JsInitError,
             rquickjs::Error::InvalidString(_)
             | rquickjs::Error::InvalidCStr(_)
             | rquickjs::Error::Utf8(_)
             | rquickjs::Error::FromJs { .. }
             | rquickjs::Error::IntoJs { .. }
-            | rquickjs::Error::AsSlice(_) => Error::JsValueError(e.to_string()),
-            _ => Error::JsExecError(e.to_string()),
-        }
+            | rquickjs::Error::AsSlice(_) => Error::JsValueError,
+            _ => Error::JsExecError,
+        };
+        variant(message, Some(Arc::new(e)))
     }
 }
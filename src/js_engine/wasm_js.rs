@@ -3,23 +3,34 @@
 
 use crate::{
     error::{Error, Result},
-    js_engine::JsEngine,
+    js_engine::{Callback, JsEngine},
 };
+use std::{cell::RefCell, collections::HashMap};
+use wasm_bindgen::closure::Closure;
+
+/// A registered callback, taking the JS arguments as an array of strings.
+type JsCallback = Closure<dyn Fn(js_sys::Array) -> String>;
 
 /// Wasm JS Engine (thin wrapper around global `JsValue` APIs).
-pub struct Engine;
+pub struct Engine {
+    /// The closures behind the functions defined by `register_callback`,
+    /// which have to live as long as the functions may be called.
+    callbacks: RefCell<HashMap<String, JsCallback>>,
+}
 
 impl JsEngine for Engine {
     type JsValue<'a> = Value;
 
     fn new() -> Result<Self> {
-        Ok(Self)
+        Ok(Self {
+            callbacks: RefCell::default(),
+        })
     }
 
     fn eval<'a>(&'a self, code: &str) -> Result<Self::JsValue<'a>> {
         js_sys::eval(code)
             .map(Value)
-            .map_err(|e| Error::JsExecError(format!("{e:?}")))
+            .map_err(|e| Error::JsExecError(format!("{e:?}"), None))
     }
 
     fn call_function<'a>(
@@ -28,16 +39,20 @@ impl JsEngine for Engine {
         args: impl Iterator<Item = Self::JsValue<'a>>,
     ) -> Result<Self::JsValue<'a>> {
         let function: js_sys::Function = js_sys::Reflect::get(&js_sys::global(), &func_name.into())
-            .map_err(|e| Error::JsExecError(format!("{e:?}")))?
+            .map_err(|e| Error::JsExecError(format!("{e:?}"), None))?
             .into();
 
         let args: js_sys::Array = args.map(|v| v.0).collect();
         let result = function
             .apply(&wasm_bindgen::JsValue::NULL, &args)
-            .map_err(|e| Error::JsExecError(format!("{e:?}")))?;
+            .map_err(|e| Error::JsExecError(format!("{e:?}"), None))?;
         Ok(Value(result))
     }
 
+    fn create_null_value(&self) -> Result<Self::JsValue<'_>> {
+        Ok(Value(wasm_bindgen::JsValue::NULL))
+    }
+
     fn create_bool_value(&self, input: bool) -> Result<Self::JsValue<'_>> {
         Ok(Value(input.into()))
     }
@@ -61,16 +76,47 @@ impl JsEngine for Engine {
         let obj = js_sys::Object::new();
         for (k, v) in input {
             js_sys::Reflect::set(&obj, &k.into(), &v.0)
-                .map_err(|e| Error::JsValueError(format!("{e:?}")))?;
+                .map_err(|e| Error::JsValueError(format!("{e:?}"), None))?;
         }
         Ok(Value(obj.into()))
     }
 
+    fn create_array_value<'a>(
+        &'a self,
+        items: impl Iterator<Item = Self::JsValue<'a>>,
+    ) -> Result<Self::JsValue<'a>> {
+        let array: js_sys::Array = items.map(|v| v.0).collect();
+        Ok(Value(array.into()))
+    }
+
+    fn register_callback(&self, name: &str, callback: Callback) -> Result<()> {
+        let closure = JsCallback::new(move |args: js_sys::Array| {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| arg.as_string().unwrap_or_default())
+                .collect();
+            callback(&args)
+        });
+        // Closures take a fixed number of arguments, so collect them first.
+        let wrap = js_sys::Function::new_with_args(
+            "f",
+            "return function () { return f(Array.prototype.map.call(arguments, String)); };",
+        );
+        let function = wrap
+            .call1(&wasm_bindgen::JsValue::NULL, closure.as_ref())
+            .map_err(|e| Error::JsExecError(format!("{e:?}"), None))?;
+        js_sys::Reflect::set(&js_sys::global(), &name.into(), &function)
+            .map_err(|e| Error::JsValueError(format!("{e:?}"), None))?;
+        // Replacing a callback drops the closure it was defined with.
+        self.callbacks.borrow_mut().insert(name.to_owned(), closure);
+        Ok(())
+    }
+
     fn value_to_string(&self, value: Self::JsValue<'_>) -> Result<String> {
         value
             .0
             .as_string()
-            .ok_or_else(|| Error::JsValueError("cannot convert value to string".to_owned()))
+            .ok_or_else(|| Error::JsValueError("cannot convert value to string".to_owned(), None))
     }
 }
 
@@ -50,25 +50,57 @@
 //! * `temml` – When combined with `OutputType::Mathml`, use the
 //!   [Temml](https://temml.org) library (KaTeX compatible) to produce concise
 //!   MathML output. Falls back to KaTeX for other output types.
+//! * `mhchem` *(default)* – Load the mhchem extension (`\ce`, `\pu`) for
+//!   chemical equations. Without it, these commands are undefined.
+//! * `speech` *(default)* – Include a spoken description in the result of
+//!   [`render_all`].
+//! * `physics` – Make the `Physics` [extension](Extension) (`\dv`, `\abs`,
+//!   `\qty`, ...) available to KaTeX, not only to Temml.
+//! * `texvc` – Make the `Texvc` [extension](Extension) (`\sgn`, `\arccot`,
+//!   ...) available to KaTeX, not only to Temml.
+//! * `lazy-extensions` – Do not load extensions such as mhchem when the
+//!   engine is initialized, but only once an input uses one of their commands
+//!   or [`enable_extension`] is called. See [`extensions`] for details.
+//! * `cache` – Add `CachingRenderer`, an LRU cache of rendered output keyed
+//!   by input and options.
+//! * `tokio` – Add `render_async`, rendering on a pool of worker threads
+//!   with warmed engines for use in async servers.
+//! * `serde` – Implement `Serialize` / `Deserialize` for [`Opts`] and the
+//!   types it refers to, using the option names of KaTeX.
+//! * `json` – Add `Opts::from_katex_json`, parsing options written as JSON
+//!   for KaTeX's JS API. Implies `serde`.
+//! * `custom-engine` – Make the `JsEngine` trait public and expose
+//!   `render_on` / `init_engine` so applications can own the engine
+//!   lifecycle (pooling, cross‑runtime sharing) instead of relying on the
+//!   thread‑local engine.
 //!
 //! ## Threading & caching
 //!
 //! A JavaScript engine instance is created lazily per thread and then reused
 //! for subsequent renders on that thread (using a thread‑local). This keeps
 //! rendering cheap after the first call. Each thread therefore maintains its
-//! own isolated JS context – there is no cross‑thread mutation.
+//! own isolated JS context – there is no cross‑thread mutation. Use
+//! [`warm_up`] to create it ahead of the first render and [`reset_engine`] to
+//! replace it by a fresh one.
+//!
+//! To control the lifetime of an engine instead, create a [`Katex`] renderer,
+//! which owns one for as long as it is kept alive. To bound the number of
+//! engines whatever the threads rendering, share a [`KatexPool`].
 //!
 //! ## Error handling
 //!
 //! All fallible APIs return [`Result<T, Error>`]. Distinct error variants
 //! differentiate between: engine initialisation, JavaScript execution, and
 //! value conversion issues. Parse errors from KaTeX itself surface as the
-//! `JsExecError` variant with a message produced by KaTeX.
+//! `ParseError` variant carrying the position of the error (or as
+//! `UnsupportedCommand` for undefined control sequences), while other errors
+//! thrown by KaTeX (typically bugs) surface as `KatexInternal`.
 //!
 //! ## Performance notes
 //!
 //! * The first render on a thread pays the cost of bootstrapping and loading
-//!   the (minified) JS bundle.
+//!   the (minified) JS bundle. With `quick-js` the bundle is precompiled to
+//!   bytecode at build time, which loads several times faster than the source.
 //! * Subsequent renders only invoke pure JS functions and are typically fast.
 //! * If you render in many short‑lived threads you will incur repeated init
 //!   overhead; prefer reusing threads (e.g. a thread pool) for batch work.
@@ -89,7 +121,8 @@
 //!
 //! ## Security
 //!
-//! If you accept untrusted LaTeX input consider:
+//! If you accept untrusted LaTeX input, [`render_untrusted`] applies a
+//! hardened policy in one call. Otherwise consider:
 //! * Set `throw_on_error(false)` to avoid throwing on invalid input.
 //! * Leave `trust(false)` (default) so potentially unsafe constructs (e.g. `\url{}`)
 //!   are sanitized.
@@ -105,24 +138,91 @@
 //! ---
 //! Happy typesetting! 🧮
 
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 #![deny(missing_docs)]
 
-use core::iter;
+use core::{cell::RefCell, fmt, iter};
 
 pub mod error;
-pub use error::{Error, Result};
+pub use error::{BackendError, Error, Result};
 
 pub mod opts;
 pub use opts::{Opts, OptsBuilder, OutputType};
 
+pub mod extensions;
+pub use extensions::{enable_extension, Extension};
+
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "cache")]
+pub use cache::{CacheStats, CachingRenderer};
+
+mod css;
+pub use css::{bundled_fonts, font_faces_css, font_faces_css_with_base, stylesheet, FontInfo};
+
+mod diagnostics;
+pub use diagnostics::{first_error, render_collecting_violations, ErrorLocation, Violation};
+
+mod figure;
+pub use figure::{render_figure, render_figure_with_template, FigureTemplate};
+
+mod index;
+pub use index::{render_indexed, IndexEntry};
+
+mod macros;
+pub use macros::{validate_macros, MacroIssue};
+
+mod meta;
+pub use meta::{render_with_metadata, RenderMeta};
+
+mod mixed;
+pub use mixed::{render_mixed, render_mixed_with_config, DelimiterConfig, MixedConfig};
+
+mod numbering;
+pub use numbering::{render_numbered, TagStyle};
+
+mod parse;
+pub use parse::{parse_tree, supports_command, validate};
+
+pub mod postprocess;
+mod preprocess;
+
+mod rendered;
+pub use rendered::{render_all, render_split, Rendered};
+
+mod renderer;
+pub use renderer::Katex;
+
+mod speech;
+pub use speech::render_speech;
+
+mod pool;
+pub use pool::KatexPool;
+
+mod worker;
+#[cfg(feature = "tokio")]
+pub use worker::render_async;
+
+mod util;
+pub use util::escape_latex;
+
 mod js_engine;
+pub use js_engine::RuntimeConfig;
+#[cfg(feature = "custom-engine")]
+pub use js_engine::{Callback, Engine, JsEngine};
+#[cfg(not(feature = "custom-engine"))]
 use js_engine::{Engine, JsEngine};
 
 /// KaTeX version.
+///
+/// Read from the `KATEX-VERSION` file at compile time, see [`runtime_version`]
+/// for the version of the bundle actually loaded.
 pub const KATEX_VERSION: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/KATEX-VERSION"));
 
 /// JS source code.
+///
+/// Extensions such as mhchem are not part of it, see [`extensions`]. With
+/// `quick-js`, `build.rs` also compiles the same files to bytecode.
 #[cfg(not(feature = "temml"))]
 const JS_SRC: &str = concat!(
     // HACK to load KaTeX code in Node.js
@@ -134,11 +234,6 @@ const JS_SRC: &str = concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/vendor/katex/katex.min.js"
     )),
-    // mhchem JS source code
-    include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/vendor/katex/contrib/mhchem.min.js"
-    )),
     // restore HACK done in node-hack.js
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/js/post-node-hack.js")),
     // entry function
@@ -156,27 +251,10 @@ const JS_SRC: &str = concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/vendor/katex/katex.min.js"
     )),
-    // mhchem JS source code
-    include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/vendor/katex/contrib/mhchem.min.js"
-    )),
     include_str!(concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/vendor/temml/dist/temml.min.js"
     )),
-    include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/vendor/temml/contrib/mhchem/mhchem.min.js"
-    )),
-    include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/vendor/temml/contrib/physics/physics.js"
-    )),
-    include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/vendor/temml/contrib/texvc/texvc.js"
-    )),
     // restore HACK done in node-hack.js
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/js/post-node-hack.js")),
     // entry function
@@ -184,8 +262,14 @@ const JS_SRC: &str = concat!(
 );
 
 thread_local! {
-    /// Per thread JS Engine used to render KaTeX.
-    static KATEX: Result<Engine> = init_katex();
+    /// Per thread JS Engine used to render KaTeX, see [`reset_engine`].
+    static KATEX: RefCell<Result<Engine>> = RefCell::new(init_katex());
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of times the bundle was evaluated on this thread.
+    static BUNDLE_LOADS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
 }
 
 /// Initialize KaTeX js environment.
@@ -193,11 +277,106 @@ fn init_katex<E>() -> Result<E>
 where
     E: JsEngine,
 {
-    let engine = E::new()?;
-    engine.eval(JS_SRC)?;
+    init_katex_with(&RuntimeConfig::default())
+}
+
+/// Initialize KaTeX js environment in an engine configured by `config`.
+fn init_katex_with<E>(config: &RuntimeConfig) -> Result<E>
+where
+    E: JsEngine,
+{
+    let engine = E::with_config(config)?;
+    engine.eval_bundle(JS_SRC)?;
+    #[cfg(test)]
+    BUNDLE_LOADS.with(|loads| loads.set(loads.get() + 1));
+    if !cfg!(feature = "lazy-extensions") {
+        for &extension in Extension::ALL {
+            extensions::load(&engine, extension)?;
+        }
+    }
     Ok(engine)
 }
 
+/// Call one of the render functions defined in `entry.js`, classifying the
+/// error it throws (if any).
+fn call_render<'a, E>(
+    engine: &'a E,
+    func_name: &str,
+    args: impl Iterator<Item = E::JsValue<'a>>,
+) -> Result<E::JsValue<'a>>
+where
+    E: JsEngine,
+{
+    engine
+        .call_function(func_name, args)
+        .map_err(|e| classify_error(engine, e))
+}
+
+/// Read property `key` of the error thrown by the last render call on
+/// `engine`, as a string. Empty if there was no error or it lacks `key`.
+fn error_info<E>(engine: &E, key: &str) -> Result<String>
+where
+    E: JsEngine,
+{
+    let key = engine.create_string_value(key.to_owned())?;
+    let value = engine.call_function("katexErrorInfo", iter::once(key))?;
+    engine.value_to_string(value)
+}
+
+/// Refine a failed render call using the error recorded by `entry.js`.
+///
+/// KaTeX / Temml report invalid input by throwing an error named
+/// `ParseError`, which is mapped to [`Error::ParseError`] (or
+/// [`Error::UnsupportedCommand`]). QuickJS reports
+/// exhausted resources (memory, stack) as an `InternalError`, which is mapped
+/// to [`Error::JsExecError`]. Any other named error is mapped to
+/// [`Error::KatexInternal`]. Errors which were not recorded (e.g. failures of
+/// the engine itself) are returned unchanged.
+fn classify_error<E>(engine: &E, error: Error) -> Error
+where
+    E: JsEngine,
+{
+    if !matches!(error, Error::JsExecError(..)) {
+        return error;
+    }
+    match (error_info(engine, "name"), error_info(engine, "message")) {
+        (Ok(name), Ok(raw)) if name == "ParseError" => parse_error(engine, raw).unwrap_or(error),
+        (Ok(name), Ok(message)) if name == "InternalError" => match error {
+            Error::JsExecError(_, source) => {
+                Error::JsExecError(format!("{name}: {message}"), source)
+            }
+            error => error,
+        },
+        (Ok(name), Ok(message)) if !name.is_empty() => Error::KatexInternal { name, message },
+        _ => error,
+    }
+}
+
+/// Build [`Error::ParseError`] from the recorded error, whose full message is
+/// `raw`, or [`Error::UnsupportedCommand`] for an undefined control sequence.
+fn parse_error<E>(engine: &E, raw: String) -> Result<Error>
+where
+    E: JsEngine,
+{
+    let message = match error_info(engine, "rawMessage")? {
+        message if message.is_empty() => raw.clone(),
+        message => message,
+    };
+    let position = error_info(engine, "position")?.parse().ok();
+    if let Some(command) = message.strip_prefix("Undefined control sequence: ") {
+        return Ok(Error::UnsupportedCommand {
+            command: command.to_owned(),
+            position,
+            raw,
+        });
+    }
+    Ok(Error::ParseError {
+        message,
+        position,
+        raw,
+    })
+}
+
 /// Render LaTeX equation to HTML using specified [engine](`JsEngine`) and [options](`Opts`).
 #[inline]
 fn render_inner<E>(engine: &E, input: &str, opts: impl AsRef<Opts>) -> Result<String>
@@ -205,31 +384,731 @@ where
     E: JsEngine,
 {
     let opts = opts.as_ref();
-    let input = engine.create_string_value(input.to_owned())?;
-    let opts_js = opts.to_js_value(engine)?;
-    let args = iter::once(input).chain(iter::once(opts_js));
-    let result = (if cfg!(feature = "temml") && opts.is_mathml_only() {
-        engine.call_function("temmlRenderToString", args)
+    let func_name = if cfg!(feature = "temml") && opts.is_mathml_only() {
+        "temmlRenderToString"
     } else {
-        engine.call_function("katexRenderToString", args)
-    })?;
+        "katexRenderToString"
+    };
+    let html = render_raw(engine, func_name, input, opts)?;
+    Ok(postprocess::apply(input, opts, html))
+}
+
+/// Call the JS render function `func_name` on the pre-processed `input`,
+/// without post-processing the result.
+fn render_raw<E>(engine: &E, func_name: &str, input: &str, opts: &Opts) -> Result<String>
+where
+    E: JsEngine,
+{
+    let tex = preprocess::apply(input, opts);
+    #[cfg(feature = "lazy-extensions")]
+    extensions::load_used(engine, &tex, opts)?;
+    let input_js = engine.create_string_value(tex.into_owned())?;
+    let opts_js = opts.to_js_value(engine, input)?;
+    let args = iter::once(input_js).chain(iter::once(opts_js));
+    let result = call_render(engine, func_name, args)?;
     engine.value_to_string(result)
 }
 
+/// Create a new [engine](`JsEngine`) with the KaTeX (and, if enabled, Temml)
+/// bundle loaded, ready to be passed to [`render_on`].
+#[cfg(feature = "custom-engine")]
+pub fn init_engine<E>() -> Result<E>
+where
+    E: JsEngine,
+{
+    init_katex()
+}
+
+/// Render LaTeX equation to HTML on a caller owned [engine](`JsEngine`) with
+/// the given [options](`Opts`).
+///
+/// This bypasses the per‑thread engine used by [`render_with_opts`] while
+/// reusing the same option marshaling and KaTeX / Temml dispatch, which makes
+/// it the building block for applications managing engines themselves.
+///
+/// The engine must already have the bundle loaded, i.e. it has to be created
+/// through [`init_engine`]; a bare `E::new()` has no render functions defined
+/// and every call fails with [`Error::JsExecError`]. JS values never outlive a
+/// single call, so the only lifetime requirement is the borrow of `engine`.
+/// Engines are generally neither `Send` nor `Sync`: keep each one on the
+/// thread that created it and do not render on it re‑entrantly.
+///
+/// # Examples
+///
+/// ```
+/// let engine: katex::Engine = katex::init_engine().unwrap();
+/// let opts = katex::Opts::default();
+/// let html = katex::render_on(&engine, "a + b", &opts).unwrap();
+/// assert!(html.contains("katex"));
+/// ```
+#[cfg(feature = "custom-engine")]
+pub fn render_on<E>(engine: &E, input: &str, opts: &Opts) -> Result<String>
+where
+    E: JsEngine,
+{
+    render_inner(engine, input, opts)
+}
+
+/// Run `f` with the JS engine of the current thread.
+fn with_engine<T>(f: impl FnOnce(&Engine) -> Result<T>) -> Result<T> {
+    KATEX.with(|engine| {
+        let engine = engine.borrow();
+        engine.as_ref().map_err(|e| e.clone()).and_then(f)
+    })
+}
+
+/// Replace the JS engine of the current thread by a fresh one.
+///
+/// Dropping the engine frees everything it accumulated, e.g. heap grown by
+/// macro-heavy renders in long-lived worker threads, and recovers from an
+/// engine left in a bad state. The new engine is initialized right away, so
+/// this pays the cost of loading the bundle again and returns any
+/// initialization error; later renders then reuse the new engine.
+///
+/// Fails with [`Error::JsInitError`] if called while the engine is in use,
+/// i.e. from within an [adaptive macro expansion policy](Opts::set_adaptive_max_expand).
+///
+/// # Examples
+///
+/// ```
+/// katex::reset_engine().unwrap();
+/// let html = katex::render("a + b").unwrap();
+/// ```
+pub fn reset_engine() -> Result<()> {
+    KATEX.with(|engine| {
+        let mut engine = engine.try_borrow_mut().map_err(|_| {
+            Error::JsInitError(
+                "cannot reset the engine while it is in use".to_owned(),
+                None,
+            )
+        })?;
+        // Drop the old engine before creating the new one.
+        *engine = Err(Error::JsInitError("engine is being reset".to_owned(), None));
+        *engine = init_katex();
+        engine.as_ref().map(|_| ()).map_err(|e| e.clone())
+    })
+}
+
+/// Initialize the JS engine of the current thread ahead of the first render.
+///
+/// Useful to pay the cost of loading the bundle at startup (e.g. in the worker
+/// threads of a server) rather than on the first request, and to surface
+/// initialization errors early. Calling it again is cheap and returns the
+/// same result.
+///
+/// # Examples
+///
+/// ```
+/// katex::warm_up().unwrap();
+/// let html = katex::render("a + b").unwrap();
+/// ```
+pub fn warm_up() -> Result<()> {
+    with_engine(|_| Ok(()))
+}
+
+/// Name of the JS backend this crate was compiled with, i.e. the enabled
+/// backend feature: `"quick-js"`, `"duktape"` or `"wasm-js"`.
+///
+/// When several backend features are enabled, the one in use is reported,
+/// with `quick-js` taking precedence over `duktape` and `wasm-js`. Meant for
+/// diagnostics such as bug reports.
+///
+/// # Examples
+///
+/// ```
+/// println!("katex {} on {}", katex::KATEX_VERSION.trim(), katex::backend_name());
+/// ```
+pub fn backend_name() -> &'static str {
+    js_engine::BACKEND
+}
+
+/// Version of the KaTeX bundle loaded into the JS engine of the current
+/// thread, i.e. `katex.version`.
+///
+/// Meant to check at startup that the vendored JS matches [`KATEX_VERSION`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(katex::runtime_version().unwrap(), katex::KATEX_VERSION.trim());
+/// ```
+pub fn runtime_version() -> Result<String> {
+    with_engine(|engine| {
+        let version = engine.call_function("katexVersion", iter::empty())?;
+        engine.value_to_string(version)
+    })
+}
+
 /// Render LaTeX equation to HTML with additional [options](`Opts`).
 pub fn render_with_opts(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
-    KATEX.with(|engine| {
-        engine
-            .as_ref()
-            .map_err(|e| e.clone())
-            .and_then(|engine| render_inner(engine, input, opts))
+    with_engine(|engine| render_inner(engine, input, opts))
+}
+
+/// Render LaTeX equation to HTML with the [options](`Opts`) of a builder,
+/// sparing the `.build().unwrap()` of an [`OptsBuilder`] still in use.
+///
+/// Every field of [`Opts`] has a default, so building never fails in
+/// practice; a failure would be reported as [`Error::InvalidOptions`].
+///
+/// # Examples
+///
+/// ```
+/// let mut builder = katex::Opts::builder();
+/// builder.display_mode(true);
+/// let html = katex::render_with_builder(r"\sum_i x_i", &builder).unwrap();
+/// assert!(html.contains("katex-display"));
+/// ```
+pub fn render_with_builder(input: &str, builder: &OptsBuilder) -> Result<String> {
+    let opts = builder
+        .build()
+        .map_err(|e| Error::InvalidOptions(e.to_string()))?;
+    render_with_opts(input, opts)
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`), keeping
+/// the global macro definitions it makes in `opts` for later renders.
+///
+/// Definitions made with `\gdef` (or `\global\def`), and with
+/// [`global_group`](`Opts::set_global_group`) any definition, are added to
+/// the [custom macros](`Opts::add_macro`) of `opts`, so a document can define
+/// macros in one equation and use them in the following ones. Definitions
+/// with delimited parameters (e.g. `\gdef\f#1.{..}`) cannot be expressed as
+/// custom macros and are dropped. If rendering fails, `opts` is left
+/// unchanged.
+///
+/// # Examples
+///
+/// ```
+/// let mut opts = katex::Opts::default();
+/// katex::render_stateful(r"\gdef\R{\mathbb{R}} x \in \R", &mut opts).unwrap();
+/// let html = katex::render_stateful(r"y \in \R", &mut opts).unwrap();
+/// assert!(html.contains("mathbb"));
+/// ```
+pub fn render_stateful(input: &str, opts: &mut Opts) -> Result<String> {
+    with_engine(|engine| {
+        let html = render_inner(engine, input, &*opts)?;
+        for (name, source) in last_macros(engine)? {
+            opts.add_macro(name, source);
+        }
+        Ok(html)
+    })
+}
+
+/// The macros after the last render call on `engine`, including the global
+/// definitions it made.
+fn last_macros<E>(engine: &E) -> Result<Vec<(String, String)>>
+where
+    E: JsEngine,
+{
+    let mut macros = Vec::new();
+    loop {
+        let field = |key: &str| {
+            let index = engine.create_int_value(macros.len() as i32)?;
+            let key = engine.create_string_value(key.to_owned())?;
+            let value = engine.call_function("katexLastMacro", [index, key].into_iter())?;
+            engine.value_to_string(value)
+        };
+        let name = field("name")?;
+        if name.is_empty() {
+            return Ok(macros);
+        }
+        macros.push((name, field("source")?));
+    }
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`), writing
+/// the result to `writer`.
+///
+/// Convenient for streaming many fragments into one buffer or a template
+/// engine's output. The markup is still converted from the engine's value
+/// into an intermediate `String` on this side, but it is not handed over to
+/// the caller, so no owned copy has to be kept around. Nothing is written if
+/// rendering fails; failures of `writer` are reported as
+/// [`Error::WriteError`].
+///
+/// # Examples
+///
+/// ```
+/// let mut page = String::from("<p>");
+/// katex::render_to_writer(&mut page, "a + b", katex::Opts::default()).unwrap();
+/// page.push_str("</p>");
+/// assert!(page.starts_with(r#"<p><span class="katex">"#));
+/// ```
+pub fn render_to_writer<W>(writer: &mut W, input: &str, opts: impl AsRef<Opts>) -> Result<()>
+where
+    W: fmt::Write + ?Sized,
+{
+    let html = render_with_opts(input, opts)?;
+    writer.write_str(&html).map_err(Error::WriteError)
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`), passing
+/// the result through `transform`.
+///
+/// `transform` receives the markup after all post-processing requested by
+/// `opts`, e.g. to add classes or rewrite URLs, and its result is returned.
+/// It is not called if rendering fails. It runs once the engine is released,
+/// so it may render other equations itself.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_with_transform("a + b", katex::Opts::default(), |html| {
+///     html.replacen(r#"class="katex""#, r#"class="katex formula""#, 1)
+/// })
+/// .unwrap();
+/// assert!(html.starts_with(r#"<span class="katex formula">"#));
+/// ```
+pub fn render_with_transform<F>(input: &str, opts: impl AsRef<Opts>, transform: F) -> Result<String>
+where
+    F: FnOnce(String) -> String,
+{
+    render_with_opts(input, opts).map(transform)
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`),
+/// preceded by the [`stylesheet`] in a `<style>` block.
+///
+/// The result displays correctly on its own, e.g. in an email or a single
+/// page document, as long as the fonts are reachable from `fonts/`. Pages
+/// rendering many equations should include the stylesheet once instead.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_standalone("a + b", katex::Opts::default()).unwrap();
+/// assert!(html.starts_with("<style>"));
+/// assert!(html.contains(r#"<span class="katex">"#));
+/// ```
+pub fn render_standalone(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    let html = render_with_opts(input, opts)?;
+    Ok(format!("<style>{}</style>{html}", stylesheet()))
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`),
+/// returning the warnings KaTeX reports in [strict](`Opts::set_strict`) mode
+/// `Warn` (the default) alongside.
+///
+/// Each warning reads like `Unicode text character "é" used in math mode
+/// [unicodeTextInMathMode]`, ending in the KaTeX error code. With any other
+/// strictness no warnings are reported, and neither are they by Temml.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::default();
+/// let (html, warnings) = katex::render_with_warnings("é", &opts).unwrap();
+/// assert!(html.contains("katex"));
+/// assert!(warnings[0].ends_with("[unicodeTextInMathMode]"));
+/// ```
+pub fn render_with_warnings(input: &str, opts: impl AsRef<Opts>) -> Result<(String, Vec<String>)> {
+    let (html, violations) =
+        with_engine(|engine| render_collecting(engine, input, opts.as_ref(), "warn"))?;
+    Ok((html, violations.iter().map(ToString::to_string).collect()))
+}
+
+/// Render on `engine` while collecting strict-mode warnings in `mode` (see
+/// `katexCollectWarnings` in `entry.js`).
+fn render_collecting<E>(
+    engine: &E,
+    input: &str,
+    opts: &Opts,
+    mode: &str,
+) -> Result<(String, Vec<Violation>)>
+where
+    E: JsEngine,
+{
+    collect_warnings(engine, mode)?;
+    let html = render_inner(engine, input, opts);
+    let violations = violations(engine);
+    collect_warnings(engine, "")?;
+    Ok((html?, violations?))
+}
+
+/// Set which strict-mode warnings `engine` collects.
+fn collect_warnings<E>(engine: &E, mode: &str) -> Result<()>
+where
+    E: JsEngine,
+{
+    let mode = engine.create_string_value(mode.to_owned())?;
+    engine.call_function("katexCollectWarnings", iter::once(mode))?;
+    Ok(())
+}
+
+/// The strict-mode warnings collected during the last render call on `engine`.
+fn violations<E>(engine: &E) -> Result<Vec<Violation>>
+where
+    E: JsEngine,
+{
+    let mut violations = Vec::new();
+    loop {
+        let field = |key: &str| {
+            let index = engine.create_int_value(violations.len() as i32)?;
+            let key = engine.create_string_value(key.to_owned())?;
+            let value = engine.call_function("katexWarning", [index, key].into_iter())?;
+            engine.value_to_string(value)
+        };
+        let code = field("code")?;
+        if code.is_empty() {
+            return Ok(violations);
+        }
+        let message = field("message")?;
+        violations.push(Violation { code, message });
+    }
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`), giving up
+/// after `timeout`.
+///
+/// Protects against inputs keeping the engine busy for a long time, such as
+/// macros expanding forever when `max_expand` is lifted. When the timeout
+/// expires, the JS code is interrupted and `Error::JsExecError("timeout", _)` is
+/// returned. Errors of a render which was not interrupted, e.g. a parse error
+/// reported just after the deadline, are returned as they are. The engine of
+/// the current thread stays usable for later renders.
+///
+/// Only available with the `quick-js` backend.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let opts = katex::Opts::default();
+/// let html = katex::render_with_timeout("a + b", &opts, Duration::from_secs(1)).unwrap();
+/// assert!(html.contains("katex"));
+/// ```
+#[cfg(feature = "quick-js")]
+pub fn render_with_timeout(
+    input: &str,
+    opts: impl AsRef<Opts>,
+    timeout: std::time::Duration,
+) -> Result<String> {
+    with_engine(|engine| {
+        let deadline = std::time::Instant::now() + timeout;
+        match js_engine::with_deadline(engine, deadline, || render_inner(engine, input, opts)) {
+            (Err(error), true) => Err(Error::JsExecError(
+                "timeout".to_owned(),
+                Some(std::sync::Arc::new(error)),
+            )),
+            (result, _) => result,
+        }
     })
 }
 
-/// Render LaTeX equation to HTML.
+/// Render LaTeX equation to HTML with additional [options](`Opts`), also
+/// returning how long rendering took.
+///
+/// Only the render call on the engine of the current thread is timed,
+/// including pre- and post-processing but not the initialization of the
+/// engine on first use, so the duration is comparable across calls. Not
+/// available on `wasm32-unknown-unknown`, where the standard library has no
+/// clock.
+///
+/// # Examples
+///
+/// ```
+/// let (html, elapsed) = katex::render_timed("a + b", katex::Opts::default()).unwrap();
+/// assert!(html.contains("katex"));
+/// println!("rendered in {elapsed:?}");
+/// ```
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn render_timed(input: &str, opts: impl AsRef<Opts>) -> Result<(String, std::time::Duration)> {
+    with_engine(|engine| {
+        let start = std::time::Instant::now();
+        let html = render_inner(engine, input, opts)?;
+        Ok((html, start.elapsed()))
+    })
+}
+
+/// Render several LaTeX equations to HTML with the same [options](`Opts`).
+///
+/// Equivalent to calling [`render_with_opts`] for every input, but the engine
+/// of the current thread is looked up only once for the whole batch. Results
+/// are returned in the order of `inputs`; an invalid input only fails its own
+/// entry. If the engine cannot be initialized, every entry holds that error.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::default();
+/// let results = katex::render_many(&["a + b", r"\frac{1}", r"\sqrt{2}"], &opts);
+/// assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+/// ```
+pub fn render_many(inputs: &[&str], opts: impl AsRef<Opts>) -> Vec<Result<String>> {
+    let opts = opts.as_ref();
+    with_engine(|engine| {
+        Ok(inputs
+            .iter()
+            .map(|input| render_inner(engine, input, opts))
+            .collect())
+    })
+    .unwrap_or_else(|e| inputs.iter().map(|_| Err(e.clone())).collect())
+}
+
+/// Render every line read from `reader` as a LaTeX equation, writing the HTML
+/// of each to `writer` on its own line.
+///
+/// Meant for large newline-delimited files of formulas: lines are rendered
+/// one at a time as they are read, on the engine of the current thread,
+/// which is looked up only once. Line endings (`\n` or `\r\n`) are not
+/// part of the input, and empty lines are written back as empty lines, so
+/// the output has one line per input line. To that end, the line breaks
+/// KaTeX puts in the path data of its SVG elements are written as spaces,
+/// which does not change the drawn paths.
+///
+/// A line that fails to render does not stop the others: unless
+/// [`throw_on_error`](Opts::set_throw_on_error) is set explicitly, lines are
+/// rendered as with `throw_on_error(false)`, and invalid ones are written as
+/// KaTeX's error markup. With `throw_on_error(true)`, the first invalid line
+/// fails the call with its error instead, after the lines before it have
+/// been written. Failures of `reader` or `writer` are reported as
+/// [`Error::IoError`].
+///
+/// # Examples
+///
+/// ```
+/// let input = "a + b\n\\frac{1}\n\\sqrt{2}\n";
+/// let mut output = Vec::new();
+/// katex::render_lines(input.as_bytes(), &mut output, katex::Opts::default()).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert_eq!(output.lines().count(), 3);
+/// assert!(output.lines().nth(1).unwrap().contains("katex-error"));
+/// ```
+pub fn render_lines<R, W>(reader: R, mut writer: W, opts: impl AsRef<Opts>) -> Result<()>
+where
+    R: std::io::BufRead,
+    W: std::io::Write,
+{
+    let mut opts = opts.as_ref().clone();
+    if !opts.throw_on_error_is_set() {
+        opts.set_throw_on_error(false);
+    }
+    with_engine(|engine| {
+        for line in reader.lines() {
+            let line = line?;
+            let input = line.strip_suffix('\r').unwrap_or(&line);
+            if !input.is_empty() {
+                let html = render_inner(engine, input, &opts)?;
+                writer.write_all(html.replace('\n', " ").as_bytes())?;
+            }
+            writer.write_all(b"\n")?;
+        }
+        Ok(writer.flush()?)
+    })
+}
+
+/// Render LaTeX equation to both HTML and MathML, combined in one fragment
+/// whose visible representation can be switched on the client side.
+///
+/// The [output type](`Opts::set_output_type`) of `opts` is ignored. The
+/// returned fragment follows this contract:
+///
+/// ```html
+/// <span class="katex-toggle" data-katex-toggle="">
+///   <span data-katex-view="html">..KaTeX HTML..</span>
+///   <span data-katex-view="mathml" hidden="">..MathML..</span>
+/// </span>
+/// ```
+///
+/// The HTML view is shown initially. A client‑side toggle only has to move
+/// the `hidden` attribute between the two `data-katex-view` children. With the
+/// `temml` feature the MathML view is produced by Temml.
+pub fn render_toggleable(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    let mut opts = opts.as_ref().clone();
+    with_engine(|engine| {
+        opts.set_output_type(OutputType::Html);
+        let html = render_inner(engine, input, &opts)?;
+        opts.set_output_type(OutputType::Mathml);
+        let mathml = render_inner(engine, input, &opts)?;
+        Ok(format!(
+            concat!(
+                r#"<span class="katex-toggle" data-katex-toggle="">"#,
+                r#"<span data-katex-view="html">{}</span>"#,
+                r#"<span data-katex-view="mathml" hidden="">{}</span>"#,
+                "</span>"
+            ),
+            html, mathml
+        ))
+    })
+}
+
+/// Render LaTeX equation in both inline and display mode with the same
+/// [options](`Opts`), returning the inline rendering first.
+///
+/// The [display mode](`Opts::set_display_mode`) of `opts` is ignored. Both
+/// renders share a single lookup of the engine of the current thread.
+///
+/// # Examples
+///
+/// ```
+/// let (inline, display) = katex::render_both(r"\sum_i x_i", katex::Opts::default()).unwrap();
+/// assert!(!inline.contains("katex-display"));
+/// assert!(display.contains("katex-display"));
+/// ```
+pub fn render_both(input: &str, opts: impl AsRef<Opts>) -> Result<(String, String)> {
+    let mut opts = opts.as_ref().clone();
+    with_engine(|engine| {
+        opts.set_display_mode(false);
+        let inline = render_inner(engine, input, &opts)?;
+        opts.set_display_mode(true);
+        let display = render_inner(engine, input, &opts)?;
+        Ok((inline, display))
+    })
+}
+
+/// Render LaTeX equation to HTML markup only, without the MathML that KaTeX
+/// adds for accessibility, for size-critical output.
+///
+/// This is the markup of KaTeX's HTML DOM tree, built by
+/// `katex.__renderToHTMLTree` and serialized with `toMarkup`, whatever the
+/// [output type](`Opts::set_output_type`) of `opts`, so options meant for
+/// hybrid output elsewhere can be reused as they are. That is the difference
+/// with rendering with [`OutputType::Html`], which KaTeX implements by
+/// building the same tree, so the markup is the same:
+///
+/// * there is no `<span class="katex-mathml">` and no `<math>` element, hence
+///   no `<annotation>` with the source either (see
+///   [`embed_source`](`Opts::set_embed_source`));
+/// * the `<span class="katex-html">` keeps its `aria-hidden="true"`, which
+///   only makes sense next to MathML: the output is invisible to screen
+///   readers, so provide a text alternative (e.g. from [`render_speech`])
+///   where accessibility matters.
+///
+/// With the `temml` feature the output is still produced by KaTeX.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_dom_markup(r"\sqrt{x}", katex::Opts::default()).unwrap();
+/// assert!(html.starts_with(r#"<span class="katex"><span class="katex-html""#));
+/// assert!(!html.contains("<math"));
+/// ```
+pub fn render_dom_markup(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    // Options are sent to KaTeX, and post-processed, as for HTML output.
+    let mut opts = opts.as_ref().clone();
+    opts.set_output_type(OutputType::Html);
+    let html = with_engine(|engine| render_raw(engine, "katexRenderHtmlTree", input, &opts))?;
+    Ok(postprocess::apply(input, &opts, html))
+}
+
+/// Render LaTeX equation from an untrusted source, e.g. user-generated
+/// content, to HTML with a fixed hardened policy.
+///
+/// The input is rendered with the default [options](`Opts`) except:
+///
+/// * `throw_on_error(false)`: invalid LaTeX is rendered inline, in KaTeX's
+///   default error color, instead of failing the call.
+/// * `trust(false)`: commands which could inject links, classes, styles or
+///   external resources (`\href`, `\url`, `\includegraphics`, `\htmlClass`,
+///   `\htmlId`, `\htmlStyle`, `\htmlData`) are rejected: only their name
+///   is rendered, in the error color, so their URLs never reach the output
+///   as links.
+/// * `max_expand(Some(1000))`: at most 1000 macro expansions, so a macro
+///   bomb such as `\def\a{\a\a}\a` stops early with an inline error.
+/// * `max_size(Some(10.0))`: user-specified sizes, e.g. of `\rule` or
+///   `\kern`, are capped at 10 em.
+///
+/// The policy does not depend on the input, so an error is only returned if
+/// the engine itself fails. For a different policy, set these options
+/// yourself and call [`render_with_opts`].
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_untrusted(r"\href{javascript:alert(1)}{x}").unwrap();
+/// assert!(!html.contains("href="));
+/// ```
+pub fn render_untrusted(input: &str) -> Result<String> {
+    let opts = Opts::builder()
+        .throw_on_error(false)
+        .trust_policy(opts::TrustPolicy::None)
+        .max_expand(Some(1000))
+        .max_size(Some(10.0))
+        .build()
+        .map_err(|e| Error::InvalidOptions(e.to_string()))?;
+    render_with_opts(input, opts)
+}
+
+/// The options used by [`render`], `None` for [`Opts::default`].
+static DEFAULT_OPTS: std::sync::RwLock<Option<Opts>> = std::sync::RwLock::new(None);
+
+/// Set the [options](`Opts`) used by [`render`], e.g. to apply custom macros
+/// everywhere an embedding library renders without options.
+///
+/// The defaults are process-wide: they apply to [`render`] calls on every
+/// thread from the next call on, unlike the JS engine which is per thread.
+/// Functions taking options, such as [`render_with_opts`], are not affected.
+/// Pass [`Opts::default`] to restore KaTeX's defaults.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::builder()
+///     .add_macro(r"\RR".to_owned(), r"\mathbb{R}".to_owned())
+///     .build()
+///     .unwrap();
+/// katex::set_default_opts(opts);
+/// let html = katex::render(r"x \in \RR").unwrap();
+/// assert!(html.contains("mathbb"));
+/// ```
+pub fn set_default_opts(opts: Opts) {
+    let mut defaults = DEFAULT_OPTS
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    *defaults = Some(opts);
+}
+
+/// The [options](`Opts`) used by [`render`], as set by [`set_default_opts`].
+pub fn default_opts() -> Opts {
+    DEFAULT_OPTS
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Render LaTeX equation to HTML with the [default options](`default_opts`).
 #[inline]
 pub fn render(input: &str) -> Result<String> {
-    render_with_opts(input, Opts::default())
+    render_with_opts(input, default_opts())
+}
+
+/// Render LaTeX equation to HTML inline, with the
+/// [default options](`default_opts`) otherwise.
+///
+/// Shorthand for [`render`] with [`display_mode`](Opts::set_display_mode)
+/// set to `false`, whatever the default options say.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_inline("a + b").unwrap();
+/// assert!(!html.contains("katex-display"));
+/// ```
+pub fn render_inline(input: &str) -> Result<String> {
+    let mut opts = default_opts();
+    opts.set_display_mode(false);
+    render_with_opts(input, opts)
+}
+
+/// Render LaTeX equation to HTML in display mode, with the
+/// [default options](`default_opts`) otherwise.
+///
+/// Shorthand for [`render`] with [`display_mode`](Opts::set_display_mode)
+/// set to `true`: the equation is centered on its own line, in a
+/// `katex-display` element.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render_display(r"\sum_{i=1}^n i").unwrap();
+/// assert!(html.starts_with(r#"<span class="katex-display">"#));
+/// ```
+pub fn render_display(input: &str) -> Result<String> {
+    let mut opts = default_opts();
+    opts.set_display_mode(true);
+    render_with_opts(input, opts)
 }
 
 #[cfg(test)]
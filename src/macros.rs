@@ -0,0 +1,306 @@
+//! Static analysis of macro tables.
+//!
+//! [`validate_macros`] inspects the definitions passed to KaTeX through
+//! [`Opts`](crate::Opts) without running the engine, catching mistakes which
+//! would otherwise only show up as runaway expansion or silently wrong output
+//! at render time. Macro tables can also be read from LaTeX declarations,
+//! see [`OptsBuilder::macros_from_tex`](crate::OptsBuilder::macros_from_tex).
+
+use crate::error::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A problem found by [`validate_macros`].
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MacroIssue {
+    /// The listed macros expand into each other in this order, the last one
+    /// leading back to the first one. Such a cycle never terminates.
+    Cycle(Vec<String>),
+    /// The macro uses parameter `#n` but not all of the parameters before it,
+    /// so the listed arguments are consumed but never used.
+    UnusedParameters {
+        /// Name of the macro.
+        name: String,
+        /// Numbers of the unused parameters.
+        parameters: Vec<u8>,
+    },
+    /// The listed macros share the exact same definition and could be merged.
+    Duplicate(Vec<String>),
+}
+
+/// Check a macro table for cycles, unused parameters, and duplicates.
+///
+/// The number of parameters of a macro is inferred like KaTeX does, from the
+/// highest `#n` appearing in its definition. Issues are reported in a
+/// deterministic order: cycles first, then unused parameters, then duplicates.
+///
+/// # Examples
+///
+/// ```
+/// use katex::MacroIssue;
+/// use std::collections::HashMap;
+///
+/// let macros = HashMap::from([
+///     (r"\a".to_owned(), r"\b + 1".to_owned()),
+///     (r"\b".to_owned(), r"\a".to_owned()),
+/// ]);
+/// let issues = katex::validate_macros(&macros).unwrap_err();
+/// assert_eq!(issues, [MacroIssue::Cycle(vec![r"\a".to_owned(), r"\b".to_owned()])]);
+/// ```
+pub fn validate_macros(macros: &HashMap<String, String>) -> Result<(), Vec<MacroIssue>> {
+    let macros: BTreeMap<&str, &str> = macros
+        .iter()
+        .map(|(name, body)| (name.as_str(), body.as_str()))
+        .collect();
+    let mut issues = find_cycles(&macros);
+    for (name, body) in &macros {
+        let used = parameters(body);
+        let highest = used.iter().copied().max().unwrap_or(0);
+        let unused: Vec<u8> = (1..highest).filter(|n| !used.contains(n)).collect();
+        if !unused.is_empty() {
+            issues.push(MacroIssue::UnusedParameters {
+                name: (*name).to_owned(),
+                parameters: unused,
+            });
+        }
+    }
+    let mut by_body: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (name, body) in &macros {
+        by_body.entry(body).or_default().push((*name).to_owned());
+    }
+    issues.extend(
+        by_body
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(MacroIssue::Duplicate),
+    );
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// Find all elementary cycles reachable in the macro reference graph, each
+/// reported once starting from its smallest name.
+fn find_cycles(macros: &BTreeMap<&str, &str>) -> Vec<MacroIssue> {
+    let edges: BTreeMap<&str, BTreeSet<&str>> = macros
+        .iter()
+        .map(|(name, body)| {
+            let targets = control_sequences(body)
+                .filter_map(|cs| macros.get_key_value(cs).map(|(k, _)| *k))
+                .collect();
+            (*name, targets)
+        })
+        .collect();
+    let mut cycles = BTreeSet::new();
+    for start in edges.keys() {
+        let mut path = vec![*start];
+        walk(&edges, start, &mut path, &mut cycles);
+    }
+    cycles
+        .into_iter()
+        .map(|cycle: Vec<&str>| MacroIssue::Cycle(cycle.into_iter().map(str::to_owned).collect()))
+        .collect()
+}
+
+/// Depth first search for paths from `path[0]` back to itself, only visiting
+/// names greater than the start so each cycle is found from its minimum.
+fn walk<'a>(
+    edges: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+    current: &'a str,
+    path: &mut Vec<&'a str>,
+    cycles: &mut BTreeSet<Vec<&'a str>>,
+) {
+    let start = path[0];
+    for &next in &edges[current] {
+        if next == start {
+            cycles.insert(path.clone());
+        } else if next > start && !path.contains(&next) {
+            path.push(next);
+            walk(edges, next, path, cycles);
+            path.pop();
+        }
+    }
+}
+
+/// Iterate over the control sequences (e.g. `\foo`, `\{`) in `body`.
+pub(crate) fn control_sequences(body: &str) -> impl Iterator<Item = &str> {
+    body.match_indices('\\').filter_map(move |(idx, _)| {
+        let preceding = body[..idx].chars().rev().take_while(|&c| c == '\\');
+        if preceding.count() % 2 == 1 {
+            return None;
+        }
+        let rest = &body[idx + 1..];
+        let len = match rest.find(|c: char| !c.is_ascii_alphabetic()) {
+            Some(0) => rest.chars().next().map_or(0, char::len_utf8),
+            Some(len) => len,
+            None => rest.len(),
+        };
+        Some(&body[idx..=idx + len])
+    })
+}
+
+/// The parameter numbers (`#1` to `#9`) used in `body`, ignoring `##` and
+/// `\#`.
+fn parameters(body: &str) -> BTreeSet<u8> {
+    let mut used = BTreeSet::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '#' => {
+                if let Some(d @ '1'..='9') = chars.next() {
+                    used.insert(d as u8 - b'0');
+                }
+            }
+            _ => {}
+        }
+    }
+    used
+}
+
+/// Parse a block of `\newcommand` / `\renewcommand` declarations into macro
+/// definitions, in order of appearance.
+///
+/// KaTeX infers the number of arguments of a string macro from the run of
+/// parameters `#1`, `#2`, ... appearing in it, so a declared argument count
+/// which the body does not use in full is kept by consuming the missing
+/// parameters with `\@firstoftwo`.
+pub(crate) fn parse_definitions(src: &str) -> Result<Vec<(String, String)>> {
+    let mut parser = DefinitionParser { src, pos: 0 };
+    let mut definitions = Vec::new();
+    while parser.skip_blank() {
+        definitions.push(parser.definition()?);
+    }
+    Ok(definitions)
+}
+
+/// Cursor over the source of [`parse_definitions`].
+struct DefinitionParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> DefinitionParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    /// Skip whitespace and `%` comments, returning whether input remains.
+    fn skip_blank(&mut self) -> bool {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('%') {
+                return !trimmed.is_empty();
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        let found = self.rest().starts_with(prefix);
+        if found {
+            self.pos += prefix.len();
+        }
+        found
+    }
+
+    fn definition(&mut self) -> Result<(String, String)> {
+        let start = self.pos;
+        let command = control_sequences(self.rest())
+            .next()
+            .filter(|cs| self.rest().starts_with(cs));
+        match command {
+            Some(cs @ (r"\newcommand" | r"\renewcommand")) => self.pos += cs.len(),
+            _ => return Err(self.error(start, r"Expected \newcommand or \renewcommand")),
+        }
+        self.eat("*");
+        self.skip_blank();
+        let braced = self.eat("{");
+        self.skip_blank();
+        let name = match control_sequences(self.rest()).next() {
+            Some(name) if self.rest().starts_with(name) && name.len() > 1 => name,
+            _ => return Err(self.error(self.pos, "Expected a control sequence to define")),
+        };
+        self.pos += name.len();
+        if braced {
+            self.skip_blank();
+            if !self.eat("}") {
+                return Err(self.error(self.pos, "Expected '}' after the macro name"));
+            }
+        }
+        self.skip_blank();
+        let mut arguments = 0;
+        if self.eat("[") {
+            let count = self.rest().find(']').map(|end| &self.rest()[..end]);
+            match count.and_then(|count| count.trim().parse::<u8>().ok()) {
+                Some(count @ 0..=9) => arguments = count,
+                _ => return Err(self.error(self.pos, "Expected an argument count from 0 to 9")),
+            }
+            self.pos += count.map_or(0, str::len) + 1;
+            self.skip_blank();
+            if self.rest().starts_with('[') {
+                return Err(self.error(self.pos, "Optional arguments are not supported"));
+            }
+        }
+        let body = self.group()?;
+        let used = parameters(body);
+        if let Some(&highest) = used.iter().find(|&&n| n > arguments) {
+            let message = format!("Parameter #{highest} exceeds the argument count of {name}");
+            return Err(self.error(start, &message));
+        }
+        let body = if (1..=arguments).all(|n| used.contains(&n)) {
+            body.to_owned()
+        } else {
+            let consumed: String = (1..=arguments).map(|n| format!("#{n}")).collect();
+            format!(r"\@firstoftwo{{{body}}}{{{consumed}}}")
+        };
+        Ok((name.to_owned(), body))
+    }
+
+    /// Read a balanced `{...}` group, returning its content.
+    fn group(&mut self) -> Result<&'a str> {
+        if !self.eat("{") {
+            return Err(self.error(self.pos, "Expected '{' before the macro body"));
+        }
+        let start = self.pos;
+        let mut depth = 0usize;
+        let mut chars = self.rest().char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    self.pos = start + idx + 1;
+                    return Ok(&self.src[start..start + idx]);
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        Err(self.error(start - 1, "Unbalanced braces in the macro body"))
+    }
+
+    fn error(&self, pos: usize, message: &str) -> Error {
+        let line_start = self.src[..pos].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = self.src[pos..]
+            .find('\n')
+            .map_or(self.src.len(), |idx| pos + idx);
+        let line = self.src[..pos].matches('\n').count() + 1;
+        Error::ParseError {
+            message: message.to_owned(),
+            position: Some(self.src[..pos].encode_utf16().count()),
+            raw: format!(
+                "{message} at line {line}: {}",
+                self.src[line_start..line_end].trim()
+            ),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Rendering with structural metadata.
+//!
+//! [`render_with_metadata`] reports facts about the structure of a formula,
+//! such as the number of rows of its environments, alongside the markup.
+
+use crate::{
+    error::{Error, Result},
+    opts::Opts,
+    parse::parse_with,
+    render_with_opts,
+};
+
+/// Metadata about a rendered formula, see [`render_with_metadata`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RenderMeta {
+    /// The number of rows of the outermost row-based environments, such as
+    /// `aligned`, `gathered`, `cases`, `array` and the matrices, summed over
+    /// all of them. Rows of environments nested in another one are not
+    /// counted separately. `0` for formulas without such environment.
+    pub rows: usize,
+    /// Whether the formula was rendered in display mode.
+    pub display_mode: bool,
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`), also
+/// returning [metadata](`RenderMeta`) about its structure, e.g. for
+/// interactive steppers revealing an `aligned` environment row by row.
+///
+/// The metadata is computed from KaTeX's parse tree, so the input is parsed
+/// a second time. Input rendered as an inline error (with
+/// `throw_on_error(false)`) reports no rows.
+///
+/// # Examples
+///
+/// ```
+/// let input = r"\begin{aligned} a &= b \\ c &= d \end{aligned}";
+/// let (html, meta) = katex::render_with_metadata(input, katex::Opts::default()).unwrap();
+/// assert_eq!(meta.rows, 2);
+/// ```
+pub fn render_with_metadata(input: &str, opts: impl AsRef<Opts>) -> Result<(String, RenderMeta)> {
+    let opts = opts.as_ref();
+    let html = render_with_opts(input, opts)?;
+    let rows = match parse_with("katexArrayRows", input, opts) {
+        Ok(rows) => rows
+            .parse()
+            .map_err(|_| Error::JsValueError(format!("invalid row count `{rows}`"), None))?,
+        Err(Error::ParseError { .. } | Error::UnsupportedCommand { .. }) => 0,
+        Err(error) => return Err(error),
+    };
+    let meta = RenderMeta {
+        rows,
+        display_mode: opts.is_display_mode(),
+    };
+    Ok((html, meta))
+}
@@ -0,0 +1,278 @@
+//! Rendering of text with embedded math.
+//!
+//! [`render_mixed`] finds math between delimiters such as `$$..$$` or
+//! `\(..\)` in a piece of plain text and renders it, like KaTeX's
+//! [auto-render](https://katex.org/docs/autorender.html) extension does for
+//! DOM elements. The extension itself walks the DOM and cannot run in the
+//! embedded engines, so its splitting algorithm is reproduced here instead:
+//!
+//! * the earliest left delimiter wins; among delimiters starting at the same
+//!   position, the first one in the [list](`MixedConfig::delimiters`) wins;
+//! * the matching right delimiter is the first one outside braces, skipping
+//!   escaped characters such as `\}`;
+//! * a left delimiter without matching right delimiter is left as text;
+//! * for `\begin{..}` delimiters the delimiters are part of the rendered math.
+//!
+//! Unlike the extension, a backslash escapes a left delimiter not starting
+//! with a backslash itself: `\$` is written as a literal `$`.
+
+use crate::{error::Result, opts::Opts, postprocess::escape_xml, render_inner, with_engine};
+
+/// A pair of math delimiters, as in the `delimiters` option of KaTeX's
+/// auto-render extension.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DelimiterConfig {
+    /// The opening delimiter, e.g. `\(`.
+    pub left: String,
+    /// The closing delimiter, e.g. `\)`.
+    pub right: String,
+    /// Whether the math is rendered in display mode.
+    pub display: bool,
+}
+
+impl DelimiterConfig {
+    /// Create a delimiter pair.
+    pub fn new(left: impl Into<String>, right: impl Into<String>, display: bool) -> Self {
+        Self {
+            left: left.into(),
+            right: right.into(),
+            display,
+        }
+    }
+}
+
+/// Configuration of [`render_mixed_with_config`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MixedConfig {
+    /// The delimiters to search for, in order of priority.
+    pub delimiters: Vec<DelimiterConfig>,
+    /// Whether to leave text between `<code>` / `<pre>` and the matching
+    /// closing tag alone instead of searching it for math.
+    pub ignore_code: bool,
+}
+
+impl Default for MixedConfig {
+    /// The auto-render extension's default delimiters plus `$..$`, with code
+    /// ignored:
+    ///
+    /// | left                 | right              | display |
+    /// |----------------------|--------------------|---------|
+    /// | `$$`                 | `$$`               | yes     |
+    /// | `$`                  | `$`                | no      |
+    /// | `\(`                 | `\)`               | no      |
+    /// | `\begin{equation}`   | `\end{equation}`   | yes     |
+    /// | `\begin{align}`      | `\end{align}`      | yes     |
+    /// | `\begin{alignat}`    | `\end{alignat}`    | yes     |
+    /// | `\begin{gather}`     | `\end{gather}`     | yes     |
+    /// | `\begin{CD}`         | `\end{CD}`         | yes     |
+    /// | `\[`                 | `\]`               | yes     |
+    ///
+    /// The extension leaves `$..$` out as it misfires on text mentioning
+    /// prices; remove it from the list for such texts.
+    fn default() -> Self {
+        let mut delimiters = vec![
+            DelimiterConfig::new("$$", "$$", true),
+            DelimiterConfig::new("$", "$", false),
+            DelimiterConfig::new(r"\(", r"\)", false),
+        ];
+        for env in ["equation", "align", "alignat", "gather", "CD"] {
+            delimiters.push(DelimiterConfig::new(
+                format!(r"\begin{{{env}}}"),
+                format!(r"\end{{{env}}}"),
+                true,
+            ));
+        }
+        delimiters.push(DelimiterConfig::new(r"\[", r"\]", true));
+        Self {
+            delimiters,
+            ignore_code: true,
+        }
+    }
+}
+
+/// A part of the text to render.
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Math { tex: &'a str, display: bool },
+}
+
+/// Render the math found in plain text with the
+/// [default configuration](`MixedConfig::default`).
+///
+/// Text outside of math is HTML-escaped, math is rendered with `opts`, except
+/// that the display mode is chosen by the delimiters.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::default();
+/// let html = katex::render_mixed(r"Euler: $e^{i\pi} = -1$ & more", &opts).unwrap();
+/// assert!(html.starts_with("Euler: <span class=\"katex\">"));
+/// assert!(html.ends_with("</span> &amp; more"));
+/// ```
+pub fn render_mixed(text: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    render_mixed_with_config(text, &MixedConfig::default(), opts)
+}
+
+/// Render the math found in plain text, delimited as configured by `config`.
+///
+/// See [`render_mixed`].
+pub fn render_mixed_with_config(
+    text: &str,
+    config: &MixedConfig,
+    opts: impl AsRef<Opts>,
+) -> Result<String> {
+    let mut opts = opts.as_ref().clone();
+    let mut segments = Vec::new();
+    for (part, ignored) in code_blocks(text, config.ignore_code) {
+        if ignored {
+            segments.push(Segment::Text(part));
+        } else {
+            split_at_delimiters(part, &config.delimiters, &mut segments);
+        }
+    }
+    with_engine(|engine| {
+        let mut out = String::with_capacity(text.len());
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => out.push_str(&escape_xml(text)),
+                Segment::Math { tex, display } => {
+                    opts.set_display_mode(display);
+                    out.push_str(&render_inner(engine, tex, &opts)?);
+                }
+            }
+        }
+        Ok(out)
+    })
+}
+
+/// Split `text` into parts, flagging the ones inside `<code>` / `<pre>`
+/// (including the tags) if `ignore_code` is set.
+fn code_blocks(text: &str, ignore_code: bool) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some((start, end)) = ignore_code.then(|| next_code_block(rest)).flatten() {
+        parts.push((&rest[..start], false));
+        parts.push((&rest[start..end], true));
+        rest = &rest[end..];
+    }
+    parts.push((rest, false));
+    parts
+}
+
+/// Range of the first `<code>` / `<pre>` element in `text`, extending to the
+/// end of `text` if it is not closed.
+fn next_code_block(text: &str) -> Option<(usize, usize)> {
+    let (start, tag) = ["<code", "<pre"]
+        .iter()
+        .filter_map(|tag| Some((find_tag(text, tag)?, *tag)))
+        .min()?;
+    let close = format!("</{}>", &tag[1..]);
+    let end = text[start..]
+        .find(&close)
+        .map_or(text.len(), |end| start + end + close.len());
+    Some((start, end))
+}
+
+/// Position of the opening tag starting with `prefix` (e.g. `<pre`), which
+/// must be followed by `>` or whitespace to exclude e.g. `<prefix>`.
+fn find_tag(text: &str, prefix: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(prefix) {
+        let start = from + pos;
+        let after = &text[start + prefix.len()..];
+        if after.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            return Some(start);
+        }
+        from = start + prefix.len();
+    }
+    None
+}
+
+/// Append the text and math segments of `text` to `segments`.
+fn split_at_delimiters<'a>(
+    mut text: &'a str,
+    delimiters: &[DelimiterConfig],
+    segments: &mut Vec<Segment<'a>>,
+) {
+    let mut from = 0;
+    while let Some((start, delimiter)) = find_left(text, from, delimiters) {
+        let Some(delimiter) = delimiter else {
+            // Drop the backslash, the delimiter is searched for after it.
+            if start > 0 {
+                segments.push(Segment::Text(&text[..start]));
+            }
+            text = &text[start + 1..];
+            from = text.chars().next().map_or(0, char::len_utf8);
+            continue;
+        };
+        let content = start + delimiter.left.len();
+        let Some(end) = find_end_of_math(&delimiter.right, text, content) else {
+            break;
+        };
+        if start > 0 {
+            segments.push(Segment::Text(&text[..start]));
+        }
+        let tex = if delimiter.left.starts_with(r"\begin{") {
+            &text[start..end + delimiter.right.len()]
+        } else {
+            &text[content..end]
+        };
+        segments.push(Segment::Math {
+            tex,
+            display: delimiter.display,
+        });
+        text = &text[end + delimiter.right.len()..];
+        from = 0;
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+}
+
+/// The earliest left delimiter in `text` from `from` on, by priority on ties.
+///
+/// A backslash escaping a delimiter is returned without delimiter.
+fn find_left<'d>(
+    text: &str,
+    from: usize,
+    delimiters: &'d [DelimiterConfig],
+) -> Option<(usize, Option<&'d DelimiterConfig>)> {
+    let starts_delimiter = |i: usize| {
+        delimiters
+            .iter()
+            .find(|d| !d.left.is_empty() && text[i..].starts_with(&d.left))
+    };
+    text[from..].char_indices().find_map(|(i, c)| {
+        let i = from + i;
+        if c == '\\' {
+            let escaped = starts_delimiter(i + 1).filter(|d| !d.left.starts_with('\\'));
+            if escaped.is_some() {
+                return Some((i, None));
+            }
+        }
+        starts_delimiter(i).map(|d| (i, Some(d)))
+    })
+}
+
+/// Position of `right` in `text` from `start` on, outside of braces.
+fn find_end_of_math(right: &str, text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut chars = text[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        let i = start + i;
+        if depth == 0 && !right.is_empty() && text[i..].starts_with(right) {
+            return Some(i);
+        }
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
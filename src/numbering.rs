@@ -0,0 +1,78 @@
+//! Equation numbering helpers.
+//!
+//! KaTeX has no automatic equation counter, so numbered equations are produced
+//! by appending a `\tag{..}` (or the unparenthesized `\tag*{..}`) to the input
+//! before rendering. Inputs that already carry their own tag are left alone so
+//! they are never numbered twice.
+
+use crate::{error::Result, opts::Opts, render_with_opts};
+
+/// How an injected equation tag is typeset.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TagStyle {
+    /// `\tag{..}`: the tag is wrapped in parentheses, e.g. `(1)`.
+    #[default]
+    Parenthesized,
+    /// `\tag*{..}`: the tag is rendered verbatim, e.g. `1`.
+    Starred,
+}
+
+/// Render LaTeX equation to HTML in display mode, numbered with `tag`.
+///
+/// The tag is injected as `\tag{tag}` or `\tag*{tag}` depending on `style`.
+/// If `input` already contains a `\tag` or `\tag*`, it is rendered unchanged
+/// so the user supplied label wins. Display mode is always enabled since
+/// KaTeX only accepts tags in display equations.
+///
+/// # Examples
+///
+/// ```
+/// use katex::TagStyle;
+///
+/// let opts = katex::Opts::default();
+/// let html = katex::render_numbered("E = mc^2", "1", TagStyle::Parenthesized, &opts).unwrap();
+/// assert!(html.contains("<mtext>(1)</mtext>"));
+/// ```
+pub fn render_numbered(
+    input: &str,
+    tag: &str,
+    style: TagStyle,
+    opts: impl AsRef<Opts>,
+) -> Result<String> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_display_mode(true);
+    if has_tag(input) {
+        return render_with_opts(input, opts);
+    }
+    let command = match style {
+        TagStyle::Parenthesized => r"\tag",
+        TagStyle::Starred => r"\tag*",
+    };
+    // On its own line, so a trailing `%` comment cannot swallow it.
+    render_with_opts(&format!("{input}\n{command}{{{tag}}}"), opts)
+}
+
+/// Whether `input` contains a `\tag` or `\tag*` control sequence outside of
+/// `%` comments.
+fn has_tag(input: &str) -> bool {
+    input.lines().map(strip_comment).any(|line| {
+        line.match_indices(r"\tag").any(|(idx, _)| {
+            let rest = &line[idx + r"\tag".len()..];
+            !is_escaped(line, idx) && !rest.starts_with(|c: char| c.is_ascii_alphabetic())
+        })
+    })
+}
+
+/// `line` up to its `%` comment, if any.
+fn strip_comment(line: &str) -> &str {
+    line.match_indices('%')
+        .find(|&(idx, _)| !is_escaped(line, idx))
+        .map_or(line, |(idx, _)| &line[..idx])
+}
+
+/// Whether the character at `idx` in `text` is preceded by an odd number of
+/// backslashes.
+fn is_escaped(text: &str, idx: usize) -> bool {
+    text[..idx].chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
@@ -18,24 +18,53 @@
 //!     .output_type(katex::OutputType::HtmlAndMathml)
 //!     .error_color("#cc0000")
 //!     .macros(std::collections::HashMap::from([
-//!         (r"\\RR".into(), r"\\mathbb{R}".into())
+//!         (r"\RR".into(), r"\mathbb{R}".into())
 //!     ]))
 //!     .build()
 //!     .unwrap();
-//! let html = katex::render_with_opts(r"\\RR", &opts).unwrap();
+//! let html = katex::render_with_opts(r"\RR", &opts).unwrap();
 //! assert!(html.contains("mathbb"));
 //! ```
 
-use crate::{error::Result, js_engine::JsEngine};
+use crate::{
+    error::{Error, Result},
+    js_engine::JsEngine,
+    macros::parse_definitions,
+};
 use derive_builder::Builder;
 use itertools::process_results;
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
 
 /// Options to be passed to KaTeX.
 ///
 /// Read <https://katex.org/docs/options.html> for more information.
+///
+/// With the `serde` feature, options can be (de)serialized with the camelCase
+/// names KaTeX uses (`displayMode`, `output`, `throwOnError`, ...). Missing
+/// fields keep their default; the `adaptive_max_expand` policy is skipped.
+///
+/// With the `temml` feature, [`OutputType::Mathml`] is rendered by Temml,
+/// which does not know `output`, `fleqn`, `minRuleThickness` and
+/// `globalGroup`: these options are not sent to Temml at all. `maxSize` is
+/// sent as the pair of limits for relative (em) and absolute (pt) sizes
+/// Temml expects, at KaTeX's 10pt per em. The Temml-specific `annotate`,
+/// `wrap` and `xml` are ignored by KaTeX. [`Opts::validate`] reports such
+/// conflicts.
+///
+/// Options compare equal when all fields do, so they can serve as part of a
+/// cache key. As some fields are `f64`, `Opts` is not `Eq` (nor `Hash`): a
+/// `NaN` `min_rule_thickness` makes options unequal to themselves. The
+/// `adaptive_max_expand` and `trust_callback` policies compare by identity,
+/// i.e. options are only equal if they hold the very same function (for
+/// callbacks: the same clone of the [`TrustCallback`]). Function pointers
+/// are compared by address, which the compiler does not guarantee to be
+/// unique: options may compare unequal although they hold the same
+/// `adaptive_max_expand` policy (or, for identical functions merged by the
+/// compiler, equal though they hold different ones).
 #[non_exhaustive]
-#[derive(Clone, Builder, Debug, Default)]
+#[derive(Clone, Builder, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, rename_all = "camelCase"))]
 #[builder(default)]
 #[builder(setter(into, strip_option))]
 pub struct Opts {
@@ -47,20 +76,29 @@ pub struct Opts {
     /// Which output format KaTeX should produce.
     ///
     /// Defaults to KaTeX's hybrid HTML + MathML when unset.
+    #[cfg_attr(feature = "serde", serde(rename = "output"))]
     output_type: Option<OutputType>,
     /// Whether to typeset equation tags / numbers (`\tag{}` / `\label{}`)
     /// on the left instead of the right (LaTeX's `leqno`).
     leqno: Option<bool>,
     /// Whether display mode equations are left‑aligned instead of centered (`fleqn`).
     fleqn: Option<bool>,
+    /// Post-processing:
+    /// left margin of display mode equations when `fleqn` is set.
+    fleqn_indent: Option<Length>,
     /// If `true`, parsing invalid LaTeX will raise an error (returned as
-    /// [`Error::JsExecError`]); if `false` KaTeX inserts error nodes styled by
+    /// [`Error::ParseError`]); if `false` KaTeX inserts error nodes styled by
     /// [`error_color`].
     throw_on_error: Option<bool>,
     /// CSS color (hex / rgb / named) applied to invalid LaTeX segments when
     /// `throw_on_error` is `false`.
     error_color: Option<String>,
     /// Collection of custom macros.
+    ///
+    /// Definitions are passed to KaTeX verbatim, so parameterized macros use
+    /// `#1`, `#2`, ... placeholders as in `\frac{#1}{#2}`. KaTeX infers the
+    /// number of arguments from the run of placeholders starting at `#1`,
+    /// e.g. a definition using only `#2` takes no argument.
     /// Read <https://katex.org/docs/options.html> for more information.
     macros: HashMap<String, String>,
     /// Specifies a minimum thickness, in ems.
@@ -70,15 +108,98 @@ pub struct Opts {
     /// If set to `None`, users can make elements and spaces arbitrarily large.
     /// Read <https://katex.org/docs/options.html> for more information.
     #[allow(clippy::option_option)]
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "double_option", skip_serializing_if = "Option::is_none")
+    )]
     max_size: Option<Option<f64>>,
     /// Limit the number of macro expansions to the specified number.
-    /// If set to `None`, the macro expander will try to fully expand as in LaTeX.
+    /// If set to `None`, the macro expander will try to fully expand as in
+    /// LaTeX: the limit is sent as the JS number `Infinity`, so it is not
+    /// capped at `i32::MAX` expansions. Finite limits are exact, as JS numbers
+    /// represent all `i32` values.
+    /// See also [`OptsBuilder::max_expand_limit`] and
+    /// [`OptsBuilder::max_expand_unlimited`].
     /// Read <https://katex.org/docs/options.html> for more information.
     #[allow(clippy::option_option)]
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "double_option", skip_serializing_if = "Option::is_none")
+    )]
     max_expand: Option<Option<i32>>,
+    /// Policy computing the macro expansion limit from the input length in
+    /// bytes, taking precedence over `max_expand`.
+    /// See [`scaled_max_expand`] for a built-in policy.
+    #[builder(setter(custom))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    adaptive_max_expand: Option<MaxExpandPolicy>,
     /// Whether to trust users' input.
     /// Read <https://katex.org/docs/options.html> for more information.
+    #[builder_setter_attr(deprecated(
+        note = "use `trust_policy` with `TrustPolicy::All` or `TrustPolicy::None`"
+    ))]
     trust: Option<bool>,
+    /// Policy deciding whether to trust each command, taking precedence over
+    /// `trust`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trust_callback: Option<TrustCallback>,
+    /// URL protocols to trust, taking precedence over `trust` for commands
+    /// referring to a URL.
+    allowed_protocols: Option<Vec<String>>,
+    /// Commands to trust, taking precedence over `trust`.
+    trusted_commands: Option<Vec<String>>,
+    /// How to handle LaTeX features KaTeX supports which are not valid LaTeX.
+    /// Read <https://katex.org/docs/options.html> for more information.
+    strict: Option<Strictness>,
+    /// Whether `\color` behaves like LaTeX's `\textcolor`, coloring only its
+    /// argument, instead of switching the color for the rest of the group.
+    /// Read <https://katex.org/docs/options.html> for more information.
+    color_is_text_color: Option<bool>,
+    /// Whether to run the input in the global group, so definitions made
+    /// with `\def` persist past the end of the input's top-level group.
+    /// Read <https://katex.org/docs/options.html> for more information.
+    global_group: Option<bool>,
+
+    /// Post-processing:
+    /// whether to set the `alttext` attribute of the `<math>` element to the
+    /// (XML-escaped) source LaTeX.
+    /// Applied by this crate after rendering, KaTeX itself never sets it.
+    mathml_alttext: Option<bool>,
+    /// Post-processing:
+    /// the `aria-label` to set on the `<math>` element, e.g. a curated
+    /// description read by screen readers instead of the MathML itself.
+    math_aria_label: Option<String>,
+    /// Post-processing:
+    /// whether to bring MathML into a canonical form shared by KaTeX and Temml.
+    normalize_backend_output: Option<bool>,
+    /// Post-processing:
+    /// whether to replace Unicode mathematical alphanumeric characters in
+    /// MathML by ASCII characters with a `mathvariant` attribute.
+    ascii_mathvariant: Option<bool>,
+    /// Post-processing:
+    /// whether to insert `<wbr>` line break opportunities after top-level
+    /// binary operators and relations of inline HTML output.
+    line_break_hints: Option<bool>,
+    /// Post-processing:
+    /// whether to remove the outer `<span class="katex">` wrapper.
+    unwrap_outer: Option<bool>,
+    /// Post-processing:
+    /// whether to embed the source LaTeX into the HTML output as a `data-tex`
+    /// attribute when the MathML carries no annotation with it.
+    embed_source: Option<bool>,
+
+    /// Pre-processing:
+    /// whether to render fractions in inline math at display size by
+    /// rewriting `\frac` into `\dfrac` before rendering.
+    big_inline_fractions: Option<bool>,
+    /// Pre-processing:
+    /// whether [`big_inline_fractions`](OptsBuilder::big_inline_fractions)
+    /// also rewrites fractions nested inside other fractions.
+    big_inline_fractions_nested: Option<bool>,
+
+    /// Whether `\ce` and `\pu` of the mhchem extension fail for this render.
+    #[cfg(feature = "mhchem")]
+    disable_mhchem: Option<bool>,
 
     /// Temml-sepcific:
     /// whether to annotate MathML with input LaTeX string.
@@ -103,11 +224,131 @@ impl Opts {
         OptsBuilder::default()
     }
 
+    /// Return an [`OptsBuilder`] preset for output accessible to screen
+    /// readers, which can be refined further before building.
+    ///
+    /// The preset sets:
+    ///
+    /// * [`output_type(OutputType::HtmlAndMathml)`](Opts::set_output_type):
+    ///   the visual HTML is hidden from assistive technology, which reads the
+    ///   MathML instead. KaTeX annotates the MathML with the source LaTeX.
+    /// * [`mathml_alttext(true)`](Opts::set_mathml_alttext): the source is
+    ///   also set as `alttext` of the `<math>` element, a plain-text fallback
+    ///   for readers without MathML support.
+    /// * With the `temml` feature, `annotate(true)`, so the source annotation
+    ///   is kept when switching to [`OutputType::Mathml`], which is rendered
+    ///   by Temml.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::accessible().display_mode(true).build().unwrap();
+    /// let html = katex::render_with_opts("x^2", &opts).unwrap();
+    /// assert!(html.contains(r#"<annotation encoding="application/x-tex">x^2</annotation>"#));
+    /// ```
+    pub fn accessible() -> OptsBuilder {
+        let mut builder = Self::builder();
+        builder
+            .output_type(OutputType::HtmlAndMathml)
+            .mathml_alttext(true);
+        #[cfg(feature = "temml")]
+        builder.annotate(true);
+        builder
+    }
+
     /// Set whether to render the math in display mode.
     pub fn set_display_mode(&mut self, flag: bool) {
         self.display_mode = Some(flag);
     }
 
+    /// Check that the options set are understood by the renderer of the
+    /// selected [output type](Opts::set_output_type), instead of being
+    /// silently ignored.
+    ///
+    /// Only relevant with the `temml` feature, which renders
+    /// [`OutputType::Mathml`] with Temml and every other output with KaTeX.
+    /// Temml-only options conflict with KaTeX output unless they ask for what
+    /// KaTeX does anyway: `annotate(true)` and `xml(true)` are accepted, as
+    /// KaTeX always annotates MathML and sets its namespace, while any `wrap`
+    /// mode is rejected. Conversely, `fleqn(true)`, `global_group(true)` and
+    /// any `min_rule_thickness` are rejected with Temml output. Without the
+    /// `temml` feature, everything is rendered by KaTeX and the Temml-only
+    /// options do not exist.
+    ///
+    /// A [`max_size`](Opts::set_max_size) which is `NaN` or negative is
+    /// rejected too, such as one converted from a [`MaxSize::Points`] whose
+    /// number of points per `em` is not positive and finite.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidOptions`] naming the first conflicting or
+    /// invalid option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder().display_mode(true).build().unwrap();
+    /// assert!(opts.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if let Some(Some(max_size)) = self.max_size {
+            if max_size.is_nan() || max_size < 0.0 {
+                return Err(Error::InvalidOptions(format!(
+                    "`max_size` must be a non-negative number of em, not {max_size}"
+                )));
+            }
+        }
+        #[cfg(feature = "temml")]
+        {
+            let (renderer, conflicts) = if self.is_mathml_only() {
+                (
+                    "Temml",
+                    [
+                        ("fleqn", self.fleqn == Some(true)),
+                        ("global_group", self.global_group == Some(true)),
+                        ("min_rule_thickness", self.min_rule_thickness.is_some()),
+                    ],
+                )
+            } else {
+                (
+                    "KaTeX",
+                    [
+                        ("annotate", self.annotate == Some(false)),
+                        ("wrap", self.wrap.is_some()),
+                        ("xml", self.xml == Some(false)),
+                    ],
+                )
+            };
+            if let Some((name, _)) = conflicts.iter().find(|(_, conflicts)| *conflicts) {
+                return Err(Error::InvalidOptions(format!(
+                    "`{name}` is not supported by {renderer}, which renders the selected output type"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the math is rendered in display mode.
+    pub(crate) fn is_display_mode(&self) -> bool {
+        self.display_mode == Some(true)
+    }
+
+    /// Whether [`throw_on_error`](Opts::set_throw_on_error) was set.
+    pub(crate) fn throw_on_error_is_set(&self) -> bool {
+        self.throw_on_error.is_some()
+    }
+
+    /// Whether one of the floating point fields is `NaN`, making the options
+    /// unequal to themselves.
+    #[cfg(feature = "cache")]
+    pub(crate) fn has_nan(&self) -> bool {
+        self.min_rule_thickness.is_some_and(f64::is_nan)
+            || self.max_size.flatten().is_some_and(f64::is_nan)
+            || self
+                .fleqn_indent
+                .is_some_and(|indent| indent.value().is_nan())
+    }
+
     /// Whether the output type is MathML only (allowing usage of Temml).
     pub(crate) fn is_mathml_only(&self) -> bool {
         self.output_type == Some(OutputType::Mathml)
@@ -128,6 +369,23 @@ impl Opts {
         self.fleqn = Some(flag);
     }
 
+    /// Set the left margin of left‑aligned display equations.
+    ///
+    /// Only applies in display mode with [`fleqn`](Opts::set_fleqn) enabled,
+    /// where it is added as an inline `margin-left` on the `katex-display`
+    /// wrapper. It layers on top of the `padding-left` KaTeX's CSS already
+    /// applies to `fleqn` equations rather than replacing it.
+    pub fn set_fleqn_indent(&mut self, indent: Length) {
+        self.fleqn_indent = Some(indent);
+    }
+
+    /// The margin to apply to the display wrapper, if any.
+    pub(crate) fn fleqn_indent(&self) -> Option<Length> {
+        (self.display_mode == Some(true) && self.fleqn == Some(true))
+            .then_some(self.fleqn_indent)
+            .flatten()
+    }
+
     /// Set whether invalid LaTeX triggers a hard error.
     pub fn set_throw_on_error(&mut self, flag: bool) {
         self.throw_on_error = Some(flag);
@@ -144,6 +402,12 @@ impl Opts {
         self.macros.insert(entry_name, entry_data);
     }
 
+    /// The custom macros, by name.
+    #[cfg(any(test, feature = "lazy-extensions"))]
+    pub(crate) fn macro_definitions(&self) -> &HashMap<String, String> {
+        &self.macros
+    }
+
     /// Set the minimum thickness (in `em`) for fraction lines, `\rule`, etc.
     pub fn set_min_rule_thickness(&mut self, value: f64) {
         self.min_rule_thickness = Some(value);
@@ -157,6 +421,34 @@ impl Opts {
         self.max_size = Some(value);
     }
 
+    /// Set the minimum thickness for fraction lines, `\rule`, etc., like
+    /// [`set_min_rule_thickness`](Opts::set_min_rule_thickness) with the unit
+    /// spelled out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use katex::opts::Em;
+    ///
+    /// let mut opts = katex::Opts::default();
+    /// opts.set_min_rule_thickness_em(Em(0.08));
+    /// ```
+    pub fn set_min_rule_thickness_em(&mut self, thickness: Em) {
+        self.set_min_rule_thickness(thickness.0);
+    }
+
+    /// Set the max size for user‑specified sizes, like
+    /// [`set_max_size`](Opts::set_max_size) with the unit spelled out.
+    pub fn set_max_size_em(&mut self, size: Option<Em>) {
+        self.set_max_size(size.map(|size| size.0));
+    }
+
+    /// Set the max size for user‑specified sizes, given in `em` or in points,
+    /// see [`MaxSize`] for the conversion.
+    pub fn set_max_size_typed(&mut self, size: Option<MaxSize>) {
+        self.set_max_size(size.map(MaxSize::to_em));
+    }
+
     /// Set the limit for macro expansion depth. Prevents runaway recursion.
     ///
     /// * `Some(Some(n))` – Explicit finite limit.
@@ -166,14 +458,393 @@ impl Opts {
         self.max_expand = Some(value);
     }
 
+    /// Set a policy deriving the macro expansion limit from the input.
+    ///
+    /// Right before rendering, `policy` is called with the length of the input
+    /// in bytes and its result is sent as `maxExpand`, overriding any value
+    /// set through [`set_max_expand`](Opts::set_max_expand). This lets small
+    /// inputs run under a tight limit while large legitimate inputs get more
+    /// room. [`scaled_max_expand`] is a ready-made policy.
+    pub fn set_adaptive_max_expand(&mut self, policy: fn(usize) -> i32) {
+        self.adaptive_max_expand = Some(MaxExpandPolicy(policy));
+    }
+
     /// Set whether to trust user input for potentially unsafe commands.
     ///
     /// Controls sanitization of constructs like `\url{}` and raw HTML. Keep
     /// `false` for untrusted input sources.
+    ///
+    /// Trusting everything also enables `\includegraphics`, which embeds an
+    /// `<img>` loading an arbitrary URL when the page is viewed: input can
+    /// then track readers or make their browsers send requests to any host.
+    /// To allow images only, trust just `\includegraphics` with
+    /// [`set_trusted_commands`](Opts::set_trusted_commands), ideally together
+    /// with [allowed protocols](Opts::set_allowed_protocols), or decide per
+    /// URL in a [trust callback](Opts::set_trust_callback).
+    #[deprecated(note = "use `set_trust_policy` with `TrustPolicy::All` or `TrustPolicy::None`")]
     pub fn set_trust(&mut self, flag: bool) {
         self.trust = Some(flag);
     }
 
+    /// Set which commands needing trust to allow, replacing any trust setting
+    /// made before (including through [`set_trust_callback`],
+    /// [`set_allowed_protocols`] and [`set_trusted_commands`]).
+    ///
+    /// See [`TrustPolicy`] for the commands concerned.
+    ///
+    /// [`set_trust_callback`]: Opts::set_trust_callback
+    /// [`set_allowed_protocols`]: Opts::set_allowed_protocols
+    /// [`set_trusted_commands`]: Opts::set_trusted_commands
+    pub fn set_trust_policy(&mut self, policy: TrustPolicy) {
+        let (trust, callback, protocols, commands) = policy.into_fields();
+        self.trust = trust;
+        self.trust_callback = callback;
+        self.allowed_protocols = protocols;
+        self.trusted_commands = commands;
+    }
+
+    /// Set a policy deciding for each command needing trust (e.g. `\href`,
+    /// `\includegraphics`, `\htmlClass`) whether to allow it, based on the
+    /// command and the URL it refers to.
+    ///
+    /// Takes precedence over [`set_trust`](Opts::set_trust). The policy runs
+    /// synchronously during rendering on the rendering thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use katex::opts::TrustContext;
+    ///
+    /// let opts = katex::Opts::builder()
+    ///     .trust_callback(|context: &TrustContext| context.protocol.as_deref() == Some("https"))
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\href{https://katex.org}{KaTeX}", &opts).unwrap();
+    /// assert!(html.contains("<a href="));
+    /// ```
+    pub fn set_trust_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(&TrustContext) -> bool + Send + Sync + 'static,
+    {
+        self.trust_callback = Some(callback.into());
+    }
+
+    /// Set the URL protocols (e.g. `https`, `mailto`) to trust in commands
+    /// referring to a URL (`\href`, `\url`, `\includegraphics`), rejecting
+    /// any other protocol. An empty list trusts no URL at all. Relative URLs
+    /// have the pseudo-protocol `_relative` in KaTeX.
+    ///
+    /// Commands without URL (e.g. `\htmlClass`) remain governed by
+    /// [`set_trust`](Opts::set_trust): with `trust(true)` they are trusted,
+    /// otherwise they are not, unless [trusted
+    /// commands](Opts::set_trusted_commands) are set. A [trust
+    /// callback](Opts::set_trust_callback) takes precedence over this list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .allowed_protocols(vec!["https".to_owned(), "mailto".to_owned()])
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\href{mailto:a@b.c}{mail}", &opts).unwrap();
+    /// assert!(html.contains(r#"<a href="mailto:a@b.c">"#));
+    /// let html = katex::render_with_opts(r"\href{ftp://b.c}{file}", &opts).unwrap();
+    /// assert!(!html.contains("<a "));
+    /// ```
+    pub fn set_allowed_protocols<I, S>(&mut self, protocols: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_protocols = Some(protocols.into_iter().map(Into::into).collect());
+    }
+
+    /// Set the commands (e.g. `\htmlId`, `\htmlClass`) to trust, rejecting
+    /// any other command needing trust. The leading backslash may be
+    /// omitted. An empty list trusts no command at all.
+    ///
+    /// Takes precedence over [`set_trust`](Opts::set_trust). When
+    /// [allowed protocols](Opts::set_allowed_protocols) are set as well, a
+    /// listed command referring to a URL is only trusted if the protocol of
+    /// the URL is allowed too. A [trust callback](Opts::set_trust_callback)
+    /// takes precedence over this list.
+    ///
+    /// Trusting `\includegraphics` lets input embed images from any URL
+    /// allowed by the protocols, see [`set_trust`](Opts::set_trust).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .trusted_commands(vec![r"\htmlId".to_owned(), r"\htmlClass".to_owned()])
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\htmlId{eq}{x}", &opts).unwrap();
+    /// assert!(html.contains(r#"id="eq""#));
+    /// let html = katex::render_with_opts(r"\href{https://katex.org}{KaTeX}", &opts).unwrap();
+    /// assert!(!html.contains("<a "));
+    /// ```
+    pub fn set_trusted_commands<I, S>(&mut self, commands: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.trusted_commands = Some(commands.into_iter().map(Into::into).collect());
+    }
+
+    /// Set how to handle input which KaTeX accepts but LaTeX would not, such
+    /// as Unicode text in math mode.
+    ///
+    /// * [`Strictness::Ignore`] – Accept such input silently.
+    /// * [`Strictness::Warn`] – Accept such input and log a warning to the JS
+    ///   console. The JS engines embedded by this crate have no console output
+    ///   visible from Rust, so the warning is discarded unless rendering with
+    ///   [`render_with_warnings`](crate::render_with_warnings).
+    /// * [`Strictness::Error`] – Reject such input with a parse error,
+    ///   returned as [`Error::ParseError`] or
+    ///   rendered in [`error_color`](Opts::set_error_color), depending on
+    ///   [`throw_on_error`](Opts::set_throw_on_error).
+    ///
+    /// When unset, KaTeX's default (`"warn"`) applies. Temml (with the `temml`
+    /// feature) has no warnings: `Error` maps to its `strict: true` and the
+    /// other variants to `strict: false`.
+    pub fn set_strict(&mut self, strictness: Strictness) {
+        self.strict = Some(strictness);
+    }
+
+    /// Set whether `\color` takes the colored content as an argument, like
+    /// `\textcolor`, instead of applying to everything that follows it in the
+    /// current group (LaTeX behavior, the default).
+    pub fn set_color_is_text_color(&mut self, flag: bool) {
+        self.color_is_text_color = Some(flag);
+    }
+
+    /// Set whether the input is parsed in the global group rather than in a
+    /// group of its own, so that local definitions (e.g. `\def`) behave like
+    /// global ones.
+    ///
+    /// This only affects a single render call: KaTeX could keep such
+    /// definitions in the `macros` object passed to it, but a fresh object is
+    /// built from [`Opts::add_macro`] for every render, so nothing defined
+    /// while rendering one input is visible when rendering the next one. Use
+    /// [`render_stateful`](crate::render_stateful) to carry them forward.
+    pub fn set_global_group(&mut self, flag: bool) {
+        self.global_group = Some(flag);
+    }
+
+    /// Set whether to copy the source LaTeX into the `alttext` attribute of
+    /// the `<math>` element, a plain-text fallback used by screen readers and
+    /// consumers unable to render MathML.
+    ///
+    /// Only has an effect when the output contains MathML.
+    pub fn set_mathml_alttext(&mut self, flag: bool) {
+        self.mathml_alttext = Some(flag);
+    }
+
+    /// Set the `aria-label` of the `<math>` element, which screen readers
+    /// announce instead of reading out the MathML.
+    ///
+    /// The label is XML-escaped. It is not applied if the element already has
+    /// an `aria-label`. Only has an effect when the output contains MathML;
+    /// the `alttext` set by [`set_mathml_alttext`](Opts::set_mathml_alttext)
+    /// still holds the source LaTeX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .math_aria_label("x squared")
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts("x^2", &opts).unwrap();
+    /// assert!(html.contains(r#"aria-label="x squared""#));
+    /// ```
+    pub fn set_math_aria_label(&mut self, label: String) {
+        self.math_aria_label = Some(label);
+    }
+
+    /// The `aria-label` to set on the `<math>` element in post-processing.
+    pub(crate) fn math_aria_label(&self) -> Option<&str> {
+        self.math_aria_label.as_deref()
+    }
+
+    /// Set whether MathML output is normalized into a canonical form, so that
+    /// KaTeX and Temml (with the `temml` feature) produce the same markup for
+    /// common constructs.
+    ///
+    /// The following differences are reconciled on every `<math>` element:
+    /// * the `xmlns` MathML namespace is always present;
+    /// * presentational `class` / `style` attributes of `<math>` and Temml's
+    ///   `tml-*` classes are removed;
+    /// * attributes are sorted by name;
+    /// * the `<semantics>` wrapper and source `<annotation>` are removed (use
+    ///   [`set_mathml_alttext`](Opts::set_mathml_alttext) to keep the source);
+    /// * `<mrow>` elements directly inside `<math>` are unwrapped, which
+    ///   covers both KaTeX's single outer row and Temml's line‑break groups;
+    /// * for [`OutputType::Mathml`], KaTeX's wrapper `<span>` is removed so the
+    ///   output is the bare `<math>` element, as with Temml.
+    ///
+    /// Remaining differences include Temml's use of Unicode mathematical
+    /// alphanumeric characters where KaTeX uses `mathvariant`, colors (Temml
+    /// uses `style`, KaTeX `<mstyle mathcolor>`), operator attributes like
+    /// `stretchy` / `fence`, and the grouping of nested rows.
+    pub fn set_normalize_backend_output(&mut self, flag: bool) {
+        self.normalize_backend_output = Some(flag);
+    }
+
+    /// Set whether Unicode mathematical alphanumeric characters in MathML
+    /// identifiers and numbers (e.g. `𝑥`, `𝐱`, `ℬ`, `𝟏`) are replaced by their
+    /// ASCII counterpart plus an explicit `mathvariant` attribute.
+    ///
+    /// Temml in particular emits these characters, which many fonts lack.
+    /// Only `<mi>` / `<mn>` elements whose characters all share one variant
+    /// and which have no `mathvariant` yet are rewritten; Greek letters are
+    /// left unchanged.
+    pub fn set_ascii_mathvariant(&mut self, flag: bool) {
+        self.ascii_mathvariant = Some(flag);
+    }
+
+    /// Set whether to insert `<wbr>` line break opportunities after the
+    /// top-level binary operators and relations (e.g. `+`, `=`) of inline
+    /// formulas, so long formulas wrap instead of overflowing narrow screens.
+    ///
+    /// A heuristic on the HTML produced by KaTeX, the counterpart of Temml's
+    /// `wrap` option: operators nested in groups, e.g. inside `\frac` or
+    /// braces, get no break. Display mode formulas and
+    /// MathML-only output are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .output_type(katex::OutputType::Html)
+    ///     .line_break_hints(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts("a + b = c", &opts).unwrap();
+    /// assert_eq!(html.matches("<wbr>").count(), 2);
+    /// ```
+    pub fn set_line_break_hints(&mut self, flag: bool) {
+        self.line_break_hints = Some(flag);
+    }
+
+    /// Set whether to remove the outermost `<span class="katex">` wrapper from
+    /// the output, keeping the markup inside it, to save space in documents
+    /// with many formulas.
+    ///
+    /// See [`strip_wrapper`](crate::postprocess::strip_wrapper) for details,
+    /// in particular regarding the stylesheet.
+    pub fn set_unwrap_outer(&mut self, flag: bool) {
+        self.unwrap_outer = Some(flag);
+    }
+
+    /// Set whether the source LaTeX is embedded into the HTML output, so
+    /// scripts (e.g. copy handlers in the spirit of KaTeX's `copy-tex`) can
+    /// recover it with [`extract_source`](crate::postprocess::extract_source).
+    ///
+    /// The source is added, HTML-escaped, as a `data-tex` attribute of the
+    /// `<span class="katex-html">` element, which survives
+    /// [`set_unwrap_outer`](Opts::set_unwrap_outer). Output whose MathML
+    /// already carries the source in an `<annotation>` is left unchanged, as
+    /// is output without HTML part ([`OutputType::Mathml`], errors).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .output_type(katex::OutputType::Html)
+    ///     .embed_source(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts("a < b", &opts).unwrap();
+    /// assert!(html.contains(r#"data-tex="a &lt; b""#));
+    /// ```
+    pub fn set_embed_source(&mut self, flag: bool) {
+        self.embed_source = Some(flag);
+    }
+
+    /// Set whether to reject the `\ce` and `\pu` commands of the mhchem
+    /// extension, e.g. in sandboxed contexts, although it is compiled in.
+    ///
+    /// Using them then fails like any invalid input, with an
+    /// [`Error::ParseError`] saying the command is disabled, or an inline
+    /// error with `throw_on_error(false)`. Custom macros named `\ce` or
+    /// `\pu` are left alone. With the `lazy-extensions` feature, the
+    /// extension is not loaded for such renders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder().disable_mhchem(true).build().unwrap();
+    /// assert!(katex::render_with_opts(r"\ce{H2O}", &opts).is_err());
+    /// ```
+    #[cfg(feature = "mhchem")]
+    pub fn set_disable_mhchem(&mut self, flag: bool) {
+        self.disable_mhchem = Some(flag);
+    }
+
+    /// Whether the commands of the mhchem extension are rejected.
+    #[cfg(all(feature = "mhchem", feature = "lazy-extensions"))]
+    pub(crate) fn is_mhchem_disabled(&self) -> bool {
+        self.disable_mhchem == Some(true)
+    }
+
+    /// Whether the source should be embedded in post-processing.
+    pub(crate) fn wants_embed_source(&self) -> bool {
+        self.embed_source == Some(true)
+    }
+
+    /// Whether line break opportunities should be inserted in post-processing.
+    pub(crate) fn wants_line_break_hints(&self) -> bool {
+        self.line_break_hints == Some(true) && self.display_mode != Some(true)
+    }
+
+    /// Whether the outer wrapper should be removed in post-processing.
+    pub(crate) fn wants_unwrap_outer(&self) -> bool {
+        self.unwrap_outer == Some(true)
+    }
+
+    /// Whether math alphanumerics should be replaced in post-processing.
+    pub(crate) fn wants_ascii_mathvariant(&self) -> bool {
+        self.ascii_mathvariant == Some(true)
+    }
+
+    /// Whether MathML should be normalized in post-processing.
+    pub(crate) fn wants_normalized_output(&self) -> bool {
+        self.normalize_backend_output == Some(true)
+    }
+
+    /// Whether the `alttext` attribute should be added in post-processing.
+    pub(crate) fn wants_mathml_alttext(&self) -> bool {
+        self.mathml_alttext == Some(true)
+    }
+
+    /// Set whether inline fractions are rendered at display size.
+    ///
+    /// When enabled and the math is not in display mode, every `\frac` in the
+    /// input is rewritten into `\dfrac` before rendering. Fractions nested in
+    /// the numerator or denominator of another fraction are left as `\frac`
+    /// unless [`set_big_inline_fractions_nested`](Opts::set_big_inline_fractions_nested)
+    /// is enabled as well. Explicit `\dfrac` and `\tfrac` are never rewritten,
+    /// so `\tfrac` keeps forcing a small fraction. `\over` and `\genfrac` are
+    /// not recognized.
+    pub fn set_big_inline_fractions(&mut self, flag: bool) {
+        self.big_inline_fractions = Some(flag);
+    }
+
+    /// Set whether [`set_big_inline_fractions`](Opts::set_big_inline_fractions)
+    /// also applies to nested fractions.
+    pub fn set_big_inline_fractions_nested(&mut self, flag: bool) {
+        self.big_inline_fractions_nested = Some(flag);
+    }
+
+    /// If inline fractions should be rewritten, whether to do so recursively.
+    pub(crate) fn big_inline_fractions(&self) -> Option<bool> {
+        (self.big_inline_fractions == Some(true) && self.display_mode != Some(true))
+            .then_some(self.big_inline_fractions_nested == Some(true))
+    }
+
     /// Temml-specific: add an annotation with the source LaTeX inside the
     /// generated MathML (facilitates copy/paste fidelity and debugging).
     #[cfg(feature = "temml")]
@@ -193,18 +864,21 @@ impl Opts {
         self.xml = Some(flag);
     }
 
-    pub(crate) fn to_js_value<'a, E>(&self, engine: &'a E) -> Result<E::JsValue<'a>>
+    /// Serialize the options for rendering `input` into a JS object.
+    pub(crate) fn to_js_value<'a, E>(&self, engine: &'a E, input: &str) -> Result<E::JsValue<'a>>
     where
         E: JsEngine,
     {
         let mut opt: HashMap<String, E::JsValue<'a>> = HashMap::new();
+        // Options Temml does not know are left out when it renders.
+        let katex = !(cfg!(feature = "temml") && self.is_mathml_only());
         if let Some(display_mode) = self.display_mode {
             opt.insert(
                 "displayMode".to_owned(),
                 engine.create_bool_value(display_mode)?,
             );
         }
-        if let Some(output_type) = self.output_type {
+        if let Some(output_type) = self.output_type.filter(|_| katex) {
             opt.insert(
                 "output".to_owned(),
                 engine.create_string_value(output_type.to_string())?,
@@ -213,7 +887,7 @@ impl Opts {
         if let Some(leqno) = self.leqno {
             opt.insert("leqno".to_owned(), engine.create_bool_value(leqno)?);
         }
-        if let Some(fleqn) = self.fleqn {
+        if let Some(fleqn) = self.fleqn.filter(|_| katex) {
             opt.insert("fleqn".to_owned(), engine.create_bool_value(fleqn)?);
         }
         if let Some(throw_on_error) = self.throw_on_error {
@@ -240,32 +914,112 @@ impl Opts {
 
             opt.insert("macros".to_owned(), macros);
         }
-        if let Some(min_rule_thickness) = self.min_rule_thickness {
+        if let Some(min_rule_thickness) = self.min_rule_thickness.filter(|_| katex) {
             opt.insert(
                 "minRuleThickness".to_owned(),
                 engine.create_float_value(min_rule_thickness)?,
             );
         }
         if let Some(Some(max_size)) = self.max_size {
-            opt.insert("maxSize".to_owned(), engine.create_float_value(max_size)?);
+            let max_size = if katex {
+                engine.create_float_value(max_size)?
+            } else {
+                // Temml limits sizes in ems and in points separately.
+                let size = [max_size, max_size * 10.0].map(|size| engine.create_float_value(size));
+                process_results(size, |size| engine.create_array_value(size))??
+            };
+            opt.insert("maxSize".to_owned(), max_size);
         }
-        if let Some(max_expand) = self.max_expand {
+        if let Some(MaxExpandPolicy(policy)) = self.adaptive_max_expand {
+            opt.insert(
+                "maxExpand".to_owned(),
+                engine.create_int_value(policy(input.len()))?,
+            );
+        } else if let Some(max_expand) = self.max_expand {
             match max_expand {
                 Some(max_expand) => {
                     opt.insert("maxExpand".to_owned(), engine.create_int_value(max_expand)?);
                 }
                 None => {
-                    opt.insert("maxExpand".to_owned(), engine.create_int_value(i32::MAX)?);
+                    // `Infinity` rather than a large count, so KaTeX never
+                    // stops expanding.
+                    opt.insert(
+                        "maxExpand".to_owned(),
+                        engine.create_float_value(f64::INFINITY)?,
+                    );
                 }
             }
         }
         if let Some(trust) = self.trust {
             opt.insert("trust".to_owned(), engine.create_bool_value(trust)?);
         }
+        if let Some(TrustCallback(callback)) = &self.trust_callback {
+            let callback = Arc::clone(callback);
+            engine.register_callback(
+                "katexTrustCallback",
+                Box::new(move |args| {
+                    let arg = |i: usize| args.get(i).filter(|arg| !arg.is_empty()).cloned();
+                    let context = TrustContext {
+                        command: arg(0).unwrap_or_default(),
+                        url: arg(1),
+                        protocol: arg(2),
+                    };
+                    callback(&context).to_string()
+                }),
+            )?;
+            opt.insert("trustCallback".to_owned(), engine.create_bool_value(true)?);
+        }
+        if let Some(protocols) = &self.allowed_protocols {
+            // KaTeX reports protocols in lowercase.
+            let protocols = protocols
+                .iter()
+                .map(|protocol| engine.create_string_value(protocol.to_lowercase()));
+            let protocols =
+                process_results(protocols, |protocols| engine.create_array_value(protocols))??;
+            opt.insert("allowedProtocols".to_owned(), protocols);
+        }
+        if let Some(commands) = &self.trusted_commands {
+            let commands = commands.iter().map(|command| {
+                let command = command.strip_prefix('\\').unwrap_or(command);
+                engine.create_string_value(format!("\\{command}"))
+            });
+            let commands =
+                process_results(commands, |commands| engine.create_array_value(commands))??;
+            opt.insert("trustedCommands".to_owned(), commands);
+        }
+        if let Some(strict) = self.strict {
+            // Temml only knows a boolean `strict`, where `true` means "error".
+            let strict = if katex {
+                engine.create_string_value(strict.to_string())?
+            } else {
+                engine.create_bool_value(strict == Strictness::Error)?
+            };
+            opt.insert("strict".to_owned(), strict);
+        }
+        if let Some(color_is_text_color) = self.color_is_text_color {
+            opt.insert(
+                "colorIsTextColor".to_owned(),
+                engine.create_bool_value(color_is_text_color)?,
+            );
+        }
+        if let Some(global_group) = self.global_group.filter(|_| katex) {
+            opt.insert(
+                "globalGroup".to_owned(),
+                engine.create_bool_value(global_group)?,
+            );
+        }
+
+        #[cfg(feature = "mhchem")]
+        if let Some(disable_mhchem) = self.disable_mhchem {
+            opt.insert(
+                "disableMhchem".to_owned(),
+                engine.create_bool_value(disable_mhchem)?,
+            );
+        }
 
         #[cfg(feature = "temml")]
         if let Some(annotate) = self.annotate {
-            opt.insert("xml".to_owned(), engine.create_bool_value(annotate)?);
+            opt.insert("annotate".to_owned(), engine.create_bool_value(annotate)?);
         }
 
         #[cfg(feature = "temml")]
@@ -285,6 +1039,357 @@ impl Opts {
     }
 }
 
+/// Built-in policy for [`Opts::set_adaptive_max_expand`].
+///
+/// Allows KaTeX's default of 1000 macro expansions plus 10 per input byte,
+/// capped at 100 000 expansions.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::builder()
+///     .adaptive_max_expand(katex::opts::scaled_max_expand)
+///     .build()
+///     .unwrap();
+/// let html = katex::render_with_opts(r"\def\a{x}\a\a\a", &opts).unwrap();
+/// assert!(html.contains("katex"));
+/// ```
+pub fn scaled_max_expand(input_len: usize) -> i32 {
+    let scaled = input_len.saturating_mul(10).saturating_add(1000);
+    i32::try_from(scaled).map_or(100_000, |scaled| scaled.min(100_000))
+}
+
+/// The CSS named colors, sorted for binary search.
+const CSS_NAMED_COLORS: &[&str] = &[
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peachpuff",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+/// Whether `color` is a CSS color of a form accepted by
+/// [`OptsBuilder::error_color_checked`].
+fn is_css_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 6) && hex.bytes().all(|b| b.is_ascii_hexdigit());
+    }
+    if let Some(args) = color
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<_> = args.split(',').map(str::trim).collect();
+        return channels.len() == 3
+            && channels
+                .iter()
+                .all(|channel| match channel.strip_suffix('%') {
+                    Some(percent) => percent
+                        .parse::<f64>()
+                        .is_ok_and(|p| (0.0..=100.0).contains(&p)),
+                    None => channel.parse::<u8>().is_ok(),
+                });
+    }
+    CSS_NAMED_COLORS
+        .binary_search(&color.to_ascii_lowercase().as_str())
+        .is_ok()
+}
+
+#[cfg(feature = "json")]
+impl Opts {
+    /// Parse options written for KaTeX's JS API as JSON, e.g. taken from an
+    /// existing JS configuration.
+    ///
+    /// The JSON object uses KaTeX's option names (`displayMode`, `output`,
+    /// `throwOnError`, `macros`, ...), as with the `serde` feature, and
+    /// `strict` may also be a boolean as in KaTeX (`true` for `"error"`,
+    /// `false` for `"ignore"`). Unknown keys, such as the `delimiters` of the
+    /// auto-render extension, are ignored; see
+    /// [`from_katex_json_with_warnings`](Opts::from_katex_json_with_warnings)
+    /// to report them. Invalid JSON or values of the wrong type fail with
+    /// [`Error::InvalidOptions`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let json = r#"{ "displayMode": true, "macros": { "\\RR": "\\mathbb{R}" } }"#;
+    /// let opts = katex::Opts::from_katex_json(json).unwrap();
+    /// let html = katex::render_with_opts(r"\RR", &opts).unwrap();
+    /// assert!(html.contains("katex-display"));
+    /// ```
+    pub fn from_katex_json(json: &str) -> Result<Opts> {
+        Self::from_katex_json_with_warnings(json, false).map(|(opts, _)| opts)
+    }
+
+    /// Parse options written for KaTeX's JS API as JSON, like
+    /// [`from_katex_json`](Opts::from_katex_json), also returning a warning
+    /// for each unknown key ignored.
+    ///
+    /// With `strict` set, unknown keys fail with [`Error::InvalidOptions`]
+    /// listing them instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let json = r#"{ "throwOnError": false, "delimiters": [] }"#;
+    /// let (_, warnings) = katex::Opts::from_katex_json_with_warnings(json, false).unwrap();
+    /// assert_eq!(warnings, ["unknown option `delimiters` ignored"]);
+    /// assert!(katex::Opts::from_katex_json_with_warnings(json, true).is_err());
+    /// ```
+    pub fn from_katex_json_with_warnings(json: &str, strict: bool) -> Result<(Opts, Vec<String>)> {
+        use serde_json::Value;
+
+        let invalid = |e: serde_json::Error| Error::InvalidOptions(e.to_string());
+        let mut object: serde_json::Map<String, Value> =
+            serde_json::from_str(json).map_err(invalid)?;
+        let known = json::field_names();
+        let unknown: Vec<String> = object
+            .keys()
+            .filter(|key| !known.contains(&key.as_str()))
+            .cloned()
+            .collect();
+        if strict && !unknown.is_empty() {
+            return Err(Error::InvalidOptions(format!(
+                "unknown options: {}",
+                unknown.join(", ")
+            )));
+        }
+        object.retain(|key, _| known.contains(&key.as_str()));
+        if let Some(Value::Bool(flag)) = object.get("strict") {
+            let strictness = if *flag {
+                Strictness::Error
+            } else {
+                Strictness::Ignore
+            };
+            object.insert("strict".to_owned(), strictness.to_string().into());
+        }
+        let opts = serde_json::from_value(Value::Object(object)).map_err(invalid)?;
+        let warnings = unknown
+            .iter()
+            .map(|key| format!("unknown option `{key}` ignored"))
+            .collect();
+        Ok((opts, warnings))
+    }
+}
+
+/// Support for [`Opts::from_katex_json`].
+#[cfg(feature = "json")]
+mod json {
+    use super::Opts;
+    use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+    /// The keys [`Opts`] deserializes.
+    pub(super) fn field_names() -> &'static [&'static str] {
+        let mut fields: &'static [&'static str] = &[];
+        // Fails once the fields are recorded.
+        let _ = Opts::deserialize(FieldNames(&mut fields));
+        fields
+    }
+
+    /// A deserializer recording the fields of the struct asked for.
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+}
+
+/// (De)serialization of `Option<Option<T>>` fields, telling an absent field
+/// (`None`) apart from an explicit `null` (`Some(None)`).
+#[cfg(feature = "serde")]
+mod double_option {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[allow(clippy::option_option, clippy::ref_option)]
+    pub(super) fn serialize<T, S>(
+        value: &Option<Option<T>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        value.serialize(serializer)
+    }
+
+    #[allow(clippy::option_option)]
+    pub(super) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+}
+
 impl AsRef<Opts> for Opts {
     fn as_ref(&self) -> &Opts {
         self
@@ -319,10 +1424,280 @@ impl OptsBuilder {
         }
         self
     }
+
+    /// Add (chain) every macro mapping of `macros` into the accumulated macro
+    /// table, e.g. from a `Vec`, an array or a filtered map.
+    ///
+    /// Merges with the macros added before, like repeated calls to
+    /// [`add_macro`](Self::add_macro): later mappings overwrite earlier ones
+    /// with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .macros_iter([
+    ///         (r"\RR".to_owned(), r"\mathbb{R}".to_owned()),
+    ///         (r"\NN".to_owned(), r"\mathbb{N}".to_owned()),
+    ///     ])
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\NN \subset \RR", &opts).unwrap();
+    /// ```
+    pub fn macros_iter<I>(mut self, macros: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.macros.get_or_insert_with(HashMap::new).extend(macros);
+        self
+    }
+
+    /// Add the macros declared in `src`, a block of LaTeX `\newcommand` /
+    /// `\renewcommand` declarations as found in a `.sty` file.
+    ///
+    /// Both the `\newcommand{\name}` and `\newcommand\name` forms are
+    /// accepted, with an optional argument count `[n]`, and declarations may
+    /// span several lines. `%` comments between declarations are skipped. As
+    /// with [`add_macro`](Self::add_macro), later definitions overwrite earlier
+    /// ones regardless of the command used. Optional arguments
+    /// (`[n][default]`) are not supported by KaTeX macros and rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ParseError`] pointing at
+    /// the malformed declaration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder()
+    ///     .macros_from_tex(
+    ///         r"
+    ///         \newcommand{\RR}{\mathbb{R}}
+    ///         \newcommand{\norm}[1]{\left\lVert #1 \right\rVert}
+    ///         ",
+    ///     )
+    ///     .unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\norm{x} \in \RR", &opts).unwrap();
+    /// ```
+    pub fn macros_from_tex(mut self, src: &str) -> Result<Self> {
+        for (name, body) in parse_definitions(src)? {
+            self = self.add_macro(name, body);
+        }
+        Ok(self)
+    }
+
+    /// Set the [error color](Self::error_color) after checking it is a CSS
+    /// color KaTeX can use: `#rgb`, `#rrggbb`, `rgb(r, g, b)` with integer
+    /// or percentage channels, or a CSS named color such as `crimson`.
+    ///
+    /// Unlike [`error_color`](Self::error_color), which passes any string on
+    /// to the generated CSS, this catches typos up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidOptions`] if
+    /// `color` has none of the forms above.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut builder = katex::Opts::builder();
+    /// builder.error_color_checked("#cc0000").unwrap();
+    /// assert!(builder.error_color_checked("redd").is_err());
+    /// ```
+    pub fn error_color_checked(&mut self, color: &str) -> Result<&mut Self> {
+        if !is_css_color(color) {
+            return Err(Error::InvalidOptions(format!(
+                "`{color}` is not a supported CSS color"
+            )));
+        }
+        self.error_color = Some(Some(color.to_owned()));
+        Ok(self)
+    }
+
+    /// Limit macro expansion to `limit` expansions.
+    ///
+    /// Shorthand for `max_expand(Some(limit))`.
+    pub fn max_expand_limit(&mut self, limit: i32) -> &mut Self {
+        self.max_expand = Some(Some(Some(limit)));
+        self
+    }
+
+    /// Remove the limit on macro expansion (use with care!).
+    ///
+    /// Shorthand for `max_expand(None)`, sent to KaTeX as `Infinity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opts = katex::Opts::builder().max_expand_unlimited().build().unwrap();
+    /// let html = katex::render_with_opts(r"\def\a{x}\a\a", &opts).unwrap();
+    /// ```
+    pub fn max_expand_unlimited(&mut self) -> &mut Self {
+        self.max_expand = Some(Some(None));
+        self
+    }
+
+    /// Set a policy deriving the macro expansion limit from the input, see
+    /// [`Opts::set_adaptive_max_expand`].
+    pub fn adaptive_max_expand(&mut self, policy: fn(usize) -> i32) -> &mut Self {
+        self.adaptive_max_expand = Some(Some(MaxExpandPolicy(policy)));
+        self
+    }
+
+    /// Set which commands needing trust to allow, replacing any trust setting
+    /// made before, see [`Opts::set_trust_policy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use katex::opts::TrustPolicy;
+    ///
+    /// let opts = katex::Opts::builder()
+    ///     .trust_policy(TrustPolicy::Protocols(vec!["https".to_owned()]))
+    ///     .build()
+    ///     .unwrap();
+    /// let html = katex::render_with_opts(r"\href{https://katex.org}{KaTeX}", &opts).unwrap();
+    /// assert!(html.contains("<a href="));
+    /// ```
+    pub fn trust_policy(&mut self, policy: TrustPolicy) -> &mut Self {
+        let (trust, callback, protocols, commands) = policy.into_fields();
+        self.trust = Some(trust);
+        self.trust_callback = Some(callback);
+        self.allowed_protocols = Some(protocols);
+        self.trusted_commands = Some(commands);
+        self
+    }
+}
+
+/// An [adaptive macro expansion policy](Opts::set_adaptive_max_expand).
+#[derive(Clone, Copy, Debug)]
+struct MaxExpandPolicy(fn(usize) -> i32);
+
+impl PartialEq for MaxExpandPolicy {
+    /// Whether both are the same function, by address, see [`Opts`].
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::fn_addr_eq(self.0, other.0)
+    }
+}
+
+/// What a [trust callback](Opts::set_trust_callback) decides about.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrustContext {
+    /// The command needing trust, e.g. `\href`.
+    pub command: String,
+    /// The URL the command refers to, if any.
+    pub url: Option<String>,
+    /// The protocol of the URL as determined by KaTeX, e.g. `https`, if any.
+    pub protocol: Option<String>,
+}
+
+/// A [trust callback](Opts::set_trust_callback).
+#[derive(Clone)]
+pub struct TrustCallback(Arc<dyn Fn(&TrustContext) -> bool + Send + Sync>);
+
+impl<F> From<F> for TrustCallback
+where
+    F: Fn(&TrustContext) -> bool + Send + Sync + 'static,
+{
+    fn from(callback: F) -> Self {
+        TrustCallback(Arc::new(callback))
+    }
+}
+
+impl PartialEq for TrustCallback {
+    /// Whether both are clones of the same callback.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for TrustCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TrustCallback(..)")
+    }
+}
+
+/// Which commands needing trust to allow, see [`Opts::set_trust_policy`].
+///
+/// KaTeX only asks for trust before rendering commands which can make the
+/// output link to or load other resources, or set raw HTML attributes:
+/// `\href`, `\url`, `\includegraphics`, `\htmlClass`, `\htmlId`,
+/// `\htmlStyle` and `\htmlData`. Any other command is always available:
+/// in particular `\color`, `\textcolor`, `\colorbox` and `\fcolorbox`
+/// still render with [`TrustPolicy::None`], so colored math does not require
+/// trusting the input. An untrusted command is rendered as its name in the
+/// [error color](Opts::set_error_color).
+///
+/// # Examples
+///
+/// ```
+/// use katex::opts::TrustPolicy;
+///
+/// let opts = katex::Opts::builder().trust_policy(TrustPolicy::None).build().unwrap();
+/// let html = katex::render_with_opts(r"\textcolor{red}{x}", &opts).unwrap();
+/// assert!(html.contains("color:red"));
+/// let html = katex::render_with_opts(r"\href{https://katex.org}{x}", &opts).unwrap();
+/// assert!(!html.contains("<a "));
+/// ```
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrustPolicy {
+    /// Trust no command, KaTeX's default.
+    None,
+    /// Trust every command. Only suitable for input from trusted sources,
+    /// see [`set_trust`](Opts::set_trust).
+    All,
+    /// Trust commands referring to a URL with one of these protocols (e.g.
+    /// `https`), see [`set_allowed_protocols`](Opts::set_allowed_protocols).
+    /// Commands without URL are not trusted.
+    Protocols(Vec<String>),
+    /// Trust these commands (e.g. `\htmlId`) whatever their URL, see
+    /// [`set_trusted_commands`](Opts::set_trusted_commands).
+    Commands(Vec<String>),
+    /// Decide for each command, see
+    /// [`set_trust_callback`](Opts::set_trust_callback).
+    Custom(TrustCallback),
+}
+
+impl TrustPolicy {
+    /// Create a [`TrustPolicy::Custom`] policy from a closure.
+    pub fn custom<F>(callback: F) -> Self
+    where
+        F: Fn(&TrustContext) -> bool + Send + Sync + 'static,
+    {
+        TrustPolicy::Custom(callback.into())
+    }
+
+    /// The values of the `trust`, `trust_callback`, `allowed_protocols` and
+    /// `trusted_commands` options implementing the policy.
+    #[allow(clippy::type_complexity)]
+    fn into_fields(
+        self,
+    ) -> (
+        Option<bool>,
+        Option<TrustCallback>,
+        Option<Vec<String>>,
+        Option<Vec<String>>,
+    ) {
+        let trust = Some(self == TrustPolicy::All);
+        match self {
+            TrustPolicy::None | TrustPolicy::All => (trust, None, None, None),
+            TrustPolicy::Protocols(protocols) => (trust, None, Some(protocols), None),
+            TrustPolicy::Commands(commands) => (trust, None, None, Some(commands)),
+            TrustPolicy::Custom(callback) => (trust, Some(callback), None, None),
+        }
+    }
 }
 
 /// Output type from KaTeX.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub enum OutputType {
     /// Outputs KaTeX in HTML only.
     Html,
@@ -342,14 +1717,234 @@ impl fmt::Display for OutputType {
     }
 }
 
+impl FromStr for OutputType {
+    type Err = ParseOutputTypeError;
+
+    /// Parse the names emitted by [`Display`](fmt::Display), ignoring case.
+    ///
+    /// ```
+    /// use katex::OutputType;
+    ///
+    /// assert_eq!("htmlAndMathml".parse(), Ok(OutputType::HtmlAndMathml));
+    /// assert_eq!("MathML".parse(), Ok(OutputType::Mathml));
+    /// assert!("svg".parse::<OutputType>().is_err());
+    /// ```
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        [
+            OutputType::Html,
+            OutputType::Mathml,
+            OutputType::HtmlAndMathml,
+        ]
+        .into_iter()
+        .find(|output_type| output_type.to_string().eq_ignore_ascii_case(s))
+        .ok_or_else(|| ParseOutputTypeError(s.to_owned()))
+    }
+}
+
+/// Error returned when parsing an unknown [`OutputType`].
+#[derive(thiserror::Error, Clone, Debug, Eq, PartialEq)]
+#[error("unknown output type `{0}`, expected `html`, `mathml` or `htmlAndMathml`")]
+pub struct ParseOutputTypeError(String);
+
+/// Strictness of KaTeX towards input which is not valid LaTeX.
+///
+/// See [`Opts::set_strict`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Strictness {
+    /// Allow non-LaTeX features silently.
+    Ignore,
+    /// Allow non-LaTeX features, warning on the JS console.
+    Warn,
+    /// Reject non-LaTeX features with a parse error.
+    Error,
+}
+
+impl fmt::Display for Strictness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strictness::Ignore => "ignore",
+            Strictness::Warn => "warn",
+            Strictness::Error => "error",
+        })
+    }
+}
+
+/// A size in `em`, i.e. relative to the font size, as taken by KaTeX's size
+/// options.
+///
+/// Converts from and into a plain `f64`, so it is also accepted by the
+/// [builder](OptsBuilder::min_rule_thickness) setters taking one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Em(pub f64);
+
+impl From<f64> for Em {
+    fn from(value: f64) -> Self {
+        Em(value)
+    }
+}
+
+impl From<Em> for f64 {
+    fn from(value: Em) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for Em {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}em", self.0)
+    }
+}
+
+/// A [max size](Opts::set_max_size) for user-specified sizes, in `em` or
+/// in points.
+///
+/// KaTeX takes the limit in `em`, so sizes in points are converted with an
+/// explicit number of points per `em`, the font size the formulas are
+/// typeset at: KaTeX's own base size is 10pt, while a page with CSS
+/// `font-size: 12pt` has 12 points per `em`. Converts into the `f64` (and
+/// `Option<f64>`) taken by the [builder](OptsBuilder::max_size) setter.
+///
+/// # Examples
+///
+/// ```
+/// use katex::opts::MaxSize;
+///
+/// assert_eq!(MaxSize::Points(36.0, 12.0).to_em(), 3.0);
+/// let opts = katex::Opts::builder()
+///     .max_size(MaxSize::Points(36.0, 12.0))
+///     .build()
+///     .unwrap();
+/// ```
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MaxSize {
+    /// A size in `em`.
+    Em(f64),
+    /// A size in points (`pt`), followed by the number of points per `em`.
+    Points(f64, f64),
+}
+
+impl MaxSize {
+    /// A size of `points`, at `points_per_em` points per `em`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidOptions`] if `points_per_em` is not positive
+    /// and finite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use katex::opts::MaxSize;
+    ///
+    /// assert_eq!(MaxSize::points(36.0, 12.0).unwrap().to_em(), 3.0);
+    /// assert!(MaxSize::points(36.0, 0.0).is_err());
+    /// ```
+    pub fn points(points: f64, points_per_em: f64) -> Result<Self> {
+        if !is_valid_points_per_em(points_per_em) {
+            return Err(Error::InvalidOptions(format!(
+                "points per em must be positive and finite, not {points_per_em}"
+            )));
+        }
+        Ok(MaxSize::Points(points, points_per_em))
+    }
+
+    /// The size in `em`.
+    ///
+    /// `NaN`, which [`Opts::validate`] rejects, for [`MaxSize::Points`] whose
+    /// number of points per `em` is not positive and finite.
+    pub fn to_em(self) -> f64 {
+        match self {
+            MaxSize::Em(size) => size,
+            MaxSize::Points(points, points_per_em) if is_valid_points_per_em(points_per_em) => {
+                points / points_per_em
+            }
+            MaxSize::Points(..) => f64::NAN,
+        }
+    }
+}
+
+/// Whether `points_per_em` is a usable font size, see [`MaxSize::Points`].
+fn is_valid_points_per_em(points_per_em: f64) -> bool {
+    points_per_em.is_finite() && points_per_em > 0.0
+}
+
+impl From<Em> for MaxSize {
+    fn from(value: Em) -> Self {
+        MaxSize::Em(value.0)
+    }
+}
+
+impl From<MaxSize> for f64 {
+    fn from(value: MaxSize) -> Self {
+        value.to_em()
+    }
+}
+
+impl From<MaxSize> for Option<f64> {
+    fn from(value: MaxSize) -> Self {
+        Some(value.to_em())
+    }
+}
+
+/// A CSS length.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Length {
+    /// Relative to the font size of the element (`em`).
+    Em(f64),
+    /// Relative to the font size of the root element (`rem`).
+    Rem(f64),
+    /// CSS pixels (`px`).
+    Px(f64),
+    /// Points (`pt`).
+    Pt(f64),
+    /// Percentage of the containing block (`%`).
+    Percent(f64),
+}
+
+impl Length {
+    /// The number of units.
+    #[cfg(feature = "cache")]
+    fn value(self) -> f64 {
+        match self {
+            Length::Em(value)
+            | Length::Rem(value)
+            | Length::Px(value)
+            | Length::Pt(value)
+            | Length::Percent(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Em(value) => write!(f, "{value}em"),
+            Length::Rem(value) => write!(f, "{value}rem"),
+            Length::Px(value) => write!(f, "{value}px"),
+            Length::Pt(value) => write!(f, "{value}pt"),
+            Length::Percent(value) => write!(f, "{value}%"),
+        }
+    }
+}
+
 /// Wrap mode for Temml.
 #[non_exhaustive]
 #[cfg(feature = "temml")]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum WrapMode {
     /// Soft line break after every top-level relation and binary operator.
     Tex,
     /// Soft line break after every top-level `=` except for the first.
+    #[cfg_attr(feature = "serde", serde(rename = "="))]
     Equals,
     /// No soft line breaks.
     None,
@@ -0,0 +1,112 @@
+//! Access to KaTeX's parse tree for tooling such as linters.
+//!
+//! [`parse_tree`] returns the tree KaTeX builds from an input, serialized as
+//! JSON, instead of rendering it. [`validate`] only checks that an input
+//! parses, and [`supports_command`] whether KaTeX defines a command.
+
+#[cfg(feature = "lazy-extensions")]
+use crate::extensions;
+use crate::{
+    call_render,
+    error::{Error, Result},
+    js_engine::JsEngine,
+    opts::Opts,
+    preprocess, with_engine,
+};
+use core::iter;
+
+/// Parse LaTeX equation and return KaTeX's parse tree as JSON.
+///
+/// The result is the JSON-serialized array of parse nodes returned by KaTeX's
+/// internal `katex.__parse`. Every node has a `type` (e.g. `"mathord"`,
+/// `"genfrac"`, `"supsub"`) and a `mode`; the remaining fields depend on the
+/// type. Source locations are reduced to `{"start": .., "end": ..}`, offsets
+/// in UTF-16 code units into the input after pre-processing.
+///
+/// The input is parsed with the same [options](`Opts`) as for rendering, so
+/// custom macros are expanded and invalid input fails with
+/// [`Error::ParseError`](crate::Error::ParseError). The tree is always built
+/// by KaTeX, even for output rendered by Temml.
+///
+/// **The tree format is not stable**: it is an internal data structure of
+/// KaTeX and may change with any update of the bundled KaTeX version (see
+/// [`KATEX_VERSION`](crate::KATEX_VERSION)), including patch releases of this
+/// crate.
+///
+/// # Examples
+///
+/// ```
+/// let tree = katex::parse_tree("x", katex::Opts::default()).unwrap();
+/// assert!(tree.starts_with(r#"[{"type":"mathord","mode":"math""#));
+/// ```
+pub fn parse_tree(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    parse_with("katexParseTree", input, opts.as_ref())
+}
+
+/// Check that LaTeX equation parses, without rendering it.
+///
+/// Reports the same errors as [`render_with_opts`](crate::render_with_opts)
+/// with `throw_on_error(true)`, whatever `throw_on_error` is set to in
+/// `opts`, but skips building and serializing the output, which makes it the
+/// cheaper choice for e.g. CI checks over all formulas of a project. As for
+/// [`parse_tree`], the input is always parsed by KaTeX, even for output
+/// rendered by Temml.
+///
+/// # Examples
+///
+/// ```
+/// let opts = katex::Opts::default();
+/// assert!(katex::validate(r"\frac{1}{2}", &opts).is_ok());
+/// assert!(katex::validate(r"\frac{1}", &opts).is_err());
+/// ```
+pub fn validate(input: &str, opts: impl AsRef<Opts>) -> Result<()> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_throw_on_error(true);
+    parse_with("katexValidate", input, &opts).map(drop)
+}
+
+/// Whether KaTeX supports the control sequence `command`, e.g. `\frac`, for
+/// uses such as editor autocompletion. The leading backslash may be omitted.
+///
+/// The command is parsed on its own with the default [options](`Opts`), so
+/// custom macros are not taken into account. A command is supported unless
+/// KaTeX reports it as undefined: commands failing for other reasons, e.g.
+/// because their arguments are missing (`\frac`) or because they are only
+/// allowed in text mode, count as supported. Anything other than a single
+/// control sequence is not supported.
+///
+/// # Examples
+///
+/// ```
+/// assert!(katex::supports_command(r"\frac"));
+/// assert!(katex::supports_command("alpha"));
+/// assert!(!katex::supports_command(r"\nonexistent"));
+/// ```
+pub fn supports_command(command: &str) -> bool {
+    let name = command.strip_prefix('\\').unwrap_or(command);
+    let mut chars = name.chars();
+    let single = match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => chars.all(|c| c.is_ascii_alphabetic()),
+        Some(_) => chars.next().is_none(),
+        None => false,
+    };
+    single
+        && !matches!(
+            validate(&format!("\\{name}"), Opts::default()),
+            Err(Error::UnsupportedCommand { .. }) | Err(Error::JsInitError(..))
+        )
+}
+
+/// Call the JS parse function `name` on `input`.
+pub(crate) fn parse_with(name: &str, input: &str, opts: &Opts) -> Result<String> {
+    with_engine(|engine| {
+        let tex = preprocess::apply(input, opts);
+        #[cfg(feature = "lazy-extensions")]
+        extensions::load_used(engine, &tex, opts)?;
+        let input_js = engine.create_string_value(tex.into_owned())?;
+        let opts_js = opts.to_js_value(engine, input)?;
+        let args = iter::once(input_js).chain(iter::once(opts_js));
+        let result = call_render(engine, name, args)?;
+        engine.value_to_string(result)
+    })
+}
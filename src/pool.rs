@@ -0,0 +1,90 @@
+//! A pool of warmed engines shared by any number of threads.
+
+use crate::{
+    error::{Error, Result},
+    opts::Opts,
+    render_with_opts,
+    worker::WorkerPool,
+};
+use std::sync::{mpsc, Arc};
+
+/// A pool of worker threads, each owning a warmed JS engine, rendering on
+/// behalf of any thread.
+///
+/// Unlike the per-thread engine used by [`render_with_opts`], the number of
+/// engines is bounded by the pool size, whichever threads (e.g. of Rayon or
+/// Tokio) the renders are issued from. Workers are spawned and warmed lazily,
+/// when a render finds none of them available, up to the pool size; renders
+/// beyond that wait for the next available worker.
+///
+/// `KatexPool` is a cheap-to-clone handle; the workers exit once every handle
+/// is dropped and the renders already queued are done.
+///
+/// # Examples
+///
+/// ```
+/// let pool = katex::KatexPool::new(2);
+/// let handles: Vec<_> = (0..4)
+///     .map(|i| {
+///         let pool = pool.clone();
+///         std::thread::spawn(move || pool.render(&format!("x^{i}"), katex::Opts::default()))
+///     })
+///     .collect();
+/// for handle in handles {
+///     assert!(handle.join().unwrap().unwrap().contains("katex"));
+/// }
+/// assert!(pool.size() <= 2);
+/// ```
+#[derive(Clone)]
+pub struct KatexPool {
+    workers: Arc<WorkerPool>,
+}
+
+impl KatexPool {
+    /// Create a pool of up to `size` (at least one) engines, none of which is
+    /// created yet.
+    pub fn new(size: usize) -> Self {
+        Self {
+            workers: Arc::new(WorkerPool::new(size)),
+        }
+    }
+
+    /// Render LaTeX equation to HTML with additional [options](`Opts`) on one
+    /// of the pool's engines, blocking until it is done.
+    pub fn render(&self, input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+        let input = input.to_owned();
+        let opts = opts.as_ref().clone();
+        let (sender, receiver) = mpsc::channel();
+        self.workers.submit(Box::new(move || {
+            let _ = sender.send(render_with_opts(&input, &opts));
+        }));
+        receiver
+            .recv()
+            .map_err(|_| Error::JsExecError("render worker panicked".to_owned(), None))?
+    }
+
+    /// The maximum number of engines.
+    pub fn max_size(&self) -> usize {
+        self.workers.max()
+    }
+
+    /// The number of engines created so far.
+    pub fn size(&self) -> usize {
+        self.workers.spawned()
+    }
+
+    /// The number of engines currently waiting for work.
+    pub fn available(&self) -> usize {
+        self.workers.idle()
+    }
+}
+
+impl core::fmt::Debug for KatexPool {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KatexPool")
+            .field("max_size", &self.max_size())
+            .field("size", &self.size())
+            .field("available", &self.available())
+            .finish()
+    }
+}
@@ -0,0 +1,951 @@
+//! Post-processing applied to the markup produced by KaTeX / Temml.
+//!
+//! Some options cannot be expressed through the upstream JS options and are
+//! instead implemented as plain string transformations on the rendered
+//! fragment. They only ever touch the markup KaTeX / Temml generates, whose
+//! shape is regular enough that no general HTML parser is required.
+//!
+//! Helpers for working with rendered fragments, such as [`extract_mathml`],
+//! [`extract_source`], [`extract_svgs`], [`minify_html`] and
+//! [`prettify_html`], are public.
+
+use crate::opts::Opts;
+
+/// Apply all post-processing steps requested by `opts` to the rendered `html`.
+pub(crate) fn apply(input: &str, opts: &Opts, mut html: String) -> String {
+    if opts.wants_normalized_output() {
+        html = normalize_mathml(&html, opts.is_mathml_only());
+    }
+    if opts.wants_ascii_mathvariant() {
+        html = ascii_mathvariant(&html);
+    }
+    if opts.wants_mathml_alttext() {
+        html = set_attribute(&html, "<math", "alttext", &escape_xml(input));
+    }
+    if let Some(label) = opts.math_aria_label() {
+        html = set_attribute(&html, "<math", "aria-label", &escape_xml(label));
+    }
+    if let Some(indent) = opts.fleqn_indent() {
+        html = set_attribute(
+            &html,
+            r#"<span class="katex-display"#,
+            "style",
+            &format!("margin-left:{indent}"),
+        );
+    }
+    if opts.wants_line_break_hints() {
+        html = insert_line_breaks(&html);
+    }
+    if opts.wants_embed_source() && !html.contains(TEX_ANNOTATION) {
+        html = set_attribute(
+            &html,
+            r#"<span class="katex-html""#,
+            "data-tex",
+            &escape_xml(input),
+        );
+    }
+    if opts.wants_unwrap_outer() {
+        html = strip_wrapper(&html);
+    }
+    html
+}
+
+/// Start tag of the MathML annotation holding the source LaTeX.
+const TEX_ANNOTATION: &str = r#"<annotation encoding="application/x-tex">"#;
+
+/// Add `name="value"` to every start tag beginning with `tag_start` (e.g.
+/// `<math` or `<span class="katex-display`) and lacking that attribute.
+///
+/// `value` is inserted verbatim and must already be escaped.
+pub(crate) fn set_attribute(html: &str, tag_start: &str, name: &str, value: &str) -> String {
+    let mut out = String::with_capacity(html.len() + value.len() + name.len() + 4);
+    let mut rest = html;
+    while let Some(start) = find_start_tag(rest, tag_start) {
+        let tag_end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &rest[start..tag_end];
+        out.push_str(tag);
+        if !has_attribute(tag, name) {
+            out.push(' ');
+            out.push_str(name);
+            out.push_str("=\"");
+            out.push_str(value);
+            out.push('"');
+        }
+        rest = &rest[tag_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Find the byte offset of the next start tag beginning with `tag_start` in
+/// `html`, skipping tags where `tag_start` is only a prefix of a longer name
+/// (e.g. `<mathx` for `<math`).
+fn find_start_tag(html: &str, tag_start: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(idx) = html[offset..].find(tag_start) {
+        let start = offset + idx;
+        let after = &html[start + tag_start.len()..];
+        if !after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-') {
+            return Some(start);
+        }
+        offset = start + tag_start.len();
+    }
+    None
+}
+
+/// The first `<math>..</math>` element of `html`, if any.
+pub(crate) fn math_element(html: &str) -> Option<&str> {
+    let start = find_start_tag(html, "<math")?;
+    let end = start + html[start..].find("</math>")? + "</math>".len();
+    Some(&html[start..end])
+}
+
+/// Extract the MathML `<math>` element from a fragment rendered by this
+/// crate.
+///
+/// For the default [`OutputType::HtmlAndMathml`](crate::OutputType), this is
+/// the content of the `katex-mathml` span, in both inline and display mode.
+/// Fragments rendered with [`OutputType::Mathml`](crate::OutputType) (by KaTeX
+/// or Temml) are reduced to their `<math>` element. Returns `None` if `html`
+/// contains no MathML, e.g. with [`OutputType::Html`](crate::OutputType) or
+/// for errors rendered inline.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render(r"\sqrt{x}").unwrap();
+/// let mathml = katex::postprocess::extract_mathml(&html).unwrap();
+/// assert!(mathml.starts_with("<math") && mathml.ends_with("</math>"));
+/// ```
+pub fn extract_mathml(html: &str) -> Option<String> {
+    let html = match html.find(r#"<span class="katex-mathml">"#) {
+        Some(start) => &html[start..],
+        None => html,
+    };
+    math_element(html).map(str::to_owned)
+}
+
+/// Extract the source LaTeX from a fragment rendered by this crate.
+///
+/// The source is read from the MathML `<annotation>` KaTeX adds, or else
+/// from the `data-tex` attribute added with
+/// [`embed_source`](crate::Opts::set_embed_source). Returns `None` if the
+/// fragment carries neither, e.g. for [`OutputType::Html`](crate::OutputType)
+/// without `embed_source`.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render(r"x^2 < y").unwrap();
+/// let source = katex::postprocess::extract_source(&html).unwrap();
+/// assert_eq!(source, r"x^2 < y");
+/// ```
+pub fn extract_source(html: &str) -> Option<String> {
+    if let Some(start) = html.find(TEX_ANNOTATION) {
+        let text = &html[start + TEX_ANNOTATION.len()..];
+        let end = text.find("</annotation>")?;
+        return Some(unescape_xml(&text[..end]));
+    }
+    tokens(html).find_map(|token| match token {
+        Token::Start { attrs, .. } => attributes(attrs)
+            .into_iter()
+            .find(|(name, _)| *name == "data-tex")
+            .map(|(_, value)| unescape_xml(value)),
+        _ => None,
+    })
+}
+
+/// Extract the inline `<svg>` elements of a fragment rendered by KaTeX, in
+/// document order, e.g. to process them in a PDF pipeline.
+///
+/// KaTeX draws only some constructs as SVG, such as the surd of `\sqrt`,
+/// stretchy arrows and accents, and large delimiters; everything else is
+/// HTML text. **The result is not an SVG of the whole formula**, only these
+/// pieces. Each element carries its `xmlns`, so it is a standalone SVG
+/// document, but its size is relative to the font size (`em`) and it is
+/// often much wider than what is visible, as KaTeX crops it with the
+/// enclosing HTML (`preserveAspectRatio="... slice"`). Returns an empty list
+/// for output without SVG, e.g. [`OutputType::Mathml`](crate::OutputType).
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render(r"\sqrt{x}").unwrap();
+/// let svgs = katex::postprocess::extract_svgs(&html);
+/// assert!(svgs[0].starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+/// assert!(svgs[0].ends_with("</svg>"));
+/// ```
+pub fn extract_svgs(html: &str) -> Vec<String> {
+    let mut svgs = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0_usize;
+    for token in tokens(html) {
+        match token {
+            Token::Start {
+                name: "svg",
+                self_closing: false,
+                ..
+            } => depth += 1,
+            Token::End { name: "svg", .. } if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    current.push_str(token.raw());
+                    svgs.push(core::mem::take(&mut current));
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        if depth > 0 {
+            current.push_str(token.raw());
+        }
+    }
+    svgs
+}
+
+/// Remove the outermost `<span class="katex">` wrapper of a fragment rendered
+/// by this crate, keeping its content.
+///
+/// In display mode the `katex-display` wrapper is kept and only the `katex`
+/// span inside it is removed. Only a wrapper at the top of the fragment is
+/// affected: fragments without one, such as errors rendered inline, are
+/// returned unchanged. Note that KaTeX's stylesheet scopes most rules under
+/// `.katex`, so the class has to be put on an enclosing element instead.
+///
+/// # Examples
+///
+/// ```
+/// let html = katex::render("x").unwrap();
+/// let stripped = katex::postprocess::strip_wrapper(&html);
+/// assert!(html.starts_with(r#"<span class="katex">"#));
+/// assert!(stripped.starts_with(r#"<span class="katex-mathml">"#));
+/// ```
+pub fn strip_wrapper(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    // For every open element, whether it is the removed wrapper, or `None`
+    // for `katex-display` wrappers, which may contain it.
+    let mut open: Vec<Option<bool>> = Vec::new();
+    let mut stripped = false;
+    for token in tokens(html) {
+        match token {
+            Token::Start {
+                name,
+                attrs,
+                self_closing: false,
+                ..
+            } => {
+                let class = (name == "span")
+                    .then(|| attributes(attrs))
+                    .and_then(|attrs| attrs.into_iter().find(|(name, _)| *name == "class"))
+                    .map(|(_, class)| class);
+                let at_top = open.iter().all(Option::is_none);
+                if !stripped && at_top && class == Some("katex") {
+                    stripped = true;
+                    open.push(Some(true));
+                    continue;
+                }
+                let is_display = class.is_some_and(|class| {
+                    class.split_ascii_whitespace().next() == Some("katex-display")
+                });
+                open.push(if at_top && is_display {
+                    None
+                } else {
+                    Some(false)
+                });
+            }
+            Token::End { .. } if open.pop() == Some(Some(true)) => continue,
+            _ => {}
+        }
+        out.push_str(token.raw());
+    }
+    out
+}
+
+/// Insert a `<wbr>` line break opportunity between consecutive top-level
+/// `base` spans of KaTeX HTML output.
+///
+/// KaTeX starts a new `base` after every top-level binary operator and
+/// relation (and at `\allowbreak`), so these are exactly the points where
+/// LaTeX would allow breaking an inline formula. Nested expressions, such as
+/// the numerator of a fraction, are a single `base` and stay unbroken.
+pub(crate) fn insert_line_breaks(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    // For every open element, whether it is a `katex-html` or top-level `base`
+    // span.
+    let mut open: Vec<Option<&str>> = Vec::new();
+    let mut after_base = false;
+    for token in tokens(html) {
+        match token {
+            Token::Start {
+                name,
+                attrs,
+                self_closing,
+                ..
+            } => {
+                let class = (name == "span")
+                    .then(|| attributes(attrs))
+                    .and_then(|attrs| attrs.into_iter().find(|(name, _)| *name == "class"))
+                    .map(|(_, class)| class);
+                let in_html = open.last() == Some(&Some("katex-html"));
+                let kind = match class.and_then(|class| class.split_ascii_whitespace().next()) {
+                    Some("katex-html") => Some("katex-html"),
+                    Some("base") if in_html => Some("base"),
+                    _ => None,
+                };
+                if after_base && kind == Some("base") {
+                    out.push_str("<wbr>");
+                }
+                after_base = false;
+                if !self_closing {
+                    open.push(kind);
+                }
+            }
+            Token::End { .. } => after_base = open.pop() == Some(Some("base")),
+            Token::Text(_) => after_base = false,
+        }
+        out.push_str(token.raw());
+    }
+    out
+}
+
+/// Remove the whitespace between tags of a rendered fragment, e.g. after it
+/// was pretty-printed or embedded in an indented template.
+///
+/// KaTeX spaces math through explicit CSS and Temml through MathML elements,
+/// so whitespace-only text between tags carries no meaning and is dropped.
+/// Everything else is kept verbatim: text containing anything but ASCII
+/// whitespace (including the no-break spaces KaTeX emits for `\text{a b}`),
+/// whitespace inside tags, and the content of `<annotation>` elements, which
+/// holds the source LaTeX. Markup as produced by this crate contains no such
+/// whitespace and is returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// let html = "<span class=\"katex\">\n  <span class=\"base\">x</span>\n</span>";
+/// let minified = katex::postprocess::minify_html(html);
+/// assert_eq!(minified, r#"<span class="katex"><span class="base">x</span></span>"#);
+/// ```
+pub fn minify_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_annotation = false;
+    for token in tokens(html) {
+        match token {
+            Token::Start {
+                name, self_closing, ..
+            } if name.starts_with("annotation") => {
+                in_annotation = !self_closing;
+            }
+            Token::End { name, .. } if name.starts_with("annotation") => in_annotation = false,
+            Token::Text(text)
+                if !in_annotation && text.bytes().all(|b| b.is_ascii_whitespace()) =>
+            {
+                continue;
+            }
+            _ => {}
+        }
+        out.push_str(token.raw());
+    }
+    out
+}
+
+/// Indent a rendered fragment for reading, e.g. in snapshot tests, with one
+/// element per line and two spaces per nesting level.
+///
+/// Only whitespace between tags changes, which carries no meaning in the
+/// markup KaTeX / Temml generate, see [`minify_html`]. Elements containing
+/// text, such as `<mi>x</mi>` or the `<annotation>` holding the source
+/// LaTeX, are kept on one line with their content verbatim, as are elements
+/// without content. Pretty-printing is idempotent, and [`minify_html`]
+/// reverses it.
+///
+/// # Examples
+///
+/// ```
+/// let html = r#"<span class="katex"><span class="base"><span class="mord">x</span></span></span>"#;
+/// let pretty = katex::postprocess::prettify_html(html);
+/// assert_eq!(
+///     pretty,
+///     "<span class=\"katex\">\n  <span class=\"base\">\n    <span class=\"mord\">x</span>\n  </span>\n</span>"
+/// );
+/// assert_eq!(katex::postprocess::prettify_html(&pretty), pretty);
+/// assert_eq!(katex::postprocess::minify_html(&pretty), html);
+/// ```
+pub fn prettify_html(html: &str) -> String {
+    let mut stack = vec![Node::Element {
+        name: "",
+        start: "",
+        end: "",
+        children: Vec::new(),
+    }];
+    for token in tokens(html) {
+        let node = match token {
+            Token::Start {
+                name,
+                self_closing: false,
+                raw,
+                ..
+            } => {
+                stack.push(Node::Element {
+                    name,
+                    start: raw,
+                    end: "",
+                    children: Vec::new(),
+                });
+                continue;
+            }
+            Token::End { raw, .. } if stack.len() > 1 => {
+                let mut node = stack.pop().expect("checked length");
+                if let Node::Element { end, .. } = &mut node {
+                    *end = raw;
+                }
+                node
+            }
+            Token::Text(text)
+                if text.bytes().all(|b| b.is_ascii_whitespace())
+                    && !stack.iter().any(|node| {
+                        matches!(node, Node::Element { name, .. } if name.starts_with("annotation"))
+                    }) =>
+            {
+                continue;
+            }
+            Token::Start {
+                name,
+                self_closing: true,
+                raw,
+                ..
+            } => Node::Element {
+                name,
+                start: raw,
+                end: "",
+                children: Vec::new(),
+            },
+            // Stray end tags are kept like text.
+            token => Node::Text(token.raw()),
+        };
+        stack.last_mut().expect("root").push(node);
+    }
+    // Close the elements left open at the end.
+    while stack.len() > 1 {
+        let node = stack.pop().expect("checked length");
+        stack.last_mut().expect("root").push(node);
+    }
+    let Some(Node::Element { children, .. }) = stack.pop() else {
+        unreachable!("the root is an element");
+    };
+    let mut out = String::with_capacity(html.len() * 2);
+    if children.iter().any(|node| matches!(node, Node::Text(_))) {
+        children.iter().for_each(|node| node.write_inline(&mut out));
+    } else {
+        for (i, node) in children.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            node.write_indented(&mut out, 0);
+        }
+    }
+    out
+}
+
+/// A node of the tree built by [`prettify_html`].
+enum Node<'a> {
+    Element {
+        name: &'a str,
+        start: &'a str,
+        end: &'a str,
+        children: Vec<Node<'a>>,
+    },
+    Text(&'a str),
+}
+
+impl Node<'_> {
+    /// Append a child, this node being an element.
+    fn push(&mut self, child: Self) {
+        if let Node::Element { children, .. } = self {
+            children.push(child);
+        }
+    }
+
+    /// Write the node without whitespace between tags.
+    fn write_inline(&self, out: &mut String) {
+        match self {
+            Node::Element {
+                start,
+                end,
+                children,
+                ..
+            } => {
+                out.push_str(start);
+                children.iter().for_each(|node| node.write_inline(out));
+                out.push_str(end);
+            }
+            Node::Text(text) => out.push_str(text),
+        }
+    }
+
+    /// Write the node at the start of a line, indented by `depth` levels.
+    fn write_indented(&self, out: &mut String, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        match self {
+            Node::Element {
+                start,
+                end,
+                children,
+                ..
+            } if !children.is_empty()
+                && !children.iter().any(|node| matches!(node, Node::Text(_))) =>
+            {
+                out.push_str(start);
+                for node in children {
+                    out.push('\n');
+                    node.write_indented(out, depth + 1);
+                }
+                out.push('\n');
+                out.push_str(&"  ".repeat(depth));
+                out.push_str(end);
+            }
+            _ => self.write_inline(out),
+        }
+    }
+}
+
+/// Whether the start tag `tag` (without the closing `>`) carries `name`.
+fn has_attribute(tag: &str, name: &str) -> bool {
+    tag.match_indices(name).any(|(idx, _)| {
+        tag[..idx].ends_with(|c: char| c.is_ascii_whitespace())
+            && tag[idx + name.len()..].starts_with('=')
+    })
+}
+
+/// Escape `text` for use in XML / HTML text content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reverse [`escape_xml`], also handling the other entities KaTeX emits.
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| &rest[1..end]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                out.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// MathML namespace, set on every `<math>` element by [`normalize_mathml`].
+const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// Bring the MathML in `html` into the canonical form shared by KaTeX and
+/// Temml output.
+///
+/// * the `<math>` element carries the MathML namespace and no `class` or
+///   `style` attributes;
+/// * Temml's `tml-*` classes are removed from all elements;
+/// * attributes are sorted by name;
+/// * `<semantics>` wrappers and their `<annotation>` are removed;
+/// * `<mrow>` elements directly inside `<math>` are unwrapped (`<math>`
+///   already behaves like a row);
+/// * if `bare` is set, everything around the `<math>` element, i.e. KaTeX's
+///   wrapper spans, is dropped.
+fn normalize_mathml(html: &str, bare: bool) -> String {
+    let mut out = String::with_capacity(html.len());
+    // For each open element inside `<math>`: whether its tags are dropped.
+    let mut open: Vec<bool> = Vec::new();
+    let mut in_math = false;
+    let mut skip_depth = 0usize;
+    for token in tokens(html) {
+        if !in_math {
+            match token {
+                Token::Start {
+                    name: "math",
+                    attrs,
+                    ..
+                } => {
+                    in_math = true;
+                    let mut attrs: Vec<_> = attributes(attrs)
+                        .into_iter()
+                        .filter(|(name, _)| !matches!(*name, "class" | "style" | "xmlns"))
+                        .collect();
+                    attrs.push(("xmlns", MATHML_NAMESPACE));
+                    write_start_tag(&mut out, "math", &attrs, false);
+                }
+                token if !bare => out.push_str(token.raw()),
+                _ => {}
+            }
+            continue;
+        }
+        if skip_depth > 0 {
+            match token {
+                Token::Start {
+                    self_closing: false,
+                    ..
+                } => skip_depth += 1,
+                Token::End { .. } => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+        match token {
+            Token::Start {
+                name: "annotation" | "annotation-xml",
+                self_closing,
+                ..
+            } => {
+                if !self_closing {
+                    skip_depth = 1;
+                }
+            }
+            Token::Start {
+                name,
+                attrs,
+                self_closing,
+                ..
+            } => {
+                let emitted = open.iter().filter(|dropped| !**dropped).count();
+                let dropped =
+                    name == "semantics" || (name == "mrow" && attrs.is_empty() && emitted == 0);
+                if !dropped {
+                    let classes: String;
+                    let mut attrs = attributes(attrs);
+                    if let Some(idx) = attrs.iter().position(|(name, _)| *name == "class") {
+                        classes = attrs[idx]
+                            .1
+                            .split_ascii_whitespace()
+                            .filter(|class| !class.starts_with("tml-"))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        if classes.is_empty() {
+                            attrs.remove(idx);
+                        } else {
+                            attrs[idx].1 = &classes;
+                        }
+                    }
+                    write_start_tag(&mut out, name, &attrs, self_closing);
+                }
+                if !self_closing {
+                    open.push(dropped);
+                }
+            }
+            Token::End { name: "math", raw } => {
+                in_math = false;
+                open.clear();
+                out.push_str(raw);
+            }
+            Token::End { raw, .. } => {
+                if !open.pop().unwrap_or(false) {
+                    out.push_str(raw);
+                }
+            }
+            Token::Text(text) => out.push_str(text),
+        }
+    }
+    out
+}
+
+/// First code points of the runs of 52 Latin letters (`A`–`Z`, `a`–`z`) in the
+/// Mathematical Alphanumeric Symbols block, with their `mathvariant`.
+const LATIN_VARIANTS: [(u32, &str); 13] = [
+    (0x1D400, "bold"),
+    (0x1D434, "italic"),
+    (0x1D468, "bold-italic"),
+    (0x1D49C, "script"),
+    (0x1D4D0, "bold-script"),
+    (0x1D504, "fraktur"),
+    (0x1D538, "double-struck"),
+    (0x1D56C, "bold-fraktur"),
+    (0x1D5A0, "sans-serif"),
+    (0x1D5D4, "bold-sans-serif"),
+    (0x1D608, "sans-serif-italic"),
+    (0x1D63C, "sans-serif-bold-italic"),
+    (0x1D670, "monospace"),
+];
+
+/// First code points of the runs of 10 digits in the Mathematical
+/// Alphanumeric Symbols block, with their `mathvariant`.
+const DIGIT_VARIANTS: [(u32, &str); 5] = [
+    (0x1D7CE, "bold"),
+    (0x1D7D8, "double-struck"),
+    (0x1D7E2, "sans-serif"),
+    (0x1D7EC, "bold-sans-serif"),
+    (0x1D7F6, "monospace"),
+];
+
+/// Letters of the Letterlike Symbols block filling the holes of the
+/// Mathematical Alphanumeric Symbols block.
+const LETTERLIKE_VARIANTS: [(char, char, &str); 24] = [
+    ('ℎ', 'h', "italic"),
+    ('ℬ', 'B', "script"),
+    ('ℰ', 'E', "script"),
+    ('ℱ', 'F', "script"),
+    ('ℋ', 'H', "script"),
+    ('ℐ', 'I', "script"),
+    ('ℒ', 'L', "script"),
+    ('ℳ', 'M', "script"),
+    ('ℛ', 'R', "script"),
+    ('ℯ', 'e', "script"),
+    ('ℊ', 'g', "script"),
+    ('ℴ', 'o', "script"),
+    ('ℭ', 'C', "fraktur"),
+    ('ℌ', 'H', "fraktur"),
+    ('ℑ', 'I', "fraktur"),
+    ('ℜ', 'R', "fraktur"),
+    ('ℨ', 'Z', "fraktur"),
+    ('ℂ', 'C', "double-struck"),
+    ('ℍ', 'H', "double-struck"),
+    ('ℕ', 'N', "double-struck"),
+    ('ℙ', 'P', "double-struck"),
+    ('ℚ', 'Q', "double-struck"),
+    ('ℝ', 'R', "double-struck"),
+    ('ℤ', 'Z', "double-struck"),
+];
+
+/// Map a mathematical alphanumeric character to its ASCII counterpart and
+/// `mathvariant`.
+fn plain_variant(c: char) -> Option<(char, &'static str)> {
+    let code = u32::from(c);
+    let lookup = |table: &[(u32, &'static str)], len: u32, plain: fn(u32) -> u8| {
+        table
+            .iter()
+            .find(|(start, _)| (*start..*start + len).contains(&code))
+            .map(|(start, variant)| (char::from(plain(code - start)), *variant))
+    };
+    lookup(&LATIN_VARIANTS, 52, |offset| {
+        if offset < 26 {
+            b'A' + offset as u8
+        } else {
+            b'a' + (offset - 26) as u8
+        }
+    })
+    .or_else(|| lookup(&DIGIT_VARIANTS, 10, |offset| b'0' + offset as u8))
+    .or_else(|| {
+        LETTERLIKE_VARIANTS
+            .iter()
+            .find(|(letter, ..)| *letter == c)
+            .map(|(_, plain, variant)| (*plain, *variant))
+    })
+}
+
+/// Replace mathematical alphanumeric characters (e.g. `𝑥`) in `<mi>` / `<mn>`
+/// elements by ASCII characters plus a `mathvariant` attribute.
+///
+/// Elements are only rewritten when all of their characters map to the same
+/// variant and they carry no `mathvariant` yet. Greek letters and other
+/// symbols are left unchanged.
+pub(crate) fn ascii_mathvariant(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut pending = None;
+    for token in tokens(html) {
+        if let (Some((name, attrs, raw)), Token::Text(text)) = (pending.take(), token) {
+            let plain = text
+                .chars()
+                .map(plain_variant)
+                .collect::<Option<Vec<_>>>()
+                .filter(|chars| !chars.is_empty())
+                .and_then(|chars| {
+                    let variant = chars[0].1;
+                    chars
+                        .iter()
+                        .all(|(_, v)| *v == variant)
+                        .then(|| (chars.iter().map(|(c, _)| *c).collect::<String>(), variant))
+                });
+            match plain {
+                Some((text, variant)) => {
+                    let mut attrs = attributes(attrs);
+                    attrs.push(("mathvariant", variant));
+                    write_start_tag(&mut out, name, &attrs, false);
+                    out.push_str(&text);
+                }
+                None => {
+                    out.push_str(raw);
+                    out.push_str(text);
+                }
+            }
+            continue;
+        }
+        if let Some((_, _, raw)) = pending.take() {
+            out.push_str(raw);
+        }
+        match token {
+            Token::Start {
+                name: name @ ("mi" | "mn"),
+                attrs,
+                self_closing: false,
+                raw,
+            } if !has_attribute(raw, "mathvariant") => pending = Some((name, attrs, raw)),
+            token => out.push_str(token.raw()),
+        }
+    }
+    if let Some((_, _, raw)) = pending {
+        out.push_str(raw);
+    }
+    out
+}
+
+/// A piece of markup as produced by [`tokens`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Token<'a> {
+    /// A start tag such as `<span class="katex">` or `<mspace/>`.
+    Start {
+        /// The element name.
+        name: &'a str,
+        /// The unparsed attributes, see [`attributes`].
+        attrs: &'a str,
+        /// Whether the tag closes itself (`<mspace/>`).
+        self_closing: bool,
+        /// The whole tag.
+        raw: &'a str,
+    },
+    /// An end tag such as `</span>`.
+    End {
+        /// The element name.
+        name: &'a str,
+        /// The whole tag.
+        raw: &'a str,
+    },
+    /// Text between tags, still escaped.
+    Text(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// The markup the token was read from.
+    pub(crate) fn raw(self) -> &'a str {
+        match self {
+            Token::Start { raw, .. } | Token::End { raw, .. } | Token::Text(raw) => raw,
+        }
+    }
+}
+
+/// Split the markup generated by KaTeX / Temml into tags and text.
+///
+/// Relies on that markup containing no comments, CDATA sections or unescaped
+/// `<` / `>` in attribute values.
+pub(crate) fn tokens(html: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = html;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let (text, tail) = rest.split_at(end);
+            rest = tail;
+            return Some(Token::Text(text));
+        }
+        let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+        let (raw, tail) = rest.split_at(end);
+        rest = tail;
+        let inner = raw.trim_start_matches('<').trim_end_matches('>');
+        if let Some(name) = inner.strip_prefix('/') {
+            return Some(Token::End {
+                name: name.trim(),
+                raw,
+            });
+        }
+        let self_closing = inner.ends_with('/');
+        let inner = inner.trim_end_matches('/');
+        let name_end = inner
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(inner.len());
+        Some(Token::Start {
+            name: &inner[..name_end],
+            attrs: inner[name_end..].trim(),
+            self_closing,
+            raw,
+        })
+    })
+}
+
+/// Parse the attributes of a start tag into `(name, value)` pairs, values
+/// still escaped. Attributes without a value get an empty one.
+pub(crate) fn attributes(attrs: &str) -> Vec<(&str, &str)> {
+    let mut parsed = Vec::new();
+    let mut rest = attrs.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(tail) = rest.strip_prefix('=') {
+            let tail = tail.trim_start();
+            let quote = tail.chars().next().filter(|c| matches!(c, '"' | '\''));
+            (value, rest) = match quote {
+                Some(quote) => {
+                    let end = tail[1..].find(quote).map_or(tail.len(), |end| end + 1);
+                    (&tail[1..end], tail.get(end + 1..).unwrap_or(""))
+                }
+                None => {
+                    let end = tail
+                        .find(|c: char| c.is_ascii_whitespace())
+                        .unwrap_or(tail.len());
+                    (&tail[..end], &tail[end..])
+                }
+            };
+        }
+        parsed.push((name, value));
+        rest = rest.trim_start();
+    }
+    parsed
+}
+
+/// Write a start tag with the given (already escaped) attributes, sorted by
+/// name.
+fn write_start_tag(out: &mut String, name: &str, attrs: &[(&str, &str)], self_closing: bool) {
+    let mut attrs = attrs.to_vec();
+    attrs.sort_by_key(|(name, _)| *name);
+    out.push('<');
+    out.push_str(name);
+    for (name, value) in attrs {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        out.push_str(value);
+        out.push('"');
+    }
+    if self_closing {
+        out.push('/');
+    }
+    out.push('>');
+}
@@ -0,0 +1,127 @@
+//! Transformations applied to the LaTeX input before it reaches KaTeX / Temml.
+//!
+//! Like [post-processing](crate::postprocess), these implement options which
+//! have no upstream counterpart. They work on a light tokenization of the
+//! input (control sequences, braces, single characters) instead of a full
+//! TeX parser, so they only rewrite constructs they can recognize reliably.
+
+use crate::opts::Opts;
+use std::borrow::Cow;
+
+/// Apply all input transformations requested by `opts` to `input`.
+pub(crate) fn apply<'a>(input: &'a str, opts: &Opts) -> Cow<'a, str> {
+    let mut input = Cow::Borrowed(input);
+    if let Some(recursive) = opts.big_inline_fractions() {
+        let mut out = String::with_capacity(input.len() + 8);
+        display_fractions(&input, recursive, false, &mut out);
+        input = Cow::Owned(out);
+    }
+    input
+}
+
+/// Rewrite `\frac` into `\dfrac`, writing the result into `out`.
+///
+/// `nested` tells whether `src` is the argument of a fraction. Fractions
+/// nested inside another fraction are only rewritten when `recursive` is set.
+/// Explicit `\dfrac` / `\tfrac` are kept, but their arguments are still
+/// scanned for nested `\frac`.
+fn display_fractions(src: &str, recursive: bool, nested: bool, out: &mut String) {
+    let mut rest = src;
+    while let Some(c) = rest.chars().next() {
+        if c != '\\' {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let name_len = rest[1..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len() - 1);
+        if name_len == 0 {
+            // Control symbol such as `\{` or `\\`, copied as a whole.
+            let len = 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        let name = &rest[1..=name_len];
+        rest = &rest[1 + name_len..];
+        match name {
+            "frac" | "dfrac" | "tfrac" => {
+                if name == "frac" && (!nested || recursive) {
+                    out.push_str(r"\dfrac");
+                } else {
+                    out.push('\\');
+                    out.push_str(name);
+                }
+                for _ in 0..2 {
+                    rest = fraction_argument(rest, recursive, out);
+                }
+            }
+            _ => {
+                out.push('\\');
+                out.push_str(name);
+            }
+        }
+    }
+}
+
+/// Copy the next macro argument of `src` into `out`, rewriting fractions in
+/// it, and return the remaining input.
+fn fraction_argument<'a>(src: &'a str, recursive: bool, out: &mut String) -> &'a str {
+    let trimmed = src.trim_start();
+    out.push_str(&src[..src.len() - trimmed.len()]);
+    let Some(c) = trimmed.chars().next() else {
+        return trimmed;
+    };
+    match c {
+        '{' => {
+            let end = matching_brace(trimmed).unwrap_or(trimmed.len());
+            out.push('{');
+            display_fractions(&trimmed[1..end], recursive, true, out);
+            if end < trimmed.len() {
+                out.push('}');
+                &trimmed[end + 1..]
+            } else {
+                ""
+            }
+        }
+        '\\' => {
+            let name_len = trimmed[1..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(trimmed.len() - 1);
+            let len = if name_len == 0 {
+                1 + trimmed[1..].chars().next().map_or(0, char::len_utf8)
+            } else {
+                1 + name_len
+            };
+            out.push_str(&trimmed[..len]);
+            &trimmed[len..]
+        }
+        c => {
+            out.push(c);
+            &trimmed[c.len_utf8()..]
+        }
+    }
+}
+
+/// Byte offset of the `}` closing the group opened by the leading `{` of
+/// `src`, honoring escaped braces.
+fn matching_brace(src: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (idx, c) in src.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,101 @@
+//! Several representations of an equation at once.
+//!
+//! [`render_all`] returns the hybrid HTML, the MathML and (with the `speech`
+//! feature) a spoken description of an input in one call on the engine of the
+//! current thread. [`render_split`] returns the HTML and MathML halves of the
+//! output as separate elements.
+
+use crate::{
+    error::Result,
+    js_engine::JsEngine,
+    opts::{Opts, OutputType},
+    postprocess, render_inner, render_raw, with_engine,
+};
+use core::iter;
+
+/// The representations of an equation produced by [`render_all`].
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rendered {
+    /// The hybrid HTML and MathML markup, as rendered with
+    /// [`OutputType::HtmlAndMathml`].
+    pub html: String,
+    /// The `<math>` element of [`html`](Rendered::html), see
+    /// [`extract_mathml`](postprocess::extract_mathml). Empty if the markup
+    /// carries no MathML, i.e. for an error rendered inline.
+    pub mathml: String,
+    /// The spoken description of the equation, see
+    /// [`render_speech`](crate::render_speech).
+    #[cfg(feature = "speech")]
+    pub speech: String,
+}
+
+/// Render LaTeX equation to HTML and MathML, and describe it in words with
+/// the `speech` feature.
+///
+/// KaTeX renders the markup once, in the hybrid
+/// [output type](`Opts::set_output_type`) whatever the one of `opts`, and the
+/// MathML is taken from it rather than rendered separately. The description
+/// requires a parse of its own, skipped without the `speech` feature. Like
+/// [`render_speech`](crate::render_speech), it fails on invalid input even
+/// when `throw_on_error` is false.
+///
+/// # Examples
+///
+/// ```
+/// let rendered = katex::render_all(r"\sqrt{x}", katex::Opts::default()).unwrap();
+/// assert!(rendered.html.contains(r#"<span class="katex-html""#));
+/// assert!(rendered.mathml.starts_with("<math"));
+/// # #[cfg(feature = "speech")]
+/// assert_eq!(rendered.speech, "square root of, x, end square root");
+/// ```
+pub fn render_all(input: &str, opts: impl AsRef<Opts>) -> Result<Rendered> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_output_type(OutputType::HtmlAndMathml);
+    with_engine(|engine| {
+        let html = render_inner(engine, input, &opts)?;
+        let mathml = postprocess::extract_mathml(&html).unwrap_or_default();
+        #[cfg(feature = "speech")]
+        let speech = crate::speech::speech_on(engine, input, &opts)?;
+        Ok(Rendered {
+            html,
+            mathml,
+            #[cfg(feature = "speech")]
+            speech,
+        })
+    })
+}
+
+/// Render LaTeX equation to its HTML and MathML halves, returned separately
+/// as `(html, mathml)`.
+///
+/// KaTeX builds the hybrid output once, in the
+/// [output type](`Opts::set_output_type`) `HtmlAndMathml` whatever the one of
+/// `opts`, and its two parts are serialized on their own instead of being cut
+/// out of the combined markup. `html` is the `<span class="katex-html">`
+/// element and `mathml` the `<math>` element, each well-formed on its own;
+/// the `katex` (and, in display mode, `katex-display`) wrappers are left
+/// out. Post-processing options apply to both halves. An error rendered
+/// inline (with `throw_on_error(false)`) is returned whole as `html`, with an
+/// empty `mathml`.
+///
+/// # Examples
+///
+/// ```
+/// let (html, mathml) = katex::render_split(r"\sqrt{x}", katex::Opts::default()).unwrap();
+/// assert!(html.starts_with(r#"<span class="katex-html""#));
+/// assert!(mathml.starts_with("<math") && mathml.ends_with("</math>"));
+/// ```
+pub fn render_split(input: &str, opts: impl AsRef<Opts>) -> Result<(String, String)> {
+    let mut opts = opts.as_ref().clone();
+    opts.set_output_type(OutputType::HtmlAndMathml);
+    with_engine(|engine| {
+        let html = render_raw(engine, "katexRenderSplit", input, &opts)?;
+        let mathml = engine.call_function("katexLastMathml", iter::empty())?;
+        let mathml = engine.value_to_string(mathml)?;
+        Ok((
+            postprocess::apply(input, &opts, html),
+            postprocess::apply(input, &opts, mathml),
+        ))
+    })
+}
@@ -0,0 +1,120 @@
+//! An owned renderer, as an alternative to the per‑thread engine.
+//!
+//! [`Katex`] holds its own JS engine with the bundle loaded for as long as it
+//! is alive, independently of the thread-local engine used by
+//! [`render_with_opts`](crate::render_with_opts) and friends.
+
+use crate::{error::Result, init_katex_with, opts::Opts, render_inner, Engine, RuntimeConfig};
+use core::marker::PhantomData;
+
+/// A KaTeX renderer owning its JS engine.
+///
+/// Creating a `Katex` loads the KaTeX (and, if enabled, Temml) bundle once;
+/// every render afterwards reuses it. Dropping the renderer frees the engine.
+///
+/// `Katex` is neither `Send` nor `Sync`, since the underlying JS engines are
+/// not thread-safe, so it cannot be moved across threads or shared through an
+/// `Arc`. Instead, give each worker thread its own renderer, created on that
+/// thread:
+///
+/// ```
+/// use std::sync::mpsc;
+///
+/// let (sender, receiver) = mpsc::channel::<String>();
+/// let worker = std::thread::spawn(move || {
+///     let katex = katex::Katex::new().unwrap();
+///     receiver
+///         .iter()
+///         .map(|input| katex.render(&input).unwrap())
+///         .collect::<Vec<_>>()
+/// });
+/// sender.send("a + b".to_owned()).unwrap();
+/// sender.send(r"\sqrt{2}".to_owned()).unwrap();
+/// drop(sender);
+/// assert_eq!(worker.join().unwrap().len(), 2);
+/// ```
+pub struct Katex {
+    engine: Engine,
+    opts: Opts,
+    /// Keep the type `!Send` and `!Sync` whatever the backend.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Katex {
+    /// Create a renderer using the default [options](`Opts`).
+    pub fn new() -> Result<Self> {
+        Self::with_opts(Opts::default())
+    }
+
+    /// Create a renderer using `default_opts` for [`Katex::render`].
+    pub fn with_opts(default_opts: Opts) -> Result<Self> {
+        Self::with_runtime_config(default_opts, &RuntimeConfig::default())
+    }
+
+    /// Create a renderer using `default_opts` for [`Katex::render`], whose
+    /// engine is limited as configured by `config`.
+    ///
+    /// Exceeding a limit while rendering fails the render with
+    /// [`Error::JsExecError`](crate::Error::JsExecError) instead of aborting
+    /// the process. The renderer stays usable afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config = katex::RuntimeConfig::default().memory_limit(64 << 20);
+    /// let katex = katex::Katex::with_runtime_config(katex::Opts::default(), &config).unwrap();
+    /// assert!(katex.render("a + b").is_ok());
+    /// ```
+    pub fn with_runtime_config(default_opts: Opts, config: &RuntimeConfig) -> Result<Self> {
+        Ok(Katex {
+            engine: init_katex_with(config)?,
+            opts: default_opts,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// The options used by [`Katex::render`].
+    pub fn opts(&self) -> &Opts {
+        &self.opts
+    }
+
+    /// Render LaTeX equation to HTML with the renderer's default options.
+    pub fn render(&self, input: &str) -> Result<String> {
+        render_inner(&self.engine, input, &self.opts)
+    }
+
+    /// Render LaTeX equation to HTML with the given [options](`Opts`),
+    /// ignoring the renderer's default options.
+    pub fn render_with_opts(&self, input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+        render_inner(&self.engine, input, opts)
+    }
+
+    /// Run the garbage collector of the renderer's engine, e.g. after a large
+    /// batch of renders, so memory no longer in use is returned promptly
+    /// instead of at the engine's next automatic collection.
+    ///
+    /// Only the `quick-js` backend supports triggering a collection; with the
+    /// other backends this does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let katex = katex::Katex::new().unwrap();
+    /// for i in 0..100 {
+    ///     katex.render(&format!(r"\frac{{{i}}}{{2}}")).unwrap();
+    /// }
+    /// katex.collect_garbage();
+    /// ```
+    pub fn collect_garbage(&self) {
+        #[cfg(feature = "quick-js")]
+        crate::js_engine::collect_garbage(&self.engine);
+    }
+}
+
+impl std::fmt::Debug for Katex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Katex")
+            .field("opts", &self.opts)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,50 @@
+//! Spoken descriptions of math for screen-reader pipelines.
+//!
+//! [`render_speech`] describes an input in words, in the style of KaTeX's
+//! `render-a11y-string` contrib, e.g. `\frac{a}{b}` becomes "start fraction,
+//! a, divided by, b, end fraction". The script producing the descriptions is
+//! loaded into the engine by the first call.
+
+use crate::{
+    call_render, error::Result, extensions, js_engine::JsEngine, opts::Opts, preprocess,
+    with_engine,
+};
+use core::iter;
+
+/// Script defining `katexRenderA11yString`, see `js/a11y-string.js`.
+const A11Y_STRING_SRC: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/js/a11y-string.js"));
+
+/// Describe LaTeX equation as comma-separated spoken phrases.
+///
+/// The input is parsed with the same [options](`Opts`) as for rendering, so
+/// custom macros are expanded and invalid input fails with
+/// [`Error::ParseError`](crate::Error::ParseError) (`throw_on_error` does
+/// not apply, there is no markup to show errors in). Options only affecting
+/// the markup, such as the output type or post-processing, are ignored.
+///
+/// # Examples
+///
+/// ```
+/// let speech = katex::render_speech(r"\frac{a}{b}", katex::Opts::default()).unwrap();
+/// assert_eq!(speech, "start fraction, a, divided by, b, end fraction");
+/// ```
+pub fn render_speech(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    with_engine(|engine| speech_on(engine, input, opts.as_ref()))
+}
+
+/// Describe `input` in words on `engine`, see [`render_speech`].
+pub(crate) fn speech_on<E>(engine: &E, input: &str, opts: &Opts) -> Result<String>
+where
+    E: JsEngine,
+{
+    extensions::load_script(engine, "a11y-string", A11Y_STRING_SRC)?;
+    let tex = preprocess::apply(input, opts);
+    #[cfg(feature = "lazy-extensions")]
+    extensions::load_used(engine, &tex, opts)?;
+    let input_js = engine.create_string_value(tex.into_owned())?;
+    let opts_js = opts.to_js_value(engine, input)?;
+    let args = iter::once(input_js).chain(iter::once(opts_js));
+    let result = call_render(engine, "katexRenderA11yString", args)?;
+    engine.value_to_string(result)
+}
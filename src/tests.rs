@@ -16,6 +16,7 @@ fn test_render() {
     assert!(!html.contains(r#"span class="katex-error""#));
 }
 
+#[cfg(feature = "mhchem")]
 #[test]
 fn test_render_mhchem() {
     let html = render(r#"\ce{CO2 + C -> 2 CO}"#).unwrap();
@@ -93,7 +94,7 @@ fn test_throw_on_error() {
     match render(r#"\"#) {
         Ok(_) => unreachable!(),
         Err(e) => match e {
-            Error::JsExecError(msg) => msg,
+            Error::ParseError { raw, .. } => raw,
             _ => unreachable!(),
         },
     };
@@ -111,6 +112,57 @@ fn test_error_color() {
     assert!(html.contains("color:#ff0000"));
 }
 
+#[test]
+fn test_error_color_checked() {
+    let mut builder = Opts::builder();
+    builder.throw_on_error(false);
+    for color in [
+        "#cc0000",
+        "#C00",
+        "rgb(204, 0, 0)",
+        "rgb(80%,0%,0%)",
+        "Crimson",
+    ] {
+        builder.error_color_checked(color).unwrap();
+    }
+    let html = render_with_opts(r"\", builder.build().unwrap()).unwrap();
+    assert!(html.contains("color:Crimson"));
+
+    for color in [
+        "notacolor",
+        "redd",
+        "#cc00",
+        "#ggg",
+        "rgb(256, 0, 0)",
+        "rgb(1, 2)",
+    ] {
+        assert!(matches!(
+            builder.error_color_checked(color),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+    let html = render_with_opts(r"\", builder.build().unwrap()).unwrap();
+    assert!(html.contains("color:Crimson"));
+}
+
+#[test]
+fn test_render_untrusted() {
+    let html = render_untrusted(r"\url{javascript:alert(1)}").unwrap();
+    let (mathml, visible) = html.split_once("katex-html").unwrap();
+    assert!(visible.contains(r#"style="color:#cc0000;">\url</span>"#));
+    assert!(!visible.contains("javascript"));
+    assert!(!mathml.contains("href") && !visible.contains("href"));
+
+    let html = render_untrusted(r"\def\a{\a\a}\a").unwrap();
+    assert!(html.contains("Too many expansions"));
+
+    let html = render_untrusted(r"\rule{1000em}{1000em}").unwrap();
+    assert!(html.contains("10em") && !html.contains("1000em;"));
+
+    let html = render_untrusted(r"\frac{1}").unwrap();
+    assert!(html.contains(r#"span class="katex-error""#));
+}
+
 #[test]
 fn test_macros() {
     let opts = Opts::builder()
@@ -122,6 +174,29 @@ fn test_macros() {
 }
 
 #[test]
+fn test_parameterized_macros() {
+    let opts = Opts::builder()
+        .add_macro(r"\half".to_owned(), r"\frac{#1}{#2}".to_owned())
+        .add_macro(r"\twice".to_owned(), r"#1 #1".to_owned())
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\half{a}{b}", &opts).unwrap();
+    assert!(html.contains("<mfrac><mi>a</mi><mi>b</mi></mfrac>"));
+    assert!(!html.contains('#'));
+
+    let html = render_with_opts(r"\twice{\half{x}{2}}", &opts).unwrap();
+    assert_eq!(html.matches("<mfrac>").count(), 2);
+
+    let mut opts = Opts::builder().throw_on_error(true).build().unwrap();
+    opts.add_macro(r"\half".to_owned(), r"\frac{#1}{#2}".to_owned());
+    assert!(matches!(
+        render_with_opts(r"\half{a}", &opts),
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
 fn test_trust() {
     let opts = Opts::builder().error_color("#ff0000").build().unwrap();
     let html = render_with_opts(r#"\url{https://www.google.com}"#, opts).unwrap();
@@ -165,3 +240,2597 @@ fn test_katex_version() {
     assert!(!crate::KATEX_VERSION.is_empty());
     assert!(!crate::KATEX_VERSION.contains('\n'));
 }
+
+#[cfg(feature = "custom-engine")]
+#[test]
+fn test_render_on() {
+    let engine: Engine = init_engine().unwrap();
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let html1 = render_on(&engine, "a = b + c", &opts).unwrap();
+    let html2 = render_with_opts("a = b + c", &opts).unwrap();
+    assert_eq!(html1, html2);
+}
+
+#[test]
+fn test_render_numbered() {
+    let opts = Opts::default();
+    let html = render_numbered("a = b + c", "1", TagStyle::Parenthesized, &opts).unwrap();
+    assert!(html.contains(r#"span class="katex-display""#));
+    assert!(html.contains("<mtext>(1)</mtext>"));
+
+    let html = render_numbered("a = b + c", "1", TagStyle::Starred, &opts).unwrap();
+    assert!(html.contains("<mtext>1</mtext>"));
+    assert!(!html.contains("<mtext>(1)</mtext>"));
+}
+
+#[test]
+fn test_render_numbered_keeps_user_tag() {
+    let opts = Opts::default();
+    let html = render_numbered(r"a = b \tag*{A}", "1", TagStyle::Parenthesized, &opts).unwrap();
+    assert!(html.contains("<mtext>A</mtext>"));
+    assert!(!html.contains("<mtext>(1)</mtext>"));
+
+    let html = render_numbered(r"a = b \tag{A}", "1", TagStyle::Starred, &opts).unwrap();
+    assert!(html.contains("<mtext>(A)</mtext>"));
+    assert!(!html.contains("<mtext>1</mtext>"));
+}
+
+#[test]
+fn test_render_numbered_comments() {
+    let opts = Opts::default();
+    for (style, number) in [
+        (TagStyle::Parenthesized, "<mtext>(1)</mtext>"),
+        (TagStyle::Starred, "<mtext>1</mtext>"),
+    ] {
+        // A trailing comment does not swallow the injected tag.
+        let html = render_numbered("a = b % note", "1", style, &opts).unwrap();
+        assert!(html.contains(number), "{style:?}");
+
+        // A commented-out tag does not count as the user's own.
+        for input in [
+            r"a = b % \tag{A}",
+            r"a = b % \tag*{A}",
+            "a = b %\\tag{A}\n+ c",
+        ] {
+            let html = render_numbered(input, "1", style, &opts).unwrap();
+            assert!(html.contains(number), "{style:?}: {input}");
+            assert!(!html.contains("<mtext>A</mtext>"), "{style:?}: {input}");
+        }
+
+        // An escaped `\%` does not start a comment.
+        let html = render_numbered(r"50\% \tag{A}", "1", style, &opts).unwrap();
+        assert!(html.contains("<mtext>(A)</mtext>"), "{style:?}");
+        assert!(!html.contains(number), "{style:?}");
+    }
+}
+
+#[test]
+fn test_mathml_alttext() {
+    let opts = Opts::builder().mathml_alttext(true).build().unwrap();
+    let html = render_with_opts(r#"a < b \text{"c" \& d}"#, opts).unwrap();
+    assert!(html.contains(
+        r#"<math xmlns="http://www.w3.org/1998/Math/MathML" alttext="a &lt; b \text{&quot;c&quot; \&amp; d}">"#
+    ));
+
+    let html = render("a < b").unwrap();
+    assert!(!html.contains("alttext"));
+}
+
+#[test]
+fn test_math_aria_label() {
+    let opts = Opts::builder()
+        .math_aria_label(r#"a is less than "b" & c"#)
+        .mathml_alttext(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts("a < b", &opts).unwrap();
+    let math = postprocess::extract_mathml(&html).unwrap();
+    let tag = &math[..math.find('>').unwrap()];
+    assert!(tag.contains(r#"aria-label="a is less than &quot;b&quot; &amp; c""#));
+    assert!(tag.contains(r#"alttext="a &lt; b""#));
+    assert_eq!(html.matches("aria-label").count(), 1);
+
+    // An existing label is kept.
+    assert_eq!(
+        postprocess::set_attribute(&html, "<math", "aria-label", "other"),
+        html
+    );
+
+    let opts = Opts::builder()
+        .math_aria_label("label")
+        .output_type(OutputType::Html)
+        .build()
+        .unwrap();
+    assert!(!render_with_opts("x", &opts).unwrap().contains("aria-label"));
+}
+
+#[test]
+fn test_big_inline_fractions() {
+    let opts = Opts::builder().big_inline_fractions(true).build().unwrap();
+    let big = render_with_opts(r"\frac{a}{b}", &opts).unwrap();
+    assert_eq!(big, render(r"\dfrac{a}{b}").unwrap());
+    assert_eq!(
+        render_with_opts(r"\tfrac{a}{b}", &opts).unwrap(),
+        render(r"\tfrac{a}{b}").unwrap()
+    );
+
+    let nested = r"\frac{\frac{a}{b}}{c}";
+    assert_eq!(
+        render_with_opts(nested, &opts).unwrap(),
+        render(r"\dfrac{\frac{a}{b}}{c}").unwrap()
+    );
+    let opts = Opts::builder()
+        .big_inline_fractions(true)
+        .big_inline_fractions_nested(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        render_with_opts(nested, &opts).unwrap(),
+        render(r"\dfrac{\dfrac{a}{b}}{c}").unwrap()
+    );
+
+    let opts = Opts::builder()
+        .big_inline_fractions(true)
+        .display_mode(true)
+        .build()
+        .unwrap();
+    let display = Opts::builder().display_mode(true).build().unwrap();
+    assert_eq!(
+        render_with_opts(r"\frac{a}{b}", &opts).unwrap(),
+        render_with_opts(r"\frac{a}{b}", &display).unwrap()
+    );
+}
+
+#[test]
+fn test_katex_internal_error() {
+    // KaTeX throws a `TypeError` (not a `ParseError`) for non-string input,
+    // which cannot be produced through the public API.
+    let engine: Engine = init_katex().unwrap();
+    let args = iter::once(engine.create_int_value(1).unwrap()).chain(iter::once(
+        Opts::default().to_js_value(&engine, "").unwrap(),
+    ));
+    match call_render(&engine, "katexRenderToString", args) {
+        Err(Error::KatexInternal { name, message }) => {
+            assert_eq!(name, "TypeError");
+            assert!(!message.is_empty());
+        }
+        _ => unreachable!(),
+    }
+
+    match render(r"\frac{1}") {
+        Err(Error::ParseError { raw, .. }) => assert!(raw.starts_with("KaTeX parse error")),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_adaptive_max_expand() {
+    fn tight(_: usize) -> i32 {
+        2
+    }
+    let input = r"\def\a{x}\a\a\a";
+    let opts = Opts::builder()
+        .max_expand(None)
+        .adaptive_max_expand(tight)
+        .build()
+        .unwrap();
+    assert!(render_with_opts(input, &opts).is_err());
+
+    let scaled = Opts::builder()
+        .adaptive_max_expand(opts::scaled_max_expand)
+        .build()
+        .unwrap();
+    assert!(render_with_opts(input, &scaled).is_ok());
+
+    // Policies compare by function.
+    let mut same = Opts::default();
+    same.set_max_expand(None);
+    same.set_adaptive_max_expand(tight);
+    assert_eq!(same, opts);
+    assert_ne!(scaled, Opts::default());
+    same.set_adaptive_max_expand(opts::scaled_max_expand);
+    assert_ne!(same, opts);
+
+    assert_eq!(opts::scaled_max_expand(0), 1000);
+    assert_eq!(opts::scaled_max_expand(100), 2000);
+    assert_eq!(opts::scaled_max_expand(usize::MAX), 100_000);
+}
+
+#[test]
+fn test_render_toggleable() {
+    let html = render_toggleable("a = b + c", Opts::default()).unwrap();
+    assert!(html.starts_with(r#"<span class="katex-toggle" data-katex-toggle="">"#));
+    let html_view = html.find(r#"<span data-katex-view="html">"#).unwrap();
+    let mathml_view = html
+        .find(r#"<span data-katex-view="mathml" hidden="">"#)
+        .unwrap();
+    assert!(html_view < mathml_view);
+    assert!(html[html_view..mathml_view].contains(r#"span class="katex-html""#));
+    assert!(!html[html_view..mathml_view].contains("<math"));
+    assert!(html[mathml_view..].contains("</math>"));
+    assert!(!html[mathml_view..].contains(r#"span class="katex-html""#));
+}
+
+#[test]
+fn test_validate_macros() {
+    use std::collections::HashMap;
+
+    let macros = HashMap::from([
+        (r"\RR".to_owned(), r"\mathbb{R}".to_owned()),
+        (r"\pair".to_owned(), r"(#1, #2)".to_owned()),
+        (r"\hash".to_owned(), r"\#1 ##3".to_owned()),
+    ]);
+    assert_eq!(validate_macros(&macros), Ok(()));
+
+    let macros = HashMap::from([
+        (r"\a".to_owned(), r"\b".to_owned()),
+        (r"\b".to_owned(), r"\c + \\ \a".to_owned()),
+        (r"\c".to_owned(), r"2 \b".to_owned()),
+        (r"\d".to_owned(), r"\d".to_owned()),
+        (r"\e".to_owned(), r"\a".to_owned()),
+    ]);
+    let issues = validate_macros(&macros).unwrap_err();
+    assert_eq!(
+        issues,
+        [
+            MacroIssue::Cycle(vec![r"\a".to_owned(), r"\b".to_owned()]),
+            MacroIssue::Cycle(vec![r"\b".to_owned(), r"\c".to_owned()]),
+            MacroIssue::Cycle(vec![r"\d".to_owned()]),
+        ]
+    );
+
+    let macros = HashMap::from([
+        (r"\third".to_owned(), r"#3".to_owned()),
+        (r"\x".to_owned(), r"\alpha".to_owned()),
+        (r"\y".to_owned(), r"\alpha".to_owned()),
+    ]);
+    let issues = validate_macros(&macros).unwrap_err();
+    assert_eq!(
+        issues,
+        [
+            MacroIssue::UnusedParameters {
+                name: r"\third".to_owned(),
+                parameters: vec![1, 2],
+            },
+            MacroIssue::Duplicate(vec![r"\x".to_owned(), r"\y".to_owned()]),
+        ]
+    );
+}
+
+#[test]
+fn test_macros_iter() {
+    let macros = vec![
+        (r"\RR".to_owned(), r"\mathbb{R}".to_owned()),
+        (r"\half".to_owned(), r"\frac{1}{2}".to_owned()),
+    ];
+    let opts = Opts::builder()
+        .add_macro(r"\NN".to_owned(), r"\mathbb{N}".to_owned())
+        .add_macro(r"\half".to_owned(), "0.5".to_owned())
+        .macros_iter(macros)
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\half \in \RR \supset \NN", &opts).unwrap();
+    assert!(html.contains("mfrac"));
+    assert_eq!(html.matches("mathbb").count(), 2);
+}
+
+#[test]
+fn test_macros_from_tex() {
+    let opts = Opts::builder()
+        .macros_from_tex(
+            r"
+            % number sets
+            \newcommand{\RR}{\mathbb{R}}
+            \newcommand\NN{\mathbb{N}}
+            \newcommand{\pair}[2]{\left(#1, #2\right)}
+            \newcommand{\second}[2]{#2}
+            \renewcommand{\RR}{
+                \mathbf{R}
+            }
+            ",
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+    let macros = opts.macro_definitions();
+    assert_eq!(macros[r"\NN"], r"\mathbb{N}");
+    assert_eq!(macros[r"\pair"], r"\left(#1, #2\right)");
+    assert!(macros[r"\RR"].contains(r"\mathbf{R}"));
+
+    let html = render_with_opts(r"\RR \NN", &opts).unwrap();
+    assert!(html.contains("mathbf"));
+    assert!(html.contains("mathbb"));
+    let html = render_with_opts(r"\pair{a}{b}", &opts).unwrap();
+    assert!(html.contains(r#"<mi>a</mi><mo separator="true">,</mo><mi>b</mi>"#));
+    let html = render_with_opts(r"\second{a}{b}", &opts).unwrap();
+    assert!(html.contains("<mi>b</mi>"));
+    assert!(!html.contains("<mi>a</mi>"));
+
+    for (src, offset) in [
+        (r"\newcommand{\a}{x} \def\b{y}", 19),
+        (r"\newcommand{\a}[1]{#2}", 0),
+        (r"\newcommand{\a}[x]{x}", 16),
+        (r"\newcommand{\a}[1][0]{#1}", 18),
+        (r"\newcommand{\a}{\frac{1}{2}", 15),
+        (r"\newcommand{a}{x}", 12),
+    ] {
+        match Opts::builder().macros_from_tex(src) {
+            Err(Error::ParseError { position, .. }) => assert_eq!(position, Some(offset), "{src}"),
+            _ => panic!("{src} should not parse"),
+        }
+    }
+}
+
+#[test]
+fn test_fleqn_indent() {
+    let opts = Opts::builder()
+        .display_mode(true)
+        .fleqn(true)
+        .fleqn_indent(opts::Length::Em(1.5))
+        .build()
+        .unwrap();
+    let html = render_with_opts("a = b + c", opts).unwrap();
+    assert!(html.starts_with(r#"<span class="katex-display fleqn" style="margin-left:1.5em">"#));
+
+    let opts = Opts::builder()
+        .display_mode(true)
+        .fleqn_indent(opts::Length::Em(1.5))
+        .build()
+        .unwrap();
+    let html = render_with_opts("a = b + c", opts).unwrap();
+    assert!(!html.contains("margin-left"));
+}
+
+#[test]
+fn test_render_figure() {
+    let html = render_figure("a = b + c", "Sum of <b> & c", Opts::default()).unwrap();
+    assert!(html.starts_with(r#"<figure class="katex-figure"><span class="katex-display">"#));
+    assert!(html.ends_with("<figcaption>Sum of &lt;b&gt; &amp; c</figcaption></figure>"));
+
+    let template = FigureTemplate {
+        figure_tag: "div".to_owned(),
+        figure_class: None,
+        caption_tag: "p".to_owned(),
+        caption_class: Some("caption".to_owned()),
+    };
+    let html = render_figure_with_template("a = b + c", "Sum", &template, Opts::default()).unwrap();
+    assert!(html.starts_with(r#"<div><span class="katex-display">"#));
+    assert!(html.ends_with(r#"<p class="caption">Sum</p></div>"#));
+}
+
+#[test]
+fn test_render_indexed() {
+    let input = r"\frac{a}{b} + \frac{\alpha}{2} \{ x \}";
+    let entry = render_indexed(input, Opts::default()).unwrap();
+    assert_eq!(entry.html, render(input).unwrap());
+    assert_eq!(entry.source, input);
+    assert_eq!(entry.commands, [r"\frac", r"\alpha"]);
+    let mathml = entry.mathml.unwrap();
+    assert!(mathml.starts_with("<math"));
+    assert!(mathml.ends_with("</math>"));
+
+    let opts = Opts::builder()
+        .output_type(OutputType::Html)
+        .build()
+        .unwrap();
+    let entry = render_indexed(input, opts).unwrap();
+    assert_eq!(entry.mathml, None);
+}
+
+#[test]
+fn test_extensions() {
+    for &extension in Extension::ALL {
+        enable_extension(extension).unwrap();
+        assert!(!extension.commands().is_empty());
+    }
+}
+
+#[test]
+fn test_mhchem_feature() {
+    let result = render(r"\ce{H2O}");
+    if cfg!(feature = "mhchem") {
+        assert!(!result.unwrap().contains(r#"span class="katex-error""#));
+    } else {
+        assert!(matches!(result, Err(Error::UnsupportedCommand { .. })));
+    }
+}
+
+#[cfg(all(feature = "lazy-extensions", feature = "mhchem"))]
+#[test]
+fn test_lazy_extensions() {
+    let engine: Engine = init_katex().unwrap();
+    let loaded = |engine: &Engine| {
+        let name = engine.create_string_value("mhchem".to_owned()).unwrap();
+        let value = engine
+            .call_function("katexHasExtension", iter::once(name))
+            .unwrap();
+        engine.value_to_string(value).unwrap() == "true"
+    };
+    assert!(!loaded(&engine));
+    render_inner(&engine, "a + b", Opts::default()).unwrap();
+    assert!(!loaded(&engine));
+    let opts = Opts::builder()
+        .add_macro(r"\water".to_owned(), r"\ce{H2O}".to_owned())
+        .build()
+        .unwrap();
+    render_inner(&engine, r"\water", &opts).unwrap();
+    assert!(loaded(&engine));
+}
+
+#[test]
+fn test_normalize_backend_output() {
+    let opts = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .normalize_backend_output(true)
+        .build()
+        .unwrap();
+    // Identical for both KaTeX and Temml.
+    for (input, expected) in [
+        (
+            "a = b + c",
+            "<mi>a</mi><mo>=</mo><mi>b</mi><mo>+</mo><mi>c</mi>",
+        ),
+        (r"\frac{a}{b}", "<mfrac><mi>a</mi><mi>b</mi></mfrac>"),
+        (
+            r"\sqrt{x^2}",
+            "<msqrt><msup><mi>x</mi><mn>2</mn></msup></msqrt>",
+        ),
+    ] {
+        let html = render_with_opts(input, &opts).unwrap();
+        assert_eq!(
+            html,
+            format!(r#"<math xmlns="http://www.w3.org/1998/Math/MathML">{expected}</math>"#)
+        );
+    }
+
+    let opts = Opts::builder()
+        .display_mode(true)
+        .normalize_backend_output(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts("x", &opts).unwrap();
+    assert!(html.contains(
+        r#"<span class="katex-mathml"><math display="block" xmlns="http://www.w3.org/1998/Math/MathML"><mi>x</mi></math></span>"#
+    ));
+    assert!(html.contains(r#"<span class="katex-html" aria-hidden="true">"#));
+}
+
+#[test]
+fn test_ascii_mathvariant() {
+    assert_eq!(
+        postprocess::ascii_mathvariant("<math><mi>𝑥</mi><mo>+</mo><mi>𝐯𝐞𝐜</mi></math>"),
+        r#"<math><mi mathvariant="italic">x</mi><mo>+</mo><mi mathvariant="bold">vec</mi></math>"#
+    );
+    assert_eq!(
+        postprocess::ascii_mathvariant(r#"<mi class="a">𝒜</mi><mi>ℬ</mi><mn>𝟏𝟐</mn>"#),
+        r#"<mi class="a" mathvariant="script">A</mi><mi mathvariant="script">B</mi><mn mathvariant="bold">12</mn>"#
+    );
+    // Mixed variants, Greek letters, and explicit variants are left alone.
+    for unchanged in [
+        "<mi>𝑥𝐱</mi>",
+        "<mi>α</mi>",
+        r#"<mi mathvariant="bold">𝐱</mi>"#,
+    ] {
+        assert_eq!(postprocess::ascii_mathvariant(unchanged), unchanged);
+    }
+
+    let opts = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .ascii_mathvariant(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\mathbf{x} + \mathcal{B}", opts).unwrap();
+    assert!(html.contains(r#"<mi mathvariant="bold">x</mi>"#));
+    assert!(!html.contains('𝐱'));
+}
+
+#[test]
+fn test_first_error() {
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    assert_eq!(first_error(r"\frac{1}{2}", &opts).unwrap(), None);
+
+    let location = first_error(r"α + \frac{1}", &opts).unwrap().unwrap();
+    assert_eq!(location.line, Some(1));
+    assert_eq!(location.column, Some(13));
+    assert_eq!(location.code, "ParseError");
+    assert!(!location.message.starts_with("KaTeX parse error"));
+
+    let location = first_error("a\n+ \\foo", &opts).unwrap().unwrap();
+    assert_eq!((location.line, location.column), (Some(2), Some(3)));
+    assert!(location.message.contains(r"\foo"));
+}
+
+#[test]
+fn test_strict() {
+    // Unicode text in math mode is accepted by KaTeX but not by LaTeX.
+    let input = "é";
+    let opts = Opts::builder()
+        .strict(opts::Strictness::Error)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        render_with_opts(input, &opts),
+        Err(Error::ParseError { raw, .. }) if raw.contains("strict mode")
+    ));
+    let opts = Opts::builder()
+        .strict(opts::Strictness::Ignore)
+        .build()
+        .unwrap();
+    assert!(render_with_opts(input, &opts).is_ok());
+    let opts = Opts::builder()
+        .strict(opts::Strictness::Warn)
+        .build()
+        .unwrap();
+    assert!(render_with_opts(input, &opts).is_ok());
+    assert!(render(input).is_ok());
+}
+
+#[test]
+fn test_color_is_text_color() {
+    let input = r"\color{red}ab\color{blue}c";
+    let render_colored = |flag| {
+        let opts = Opts::builder().color_is_text_color(flag).build().unwrap();
+        render_with_opts(input, &opts).unwrap()
+    };
+    let group_color = render_colored(false);
+    let text_color = render_colored(true);
+    assert_ne!(group_color, text_color);
+    // As `\textcolor`, the first `\color` only applies to `a`.
+    assert!(group_color.contains(r#"style="color:red;">ab<"#));
+    assert!(text_color.contains(r#"style="color:red;">a<"#));
+}
+
+#[test]
+fn test_global_group() {
+    let opts = Opts::builder()
+        .global_group(true)
+        .throw_on_error(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\gdef\x{1}\x", &opts).unwrap();
+    assert!(html.contains(">1<"));
+    let html = render_with_opts(r"\def\y{2}\y", &opts).unwrap();
+    assert!(html.contains(">2<"));
+    // Definitions do not leak into later renders.
+    assert!(render_with_opts(r"\x", &opts).is_err());
+}
+
+#[test]
+fn test_render_stateful() {
+    let mut opts = Opts::builder()
+        .add_macro(r"\RR".to_owned(), r"\mathbb{R}".to_owned())
+        .throw_on_error(true)
+        .build()
+        .unwrap();
+    render_stateful(r"\gdef\foo{bar}\def\local{x}\foo", &mut opts).unwrap();
+    let html = render_stateful(r"\foo \RR", &mut opts).unwrap();
+    assert!(html.contains("<mi>b</mi><mi>a</mi><mi>r</mi>"));
+    assert!(html.contains("mathbb"));
+    assert!(render_with_opts(r"\foo", &opts).is_ok());
+    assert!(render_with_opts(r"\local", &opts).is_err());
+
+    // Control words, parameters and unused parameters survive the round trip.
+    render_stateful(
+        r"\gdef\pair#1#2{(#1,#2)}\gdef\second#1#2{#2}\gdef\alphax{\alpha x}",
+        &mut opts,
+    )
+    .unwrap();
+    let html = render_with_opts(r"\pair{a}{b} \second{c}{d} \alphax", &opts).unwrap();
+    assert!(html.contains(r#"<mo stretchy="false">(</mo><mi>a</mi>"#));
+    assert!(html.contains("<mi>d</mi>") && !html.contains("<mi>c</mi>"));
+    assert!(html.contains("<mi>α</mi><mi>x</mi>"));
+
+    let mut opts = Opts::builder()
+        .global_group(true)
+        .throw_on_error(true)
+        .build()
+        .unwrap();
+    render_stateful(r"\def\x{1}\gdef\f#1.{#1}", &mut opts).unwrap();
+    assert!(render_with_opts(r"\x", &opts)
+        .unwrap()
+        .contains("<mn>1</mn>"));
+    assert!(!opts.macro_definitions().contains_key(r"\f"));
+
+    let before = opts.clone();
+    assert!(render_stateful(r"\gdef\y{2}\frac{1}", &mut opts).is_err());
+    assert_eq!(opts, before);
+}
+
+#[cfg(feature = "temml")]
+#[test]
+fn test_temml_annotate() {
+    let opts = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .annotate(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts("a + b", &opts).unwrap();
+    assert!(html.contains(r#"<annotation encoding="application/x-tex">a + b</annotation>"#));
+    // `annotate` no longer toggles the XML namespace.
+    assert!(!html.contains("xmlns"));
+}
+
+#[test]
+fn test_katex_renderer() {
+    let loads = || BUNDLE_LOADS.with(|loads| loads.get());
+    let before = loads();
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let katex = Katex::with_opts(opts).unwrap();
+    assert_eq!(loads(), before + 1);
+
+    let html = katex.render("a + b").unwrap();
+    assert!(html.contains(r#"span class="katex-display""#));
+    let html = katex
+        .render_with_opts(r"\sqrt{2}", Opts::default())
+        .unwrap();
+    assert!(!html.contains(r#"span class="katex-display""#));
+    assert_eq!(loads(), before + 1);
+}
+
+#[test]
+fn test_render_timed() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let (html, elapsed) = render_timed(r"\sum_{i=1}^n i^2", &opts).unwrap();
+    assert_eq!(html, render_with_opts(r"\sum_{i=1}^n i^2", &opts).unwrap());
+    assert!(elapsed > std::time::Duration::ZERO);
+    assert!(render_timed(r"\frac{1}", &opts).is_err());
+}
+
+#[test]
+fn test_render_many() {
+    let opts = Opts::builder().throw_on_error(true).build().unwrap();
+    let results = render_many(&["a + b", r"\frac{1}", r"\sqrt{2}", r"\foo"], &opts);
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &render("a + b").unwrap());
+    assert!(matches!(results[1], Err(Error::ParseError { .. })));
+    assert!(results[2].as_ref().unwrap().contains("katex"));
+    assert!(
+        matches!(&results[3], Err(Error::UnsupportedCommand { command, .. }) if command == r"\foo")
+    );
+    assert!(render_many(&[], &opts).is_empty());
+}
+
+#[test]
+fn test_render_mixed() {
+    let opts = Opts::default();
+    let inline = render("x").unwrap();
+    let display =
+        render_with_opts("x", Opts::builder().display_mode(true).build().unwrap()).unwrap();
+
+    let html = render_mixed(r"a $x$ b $$x$$ c \(x\) d \[x\] <e>", &opts).unwrap();
+    assert_eq!(
+        html,
+        format!("a {inline} b {display} c {inline} d {display} &lt;e&gt;")
+    );
+
+    // Braces and escapes do not end the math, unmatched delimiters are text.
+    let html = render_mixed(r"$\text{$y$}\$$ and $5", &opts).unwrap();
+    assert!(html.ends_with(" and $5"));
+    assert_eq!(html.matches(r#"class="katex""#).count(), 1);
+
+    // Code is left alone, unless configured otherwise.
+    let html = render_mixed("<code>$x$</code> $x$", &opts).unwrap();
+    assert_eq!(html, format!("&lt;code&gt;$x$&lt;/code&gt; {inline}"));
+    let config = MixedConfig {
+        delimiters: vec![DelimiterConfig::new("@@", "@@", true)],
+        ignore_code: false,
+    };
+    let html = render_mixed_with_config("<code>@@x@@</code> $x$", &config, &opts).unwrap();
+    assert_eq!(html, format!("&lt;code&gt;{display}&lt;/code&gt; $x$"));
+
+    // Environments are rendered including their delimiters.
+    let html = render_mixed(r"\begin{gather}a\\b\end{gather}", &opts).unwrap();
+    assert!(html.contains("katex-display"));
+}
+
+#[test]
+fn test_render_mixed_custom_delimiters() {
+    let opts = Opts::default();
+    let inline = render("x").unwrap();
+    let config = MixedConfig {
+        delimiters: vec![
+            DelimiterConfig::new(r"\(", r"\)", false),
+            DelimiterConfig::new(r"\[", r"\]", true),
+        ],
+        ..MixedConfig::default()
+    };
+    let html = render_mixed_with_config(r"$x$ and $$x$$ cost \(x\)", &config, &opts).unwrap();
+    assert_eq!(html, format!("$x$ and $$x$$ cost {inline}"));
+
+    // An escaped delimiter is a literal, also inside other math.
+    let html = render_mixed(r"\$5 and \$6, $x$", &opts).unwrap();
+    assert_eq!(html, format!("$5 and $6, {inline}"));
+    let html = render_mixed(r"\$$x$", &opts).unwrap();
+    assert_eq!(html, format!("${inline}"));
+    let html = render_mixed(r"$\$$", &opts).unwrap();
+    assert_eq!(html, render(r"\$").unwrap());
+    let html = render_mixed_with_config(r"\$x\$ \(x\)", &config, &opts).unwrap();
+    assert_eq!(html, format!(r"\$x\$ {inline}"));
+}
+
+#[test]
+fn test_render_speech() {
+    let opts = Opts::default();
+    let speech = render_speech(r"\frac{a}{b}", &opts).unwrap();
+    assert!(speech.contains("fraction"));
+
+    let speech = render_speech(r"\sqrt{x^2 + y_1} \leq \text{some bound}", &opts).unwrap();
+    assert_eq!(
+        speech,
+        "square root of, x, squared, plus, y, start subscript, 1, end subscript, \
+         end square root, less than or equal to, start text, some bound, end text"
+    );
+
+    let opts = Opts::builder()
+        .add_macro(r"\half".to_owned(), r"\frac{1}{2}".to_owned())
+        .display_mode(true)
+        .build()
+        .unwrap();
+    let speech = render_speech(r"\half", &opts).unwrap();
+    assert_eq!(speech, "start fraction, 1, divided by, 2, end fraction");
+    assert!(matches!(
+        render_speech(r"\frac{1}", &opts),
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_runtime_version() {
+    assert_eq!(runtime_version().unwrap(), KATEX_VERSION.trim());
+}
+
+#[test]
+fn test_backend_name() {
+    let expected = if cfg!(feature = "quick-js") {
+        "quick-js"
+    } else if cfg!(feature = "duktape") {
+        "duktape"
+    } else {
+        "wasm-js"
+    };
+    assert_eq!(backend_name(), expected);
+}
+
+#[test]
+fn test_error_accessors() {
+    let opts = Opts::builder().throw_on_error(true).build().unwrap();
+    let error = render_with_opts("x^", &opts).unwrap_err();
+    assert_eq!(error.message(), Some("Expected group after '^'"));
+    assert_eq!(error.position(), Some(1));
+    assert!(error
+        .detail()
+        .starts_with("KaTeX parse error: Expected group after '^'"));
+    assert_eq!(
+        error.to_string(),
+        format!("failed to parse input (detail: {})", error.detail())
+    );
+    assert_eq!((error.command(), error.js_error_name()), (None, None));
+
+    let error = render_with_opts(r"a \foo", &opts).unwrap_err();
+    assert_eq!(error.command(), Some(r"\foo"));
+    assert_eq!(error.position(), Some(2));
+    assert_eq!(error.message(), None);
+
+    let error = Error::KatexInternal {
+        name: "TypeError".to_owned(),
+        message: "x is undefined".to_owned(),
+    };
+    assert_eq!(error.js_error_name(), Some("TypeError"));
+    assert_eq!(error.message(), Some("x is undefined"));
+    assert_eq!(error.detail(), "x is undefined");
+
+    let error = Error::JsExecError("timeout".to_owned(), None);
+    assert_eq!(error.detail(), "timeout");
+    assert_eq!((error.message(), error.position()), (None, None));
+    assert_eq!(Error::InvalidOptions("bad".to_owned()).detail(), "bad");
+    assert!(!Error::WriteError(core::fmt::Error).detail().is_empty());
+}
+
+#[test]
+fn test_parse_error() {
+    let opts = Opts::builder().throw_on_error(true).build().unwrap();
+    match render_with_opts(r"x^", &opts) {
+        Err(Error::ParseError {
+            message,
+            position,
+            raw,
+        }) => {
+            assert_eq!(message, "Expected group after '^'");
+            assert_eq!(position, Some(1));
+            assert!(raw.starts_with("KaTeX parse error: Expected group after '^' at position 2"));
+        }
+        _ => unreachable!(),
+    }
+    assert!(matches!(
+        render_with_opts(r"\frac{1}", &opts),
+        Err(Error::ParseError {
+            position: Some(_),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_unsupported_command() {
+    let opts = Opts::builder().throw_on_error(true).build().unwrap();
+    match render_with_opts(r"a + \foobar{x}", &opts) {
+        Err(Error::UnsupportedCommand {
+            command,
+            position,
+            raw,
+        }) => {
+            assert_eq!(command, r"\foobar");
+            assert_eq!(position, Some(4));
+            assert!(raw.starts_with("KaTeX parse error: Undefined control sequence"));
+        }
+        result => panic!("{result:?}"),
+    }
+    assert!(matches!(
+        render_with_opts(r"\frac{1}", &opts),
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_extract_mathml() {
+    let mathml =
+        |opts: Opts| postprocess::extract_mathml(&render_with_opts("a + b", opts).unwrap());
+
+    let inline = mathml(Opts::default()).unwrap();
+    assert!(inline.starts_with("<math"));
+    assert!(inline.ends_with("</math>"));
+    assert!(!inline.contains("katex-html"));
+    assert!(!inline.contains(r#"display="block""#));
+
+    let display = mathml(Opts::builder().display_mode(true).build().unwrap()).unwrap();
+    assert!(display.starts_with("<math"));
+    assert!(display.contains(r#"display="block""#));
+    assert!(display.ends_with("</math>"));
+
+    let mathml_only = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .build()
+        .unwrap();
+    assert!(mathml(mathml_only).unwrap().starts_with("<math"));
+
+    let html_only = Opts::builder()
+        .output_type(OutputType::Html)
+        .build()
+        .unwrap();
+    assert_eq!(mathml(html_only), None);
+    let error = render_with_opts(
+        r"\frac{1}",
+        Opts::builder().throw_on_error(false).build().unwrap(),
+    );
+    assert_eq!(postprocess::extract_mathml(&error.unwrap()), None);
+}
+
+#[test]
+fn test_line_break_hints() {
+    let input = r"a + \frac{b + c}{d} = {e + f} - g";
+    let mut builder = Opts::builder();
+    builder.output_type(OutputType::Html);
+    let plain = render_with_opts(input, builder.build().unwrap()).unwrap();
+    let html = render_with_opts(input, builder.line_break_hints(true).build().unwrap()).unwrap();
+    assert_eq!(html.replace("<wbr>", ""), plain);
+
+    // Breaks follow the top-level `+`, `=` and `-`, not those in the
+    // numerator or braces.
+    let segments: Vec<_> = html.split("<wbr>").collect();
+    assert_eq!(segments.len(), 4);
+    assert!(segments[0].contains(r#"<span class="mbin">+</span>"#));
+    assert!(
+        segments[1].contains("mfrac") && segments[1].contains(r#"<span class="mrel">=</span>"#)
+    );
+    assert!(segments[2].contains(r#"<span class="mbin">−</span>"#));
+    for segment in &segments[1..] {
+        assert!(segment.starts_with(r#"<span class="base">"#));
+    }
+
+    let html = render_with_opts("a + b", builder.display_mode(true).build().unwrap()).unwrap();
+    assert!(!html.contains("<wbr>"));
+}
+
+#[test]
+fn test_unwrap_outer() {
+    let unwrapped = |mut opts: OptsBuilder, input| {
+        let full = render_with_opts(input, opts.build().unwrap()).unwrap();
+        let stripped = render_with_opts(input, opts.unwrap_outer(true).build().unwrap()).unwrap();
+        assert_eq!(stripped, postprocess::strip_wrapper(&full));
+        (full, stripped)
+    };
+
+    let (full, stripped) = unwrapped(Opts::builder(), "a + b");
+    assert_eq!(format!(r#"<span class="katex">{stripped}</span>"#), full);
+
+    let mut display = Opts::builder();
+    display.display_mode(true);
+    let (full, stripped) = unwrapped(display, "a + b");
+    assert!(stripped.starts_with(r#"<span class="katex-display"><span class="katex-mathml">"#));
+    assert_eq!(
+        stripped.matches("<span").count() + 1,
+        full.matches("<span").count()
+    );
+    assert_eq!(
+        stripped.matches("</span>").count() + 1,
+        full.matches("</span>").count()
+    );
+
+    let mut error_color = Opts::builder();
+    error_color.throw_on_error(false).error_color("#cc0000");
+    let (full, stripped) = unwrapped(error_color, r"\frac{1}");
+    assert!(full.starts_with(r#"<span class="katex-error""#));
+    assert_eq!(stripped, full);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_opts_serde() {
+    let opts = Opts::builder()
+        .add_macro(r"\RR".to_owned(), r"\mathbb{R}".to_owned())
+        .display_mode(true)
+        .output_type(OutputType::HtmlAndMathml)
+        .max_size(None)
+        .max_expand(Some(50))
+        .strict(opts::Strictness::Ignore)
+        .fleqn_indent(opts::Length::Em(2.0))
+        .build()
+        .unwrap();
+    let json = serde_json::to_value(&opts).unwrap();
+    assert_eq!(json["displayMode"], true);
+    assert_eq!(json["output"], "htmlAndMathml");
+    assert_eq!(json["macros"], serde_json::json!({ r"\RR": r"\mathbb{R}" }));
+    assert_eq!(json["maxSize"], serde_json::Value::Null);
+    assert_eq!(json["maxExpand"], 50);
+    assert_eq!(json["strict"], "ignore");
+
+    let round_trip: Opts = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+    assert_eq!(format!("{round_trip:?}"), format!("{opts:?}"));
+
+    // A subset of the fields is enough, the rest keeps its default.
+    let subset: Opts =
+        serde_json::from_str(r#"{ "displayMode": true, "throwOnError": false }"#).unwrap();
+    let html = render_with_opts(r"\frac{1}", &subset).unwrap();
+    assert!(html.contains("katex-error"));
+    assert_eq!(
+        format!("{:?}", Opts::default()),
+        format!("{:?}", serde_json::from_str::<Opts>("{}").unwrap())
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_opts_from_katex_json() {
+    let json = r##"{
+        "displayMode": true,
+        "output": "html",
+        "leqno": false,
+        "fleqn": true,
+        "throwOnError": false,
+        "errorColor": "#cc0000",
+        "macros": {
+            "\\RR": "\\mathbb{R}",
+            "\\norm": "\\left\\lVert#1\\right\\rVert"
+        },
+        "minRuleThickness": 0.05,
+        "colorIsTextColor": true,
+        "maxSize": 10,
+        "maxExpand": 1000,
+        "strict": false,
+        "trust": false,
+        "globalGroup": false,
+        "delimiters": [{ "left": "$$", "right": "$$", "display": true }],
+        "ignoredTags": ["script"]
+    }"##;
+    let expected = Opts::builder()
+        .add_macro(r"\RR".to_owned(), r"\mathbb{R}".to_owned())
+        .add_macro(r"\norm".to_owned(), r"\left\lVert#1\right\rVert".to_owned())
+        .display_mode(true)
+        .output_type(OutputType::Html)
+        .leqno(false)
+        .fleqn(true)
+        .throw_on_error(false)
+        .error_color("#cc0000")
+        .min_rule_thickness(0.05)
+        .color_is_text_color(true)
+        .max_size(Some(10.0))
+        .max_expand(Some(1000))
+        .strict(opts::Strictness::Ignore)
+        .trust_policy(opts::TrustPolicy::None)
+        .global_group(false)
+        .build()
+        .unwrap();
+    assert_eq!(Opts::from_katex_json(json).unwrap(), expected);
+
+    let (opts, warnings) = Opts::from_katex_json_with_warnings(json, false).unwrap();
+    assert_eq!(opts, expected);
+    assert_eq!(
+        warnings,
+        [
+            "unknown option `delimiters` ignored",
+            "unknown option `ignoredTags` ignored"
+        ]
+    );
+    let html = render_with_opts(r"\norm{\RR}", &opts).unwrap();
+    assert!(html.contains("katex-display") && html.contains("mathbb"));
+
+    let error = Opts::from_katex_json_with_warnings(json, true).unwrap_err();
+    assert!(matches!(error, Error::InvalidOptions(_)));
+    assert!(error.detail().contains("delimiters, ignoredTags"));
+
+    let opts = Opts::from_katex_json(r#"{ "strict": true }"#).unwrap();
+    assert_eq!(
+        opts,
+        Opts::builder()
+            .strict(opts::Strictness::Error)
+            .build()
+            .unwrap()
+    );
+    assert_eq!(Opts::from_katex_json("{}").unwrap(), Opts::default());
+    for invalid in [
+        "",
+        "[]",
+        r#"{ "displayMode": "yes" }"#,
+        r#"{ "output": "svg" }"#,
+    ] {
+        assert!(
+            matches!(
+                Opts::from_katex_json(invalid),
+                Err(Error::InvalidOptions(_))
+            ),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn test_output_type_from_str() {
+    for output_type in [
+        OutputType::Html,
+        OutputType::Mathml,
+        OutputType::HtmlAndMathml,
+    ] {
+        let name = output_type.to_string();
+        assert_eq!(name.parse(), Ok(output_type));
+        assert_eq!(name.to_uppercase().parse(), Ok(output_type));
+    }
+    let error = "svg".parse::<OutputType>().unwrap_err();
+    assert!(error.to_string().contains("`svg`"));
+}
+
+#[test]
+fn test_max_expand_shorthands() {
+    let engine: Engine = init_katex().unwrap();
+    engine
+        .eval("function __maxExpand(opts) { return String(opts.maxExpand); }")
+        .unwrap();
+    let max_expand = |opts: Opts| {
+        let opts = opts.to_js_value(&engine, "").unwrap();
+        let value = engine
+            .call_function("__maxExpand", iter::once(opts))
+            .unwrap();
+        engine.value_to_string(value).unwrap()
+    };
+    let unlimited = Opts::builder().max_expand_unlimited().build().unwrap();
+    assert_eq!(max_expand(unlimited), "Infinity");
+    let limited = Opts::builder().max_expand_limit(42).build().unwrap();
+    assert_eq!(max_expand(limited), "42");
+    assert_eq!(max_expand(Opts::default()), "undefined");
+
+    let opts = Opts::builder().max_expand_limit(2).build().unwrap();
+    assert!(render_with_opts(r"\def\a{x}\a\a\a", opts).is_err());
+}
+
+#[cfg(feature = "temml")]
+#[test]
+fn test_temml_options() {
+    let engine: Engine = init_katex().unwrap();
+    engine
+        .eval("function __keys(opts) { return JSON.stringify(opts, Object.keys(opts).sort()); }")
+        .unwrap();
+    let keys = |opts: &Opts| {
+        let opts = opts.to_js_value(&engine, "").unwrap();
+        let value = engine.call_function("__keys", iter::once(opts)).unwrap();
+        engine.value_to_string(value).unwrap()
+    };
+    let mut builder = Opts::builder();
+    builder
+        .display_mode(true)
+        .leqno(true)
+        .fleqn(true)
+        .min_rule_thickness(0.1)
+        .global_group(true)
+        .max_size(Some(5.0))
+        .annotate(true);
+    assert_eq!(
+        keys(&builder.output_type(OutputType::Mathml).build().unwrap()),
+        r#"{"annotate":true,"displayMode":true,"leqno":true,"maxSize":[5,50]}"#
+    );
+    assert_eq!(
+        keys(&builder.output_type(OutputType::Html).build().unwrap()),
+        r#"{"annotate":true,"displayMode":true,"fleqn":true,"globalGroup":true,"leqno":true,"maxSize":5,"minRuleThickness":0.1,"output":"html"}"#
+    );
+
+    let opts = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .max_size(Some(1.0))
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\rule{1em}{10em}", &opts).unwrap();
+    assert!(html.contains(r#"height="1em""#));
+}
+
+#[test]
+fn test_max_expand_unlimited_many() {
+    // Every level doubles the expansions of the empty `\z`, about 130k in all.
+    let mut input = r"\def\z{}".to_owned();
+    let mut previous = r"\z".to_owned();
+    for level in 'a'..='p' {
+        let name = format!(r"\z{level}");
+        input += &format!(r"\def{name}{{{}}}", previous.repeat(2));
+        previous = name;
+    }
+    input += &format!("x{previous} y");
+
+    let mut opts = Opts::builder().max_expand_unlimited().build().unwrap();
+    let html = render_with_opts(&input, &opts).unwrap();
+    assert!(html.contains("<mi>x</mi><mi>y</mi>"));
+
+    opts.set_max_expand(Some(100_000));
+    assert!(render_with_opts(&input, &opts).is_err());
+}
+
+#[cfg(feature = "quick-js")]
+#[test]
+fn test_memory_limit() {
+    let opts = Opts::builder().max_expand(None).build().unwrap();
+    let config = RuntimeConfig::default().memory_limit(16 << 20);
+    let katex = Katex::with_runtime_config(opts, &config).unwrap();
+    // Expands into a million `x`.
+    let bomb = concat!(
+        r"\def\a{xxxxxxxxxx}\def\b{\a\a\a\a\a\a\a\a\a\a}",
+        r"\def\c{\b\b\b\b\b\b\b\b\b\b}\def\d{\c\c\c\c\c\c\c\c\c\c}",
+        r"\def\e{\d\d\d\d\d\d\d\d\d\d}\def\f{\e\e\e\e\e\e\e\e\e\e}\f",
+    );
+    assert!(matches!(katex.render(bomb), Err(Error::JsExecError(..))));
+    assert!(katex.render("a + b").is_ok());
+}
+
+#[cfg(feature = "quick-js")]
+#[test]
+fn test_bundle_bytecode() {
+    use std::time::{Duration, Instant};
+
+    // Anything but the bundle itself is evaluated from source.
+    let source = format!("{JS_SRC}\n");
+    let from_bytecode = Engine::new().unwrap();
+    assert!(js_engine::load_bundle(&from_bytecode).unwrap());
+    let from_source = Engine::new().unwrap();
+    from_source.eval_bundle(&source).unwrap();
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    for input in [
+        r"\frac{1}{2}",
+        r"\sqrt{x^2 + y^2}",
+        r"\begin{pmatrix}a\\b\end{pmatrix}",
+    ] {
+        assert_eq!(
+            render_inner(&from_bytecode, input, &opts).unwrap(),
+            render_inner(&from_source, input, &opts).unwrap()
+        );
+    }
+    assert_eq!(
+        from_bytecode
+            .value_to_string(
+                from_bytecode
+                    .call_function("katexVersion", iter::empty())
+                    .unwrap()
+            )
+            .unwrap(),
+        KATEX_VERSION
+    );
+
+    // Loading the bytecode saves parsing the bundle.
+    let fastest = |code: &str| {
+        (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                Engine::new().unwrap().eval_bundle(code).unwrap();
+                start.elapsed()
+            })
+            .min()
+            .unwrap_or(Duration::MAX)
+    };
+    let bytecode = fastest(JS_SRC);
+    let source = fastest(&source);
+    assert!(
+        bytecode < source,
+        "bytecode {bytecode:?}, source {source:?}"
+    );
+}
+
+#[cfg(feature = "quick-js")]
+#[test]
+fn test_max_stack_size() {
+    // Deep enough to exhaust the default limit in release builds too.
+    let input = r"\frac{1}{".repeat(200) + "x" + &"}".repeat(200);
+    let katex = Katex::new().unwrap();
+    assert!(matches!(
+        katex.render(&input),
+        Err(Error::KatexInternal { name, .. }) if name == "RangeError"
+    ));
+
+    // The thread needs a stack larger than the limit.
+    std::thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(move || {
+            let config = RuntimeConfig::default().max_stack_size(128 << 20);
+            let katex = Katex::with_runtime_config(Opts::default(), &config).unwrap();
+            assert!(katex.render(&input).unwrap().contains("mfrac"));
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn test_collect_garbage() {
+    let katex = Katex::new().unwrap();
+    let expected = katex.render(r"\sqrt{x^2 + y^2}").unwrap();
+    for i in 0..200 {
+        let html = katex
+            .render(&format!(r"\frac{{{i}}}{{\sqrt{{{i} + x}}}}"))
+            .unwrap();
+        assert!(html.contains(&i.to_string()));
+    }
+    katex.collect_garbage();
+    katex.collect_garbage();
+    assert_eq!(katex.render(r"\sqrt{x^2 + y^2}").unwrap(), expected);
+}
+
+#[cfg(feature = "quick-js")]
+#[test]
+fn test_render_with_timeout() {
+    use std::time::{Duration, Instant};
+
+    let opts = Opts::builder().max_expand(None).build().unwrap();
+    let input = r"\frac{a}{\sqrt{b}} \def\x{y} \x";
+    let before = render_with_timeout(input, &opts, Duration::from_secs(10)).unwrap();
+    let start = Instant::now();
+    match render_with_timeout(r"\def\loop{\loop}\loop", &opts, Duration::from_millis(100)) {
+        Err(Error::JsExecError(msg, _)) => assert_eq!(msg, "timeout"),
+        result => panic!("unexpected result {result:?}"),
+    }
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // The engine renders as before the interruption.
+    let after = render_with_timeout(input, &opts, Duration::from_secs(10)).unwrap();
+    assert_eq!(after, before);
+    assert_eq!(after, render_with_opts(input, &opts).unwrap());
+    let html = render_with_timeout("a + b", &opts, Duration::from_secs(10)).unwrap();
+    assert_eq!(html, render("a + b").unwrap());
+
+    // Passing the deadline without interrupting JS code is no timeout.
+    with_engine(|engine| {
+        let deadline = Instant::now();
+        let ((), interrupted) = js_engine::with_deadline(engine, deadline, || {
+            std::thread::sleep(Duration::from_millis(1));
+        });
+        assert!(!interrupted);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_warm_up() {
+    let loads = || BUNDLE_LOADS.with(|loads| loads.get());
+    let before = loads();
+    warm_up().unwrap();
+    assert_eq!(loads(), before + 1);
+    warm_up().unwrap();
+    render("a + b").unwrap();
+    assert_eq!(loads(), before + 1);
+}
+
+#[test]
+fn test_reset_engine() {
+    let loads = || BUNDLE_LOADS.with(|loads| loads.get());
+    let html = render("a + b").unwrap();
+    let before = loads();
+    reset_engine().unwrap();
+    assert_eq!(loads(), before + 1);
+    assert_eq!(render("a + b").unwrap(), html);
+    assert_eq!(loads(), before + 1);
+}
+
+#[test]
+fn test_create_array_value() {
+    let engine: Engine = init_katex().unwrap();
+    engine
+        .eval("function __describe(a) { return Array.isArray(a) + ' ' + JSON.stringify(a); }")
+        .unwrap();
+    let items = [
+        engine.create_int_value(1).unwrap(),
+        engine.create_string_value("a".to_owned()).unwrap(),
+        engine.create_bool_value(true).unwrap(),
+    ];
+    let array = engine.create_array_value(items.into_iter()).unwrap();
+    let described = engine
+        .call_function("__describe", iter::once(array))
+        .unwrap();
+    assert_eq!(
+        engine.value_to_string(described).unwrap(),
+        r#"true [1,"a",true]"#
+    );
+}
+
+#[test]
+fn test_create_null_value() {
+    let engine: Engine = init_katex().unwrap();
+    engine
+        .eval("function __describe(a) { return String(a === null); }")
+        .unwrap();
+    let null = engine.create_null_value().unwrap();
+    let described = engine
+        .call_function("__describe", iter::once(null))
+        .unwrap();
+    assert_eq!(engine.value_to_string(described).unwrap(), "true");
+
+    // KaTeX accepts `null` in place of the options object.
+    let args = [
+        engine.create_string_value("x".to_owned()).unwrap(),
+        engine.create_null_value().unwrap(),
+    ];
+    let html = engine
+        .call_function("katexRenderToString", args.into_iter())
+        .unwrap();
+    assert!(engine.value_to_string(html).unwrap().contains("katex"));
+}
+
+#[test]
+fn test_engine_arrays_and_callbacks() {
+    let engine: Engine = init_katex().unwrap();
+    engine
+        .eval("function __join(a) { return a.join('|') + ':' + __rust('x', 1, null); }")
+        .unwrap();
+    engine
+        .register_callback("__rust", Box::new(|args: &[String]| args.join(",")))
+        .unwrap();
+    let items = ["a", "b"].map(|item| engine.create_string_value(item.to_owned()).unwrap());
+    let array = engine.create_array_value(items.into_iter()).unwrap();
+    let joined = engine.call_function("__join", iter::once(array)).unwrap();
+    assert_eq!(engine.value_to_string(joined).unwrap(), "a|b:x,1,null");
+
+    // Registering again replaces the callback.
+    engine
+        .register_callback("__rust", Box::new(|args: &[String]| args.len().to_string()))
+        .unwrap();
+    let array = engine.create_array_value(iter::empty()).unwrap();
+    let joined = engine.call_function("__join", iter::once(array)).unwrap();
+    assert_eq!(engine.value_to_string(joined).unwrap(), ":3");
+}
+
+#[test]
+fn test_max_size_unlimited() {
+    let input = r"\rule{1000em}{1em}";
+    let opts = Opts::builder().max_size(None).build().unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert!(html.contains("border-right-width:1000em"));
+
+    let opts = Opts::builder().max_size(Some(10.0)).build().unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert!(html.contains("border-right-width:10em"));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_trust_callback() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let opts = Opts::builder()
+        .trust_callback({
+            let seen = Arc::clone(&seen);
+            move |context: &opts::TrustContext| {
+                seen.lock().unwrap().push(context.clone());
+                context.protocol.as_deref() == Some("https")
+            }
+        })
+        .trust(false)
+        .build()
+        .unwrap();
+
+    let html = render_with_opts(r"\href{https://katex.org}{a}", &opts).unwrap();
+    assert!(html.contains(r#"<a href="https://katex.org">"#));
+    let html = render_with_opts(r"\href{javascript:alert(1)}{a}", &opts).unwrap();
+    assert!(!html.contains("<a "));
+    assert!(html.contains("<mtext>\\href</mtext>"));
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].command, r"\href");
+    assert_eq!(seen[0].url.as_deref(), Some("https://katex.org"));
+    assert_eq!(seen[1].protocol.as_deref(), Some("javascript"));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_allowed_protocols() {
+    let opts = Opts::builder()
+        .allowed_protocols(vec!["https".to_owned(), "MAILTO".to_owned()])
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\href{https://katex.org}{a}", &opts).unwrap();
+    assert!(html.contains(r#"<a href="https://katex.org">"#));
+    let html = render_with_opts(r"\href{mailto:a@b.c}{a}", &opts).unwrap();
+    assert!(html.contains(r#"<a href="mailto:a@b.c">"#));
+    for input in [
+        r"\href{http://katex.org}{a}",
+        r"\href{javascript:alert(1)}{a}",
+    ] {
+        let html = render_with_opts(input, &opts).unwrap();
+        assert!(!html.contains("<a "), "{input}");
+    }
+    let html = render_with_opts(r"\htmlClass{foo}{a}", &opts).unwrap();
+    assert!(!html.contains("enclosing foo"));
+
+    let mut opts = opts;
+    opts.set_trust(true);
+    let html = render_with_opts(r"\htmlClass{foo}{a}", &opts).unwrap();
+    assert!(html.contains("enclosing foo"));
+    let html = render_with_opts(r"\href{http://katex.org}{a}", &opts).unwrap();
+    assert!(!html.contains("<a "));
+
+    let opts = Opts::builder()
+        .trust(true)
+        .allowed_protocols(Vec::<String>::new())
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\href{https://katex.org}{a}", &opts).unwrap();
+    assert!(!html.contains("<a "));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_trusted_commands() {
+    let opts = Opts::builder()
+        .trusted_commands(vec![r"\htmlId".to_owned(), "htmlClass".to_owned()])
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\htmlId{a}{x}", &opts).unwrap();
+    assert!(html.contains(r#"id="a""#));
+    let html = render_with_opts(r"\htmlClass{foo}{x}", &opts).unwrap();
+    assert!(html.contains("enclosing foo"));
+    for input in [r"\href{https://katex.org}{a}", r"\url{https://katex.org}"] {
+        let html = render_with_opts(input, &opts).unwrap();
+        assert!(!html.contains("<a "), "{input}");
+    }
+    let html = render_with_opts(r"\htmlData{foo=bar}{x}", &opts).unwrap();
+    assert!(!html.contains("data-foo"));
+
+    let opts = Opts::builder()
+        .trust(true)
+        .trusted_commands(vec![r"\href".to_owned()])
+        .allowed_protocols(vec!["https".to_owned()])
+        .build()
+        .unwrap();
+    let html = render_with_opts(r"\href{https://katex.org}{a}", &opts).unwrap();
+    assert!(html.contains(r#"<a href="https://katex.org">"#));
+    let html = render_with_opts(r"\href{http://katex.org}{a}", &opts).unwrap();
+    assert!(!html.contains("<a "));
+    let html = render_with_opts(r"\htmlId{a}{x}", &opts).unwrap();
+    assert!(!html.contains(r#"id="a""#));
+}
+
+#[test]
+fn test_trusted_includegraphics() {
+    let input = r"\includegraphics[height=1em]{https://katex.org/img/x.png}";
+    let html = render(input).unwrap();
+    assert!(!html.contains("<img"));
+    assert!(html.contains(r#"style="color:#cc0000;">\includegraphics</span>"#));
+
+    let opts = Opts::builder()
+        .trusted_commands(vec![r"\includegraphics".to_owned()])
+        .allowed_protocols(vec!["https".to_owned()])
+        .build()
+        .unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert!(html.contains(r#"<img src="https://katex.org/img/x.png""#));
+    let html = render_with_opts(r"\includegraphics{http://katex.org/x.png}", &opts).unwrap();
+    assert!(!html.contains("<img"));
+    let html = render_with_opts(r"\href{https://katex.org}{a}", &opts).unwrap();
+    assert!(!html.contains("<a "));
+
+    let opts = Opts::builder()
+        .trust_callback(|context: &opts::TrustContext| {
+            context.command == r"\includegraphics"
+                && context.url.as_deref() == Some("https://katex.org/img/x.png")
+        })
+        .build()
+        .unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert!(html.contains("<img"));
+    let html = render_with_opts(r"\includegraphics{https://evil.example/x.png}", &opts).unwrap();
+    assert!(!html.contains("<img"));
+}
+
+#[test]
+fn test_render_with_warnings() {
+    let opts = Opts::default();
+    let (html, warnings) = render_with_warnings(r"\text{a} é", &opts).unwrap();
+    assert!(html.contains("é"));
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("é"));
+    assert!(warnings[0].ends_with("[unicodeTextInMathMode]"));
+
+    let (_, warnings) = render_with_warnings("a + b", &opts).unwrap();
+    assert!(warnings.is_empty());
+
+    let opts = Opts::builder()
+        .strict(opts::Strictness::Ignore)
+        .build()
+        .unwrap();
+    let (_, warnings) = render_with_warnings("é", &opts).unwrap();
+    assert!(warnings.is_empty());
+
+    let opts = Opts::builder()
+        .strict(opts::Strictness::Error)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        render_with_warnings("é", &opts),
+        Err(Error::ParseError { .. })
+    ));
+    // Warnings are only collected when asked for.
+    assert!(render("é").is_ok());
+}
+
+#[test]
+fn test_render_collecting_violations() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let (html, violations) = render_collecting_violations(r"é \\ a", &opts).unwrap();
+    assert!(html.contains("é"));
+    let codes: Vec<_> = violations.iter().map(|v| v.code.as_str()).collect();
+    assert_eq!(codes, ["unicodeTextInMathMode", "newLineInDisplayMode"]);
+    assert!(violations[0].message.contains("é"));
+    assert_eq!(
+        violations[0].to_string(),
+        format!("{} [unicodeTextInMathMode]", violations[0].message)
+    );
+
+    for strict in [opts::Strictness::Ignore, opts::Strictness::Error] {
+        let opts = Opts::builder().strict(strict).build().unwrap();
+        let (_, violations) = render_collecting_violations("é", &opts).unwrap();
+        assert_eq!(violations.len(), 1);
+        // The strictness still applies to other renders.
+        assert_eq!(
+            render_with_opts("é", &opts).is_ok(),
+            strict == opts::Strictness::Ignore
+        );
+    }
+
+    let (_, violations) = render_collecting_violations("a + b", &opts).unwrap();
+    assert!(violations.is_empty());
+    assert!(render_collecting_violations(r"\frac{1}", &opts).is_err());
+}
+
+#[test]
+fn test_physics() {
+    // KaTeX defines `\bra` / `\ket` itself, with or without the extension.
+    assert!(render(r"\bra{\psi}").is_ok());
+    for input in [r"\dv{f}{x}", r"\abs{x}", r"\expval{A}"] {
+        let result = render(input);
+        if cfg!(feature = "physics") {
+            assert!(result.unwrap().contains("katex"), "{input}");
+        } else {
+            assert!(
+                matches!(result, Err(Error::UnsupportedCommand { .. })),
+                "{input}: {result:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_texvc() {
+    // KaTeX defines most texvc macros itself, with or without the extension.
+    for input in [r"x \in \Reals", r"\infin", r"\plusmn 1"] {
+        assert!(render(input).is_ok(), "{input}");
+    }
+    for input in [
+        r"\sgn x",
+        r"\arccot x",
+        r"\arcsec x",
+        r"\arccsc x",
+        r"\sen x",
+    ] {
+        let result = render(input);
+        if cfg!(feature = "texvc") {
+            assert!(result.unwrap().contains("<mi>"), "{input}");
+        } else {
+            assert!(
+                matches!(result, Err(Error::UnsupportedCommand { .. })),
+                "{input}: {result:?}"
+            );
+        }
+    }
+    #[cfg(feature = "temml")]
+    {
+        let opts = Opts::builder()
+            .output_type(OutputType::Mathml)
+            .build()
+            .unwrap();
+        let mathml = render_with_opts(r"\sgn x + \Reals", &opts).unwrap();
+        assert!(mathml.contains("sgn"));
+    }
+}
+
+#[test]
+fn test_cancel() {
+    // `\cancel` and friends are built into KaTeX, no contrib script needed.
+    for input in [r"\cancel{x}", r"\bcancel{x}", r"\xcancel{x}"] {
+        let html = render(input).unwrap();
+        assert!(html.contains("<svg"), "{input}");
+        assert!(html.contains("<line"), "{input}");
+    }
+}
+
+#[test]
+fn test_embed_source() {
+    let input = r#"\text{"a" \& 'b'} < x"#;
+    let opts = Opts::builder()
+        .output_type(OutputType::Html)
+        .embed_source(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert_eq!(html.matches("data-tex=").count(), 1);
+    assert!(!html.contains(input));
+    assert_eq!(postprocess::extract_source(&html).as_deref(), Some(input));
+
+    let mut unwrapped = opts.clone();
+    unwrapped.set_unwrap_outer(true);
+    let html = render_with_opts(input, &unwrapped).unwrap();
+    assert_eq!(postprocess::extract_source(&html).as_deref(), Some(input));
+
+    // The MathML annotation already carries the source.
+    let opts = Opts::builder().embed_source(true).build().unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert!(!html.contains("data-tex="));
+    assert_eq!(postprocess::extract_source(&html).as_deref(), Some(input));
+
+    let opts = Opts::builder()
+        .output_type(OutputType::Html)
+        .build()
+        .unwrap();
+    let html = render_with_opts(input, &opts).unwrap();
+    assert_eq!(postprocess::extract_source(&html), None);
+}
+
+#[test]
+fn test_parse_tree() {
+    let opts = Opts::default();
+    let tree = parse_tree("a+b", &opts).unwrap();
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let nodes = tree.as_array().unwrap();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0]["type"], "mathord");
+    assert_eq!(nodes[0]["text"], "a");
+    assert_eq!(nodes[0]["loc"], serde_json::json!({"start": 0, "end": 1}));
+    assert_eq!(nodes[1]["type"], "atom");
+
+    let tree = parse_tree(r"\frac12", &opts).unwrap();
+    assert!(tree.starts_with(r#"[{"type":"genfrac""#));
+
+    assert!(matches!(
+        parse_tree(r"\frac{", &opts),
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_validate() {
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    assert!(validate(r"\frac{1}{2}", &opts).is_ok());
+    assert!(matches!(
+        validate(r"\frac{1}", &opts),
+        Err(Error::ParseError { .. })
+    ));
+    assert!(matches!(
+        validate(r"\foo", &opts),
+        Err(Error::UnsupportedCommand { command, .. }) if command == r"\foo"
+    ));
+
+    let opts = Opts::builder()
+        .add_macro(r"\foo".to_owned(), "x".to_owned())
+        .build()
+        .unwrap();
+    assert!(validate(r"\foo", &opts).is_ok());
+}
+
+#[test]
+fn test_accessible() {
+    let opts = Opts::accessible().build().unwrap();
+    let html = render_with_opts("a < b", &opts).unwrap();
+    assert!(
+        html.contains(r#"<math xmlns="http://www.w3.org/1998/Math/MathML" alttext="a &lt; b">"#)
+    );
+    assert!(html.contains(r#"<annotation encoding="application/x-tex">a &lt; b</annotation>"#));
+    assert!(html.contains(r#"<span class="katex-html" aria-hidden="true">"#));
+
+    #[cfg(feature = "temml")]
+    {
+        let opts = Opts::accessible()
+            .output_type(OutputType::Mathml)
+            .build()
+            .unwrap();
+        let html = render_with_opts("a < b", &opts).unwrap();
+        assert!(html.contains(r#"<annotation encoding="application/x-tex">a &lt; b</annotation>"#));
+    }
+}
+
+#[test]
+fn test_supports_command() {
+    for command in [
+        r"\frac", "frac", r"\alpha", r"\sqrt", r"\text", r"\begin", r"\,", r"\{", r"\AA",
+    ] {
+        assert!(supports_command(command), "{command}");
+    }
+    #[cfg(feature = "mhchem")]
+    assert!(supports_command(r"\ce"));
+    for command in [
+        r"\nonexistent",
+        "",
+        r"\",
+        r"\frac x",
+        r"\alpha\beta",
+        r"\a1",
+    ] {
+        assert!(!supports_command(command), "{command}");
+    }
+}
+
+#[test]
+fn test_render_with_metadata() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let input = r"\begin{aligned} a &= b \\ c &= d \\ e &= f \end{aligned}";
+    let (html, meta) = render_with_metadata(input, &opts).unwrap();
+    assert_eq!(html, render_with_opts(input, &opts).unwrap());
+    assert_eq!((meta.rows, meta.display_mode), (3, true));
+
+    let input = r"f(x) = \begin{cases} 0 & x < 0 \\ \begin{matrix} 1 \\ 2 \end{matrix} & x \ge 0 \end{cases}";
+    let (_, meta) = render_with_metadata(input, Opts::default()).unwrap();
+    assert_eq!((meta.rows, meta.display_mode), (2, false));
+
+    let (_, meta) = render_with_metadata("x + y", Opts::default()).unwrap();
+    assert_eq!(meta.rows, 0);
+
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    let (html, meta) = render_with_metadata(r"\begin{aligned} a \\", &opts).unwrap();
+    assert!(html.contains("katex-error"));
+    assert_eq!(meta.rows, 0);
+}
+
+#[cfg(feature = "mhchem")]
+#[test]
+fn test_disable_mhchem() {
+    let mut builder = Opts::builder();
+    builder.throw_on_error(true);
+    let html = render_with_opts(r"\ce{H2O}", builder.build().unwrap()).unwrap();
+    assert!(html.contains("H") && !html.contains("katex-error"));
+
+    builder.disable_mhchem(true);
+    for input in [r"\ce{H2O}", r"\pu{1 m}"] {
+        match render_with_opts(input, builder.build().unwrap()) {
+            Err(Error::ParseError { message, .. }) => {
+                assert!(message.ends_with(" is disabled"), "{message}")
+            }
+            result => panic!("{input}: {result:?}"),
+        }
+    }
+    let html =
+        render_with_opts(r"\ce{H2O}", builder.throw_on_error(false).build().unwrap()).unwrap();
+    assert!(html.contains("katex-error"));
+
+    let opts = Opts::builder()
+        .add_macro(r"\ce".to_owned(), r"\mathrm{#1}".to_owned())
+        .disable_mhchem(true)
+        .build()
+        .unwrap();
+    assert!(render_with_opts(r"\ce{H2O}", opts).is_ok());
+
+    #[cfg(feature = "temml")]
+    {
+        let opts = Opts::builder()
+            .output_type(OutputType::Mathml)
+            .disable_mhchem(true)
+            .throw_on_error(true)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            render_with_opts(r"\ce{H2O}", opts),
+            Err(Error::ParseError { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_escape_latex() {
+    for (c, escaped) in [
+        ("#", r"\#"),
+        ("$", r"\$"),
+        ("%", r"\%"),
+        ("&", r"\&"),
+        ("_", r"\_"),
+        ("{", r"\{"),
+        ("}", r"\}"),
+        ("~", r"\textasciitilde{}"),
+        ("^", r"\textasciicircum{}"),
+        ("\\", r"\textbackslash{}"),
+    ] {
+        assert_eq!(escape_latex(c), escaped);
+    }
+    assert_eq!(escape_latex("a b\u{e9}"), "a b\u{e9}");
+
+    let text = r"#1 costs $5 & 10% off {x_1} ~ y^2 \relax";
+    let opts = Opts::builder()
+        .output_type(OutputType::Mathml)
+        .throw_on_error(true)
+        .build()
+        .unwrap();
+    let html = render_with_opts(&format!(r"\text{{{}}}", escape_latex(text)), &opts).unwrap();
+    let rendered: String = html
+        .split("<mtext>")
+        .skip(1)
+        .filter_map(|part| part.split("</mtext>").next())
+        .collect();
+    assert_eq!(rendered.replace('\u{a0}', " "), text.replace('&', "&amp;"));
+}
+
+#[test]
+fn test_extract_svgs() {
+    let html = render(r"\sqrt{x}").unwrap();
+    let svgs = postprocess::extract_svgs(&html);
+    assert_eq!(svgs.len(), 1);
+    assert!(svgs[0].starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+    assert!(svgs[0].contains("<path") && svgs[0].ends_with("</svg>"));
+    assert!(html.contains(&svgs[0]));
+
+    let html = render(r"\sqrt{x} + \overrightarrow{AB} + \sqrt{y}").unwrap();
+    let svgs = postprocess::extract_svgs(&html);
+    assert_eq!(svgs.len(), 3);
+    assert!(svgs.iter().all(|svg| svg.matches("<svg").count() == 1));
+
+    assert!(postprocess::extract_svgs(&render("x + y").unwrap()).is_empty());
+}
+
+#[test]
+fn test_opts_validate() {
+    assert!(Opts::default().validate().is_ok());
+    assert!(Opts::accessible().build().unwrap().validate().is_ok());
+    let opts = Opts::builder()
+        .fleqn(true)
+        .min_rule_thickness(0.05)
+        .global_group(true)
+        .build()
+        .unwrap();
+    assert!(opts.validate().is_ok());
+
+    #[cfg(feature = "temml")]
+    {
+        let mut mathml = Opts::builder();
+        mathml.output_type(OutputType::Mathml);
+        let valid = mathml
+            .annotate(true)
+            .wrap(opts::WrapMode::Equals)
+            .xml(false);
+        assert!(valid.build().unwrap().validate().is_ok());
+        for opts in [
+            mathml.clone().fleqn(true).build(),
+            mathml.clone().global_group(true).build(),
+            mathml.clone().min_rule_thickness(0.05).build(),
+        ] {
+            assert!(matches!(
+                opts.unwrap().validate(),
+                Err(Error::InvalidOptions(message)) if message.contains("Temml")
+            ));
+        }
+        assert!(mathml.fleqn(false).build().unwrap().validate().is_ok());
+
+        let mut html = Opts::builder();
+        html.output_type(OutputType::HtmlAndMathml)
+            .annotate(true)
+            .xml(true);
+        assert!(html.build().unwrap().validate().is_ok());
+        for opts in [
+            html.clone().annotate(false).build(),
+            html.clone().wrap(opts::WrapMode::Tex).build(),
+            html.clone().xml(false).build(),
+        ] {
+            assert!(matches!(
+                opts.unwrap().validate(),
+                Err(Error::InvalidOptions(message)) if message.contains("KaTeX")
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_trust_policy() {
+    use opts::TrustPolicy;
+
+    let render_policy = |policy: TrustPolicy, input: &str| {
+        let opts = Opts::builder().trust_policy(policy).build().unwrap();
+        render_with_opts(input, &opts).unwrap()
+    };
+    let href = r"\href{https://katex.org}{a}";
+    let http = r"\href{http://katex.org}{a}";
+    let class = r"\htmlClass{foo}{a}";
+    let link = r#"<a href="#;
+
+    // Colors are never subject to trust.
+    let html = render_policy(TrustPolicy::None, r"\color{red}{a}\textcolor{blue}{b}");
+    assert!(html.contains("color:red") && html.contains("color:blue"));
+    assert!(!render_policy(TrustPolicy::None, href).contains(link));
+    assert!(!render_policy(TrustPolicy::None, class).contains("enclosing foo"));
+
+    assert!(render_policy(TrustPolicy::All, href).contains(link));
+    assert!(render_policy(TrustPolicy::All, class).contains("enclosing foo"));
+
+    let https = || TrustPolicy::Protocols(vec!["https".to_owned()]);
+    assert!(render_policy(https(), href).contains(link));
+    assert!(!render_policy(https(), http).contains(link));
+    assert!(!render_policy(https(), class).contains("enclosing foo"));
+
+    let commands = || TrustPolicy::Commands(vec![r"\htmlClass".to_owned()]);
+    assert!(render_policy(commands(), class).contains("enclosing foo"));
+    assert!(!render_policy(commands(), href).contains(link));
+
+    let custom = || TrustPolicy::custom(|context: &opts::TrustContext| context.url.is_none());
+    assert!(render_policy(custom(), class).contains("enclosing foo"));
+    assert!(!render_policy(custom(), href).contains(link));
+
+    // A policy replaces the trust settings made before.
+    let opts = Opts::builder()
+        .trust_policy(TrustPolicy::All)
+        .trust_policy(https())
+        .build()
+        .unwrap();
+    assert!(!render_with_opts(class, &opts)
+        .unwrap()
+        .contains("enclosing foo"));
+    let mut opts = Opts::builder().trust_policy(custom()).build().unwrap();
+    opts.set_trust_policy(TrustPolicy::None);
+    assert_eq!(
+        opts,
+        Opts::builder()
+            .trust_policy(TrustPolicy::None)
+            .build()
+            .unwrap()
+    );
+    assert!(!render_with_opts(class, &opts)
+        .unwrap()
+        .contains("enclosing foo"));
+}
+
+#[test]
+fn test_render_inline_display() {
+    let input = r"\int_0^1 x \, dx";
+    let display = render_display(input).unwrap();
+    assert!(display.starts_with(r#"<span class="katex-display">"#));
+    assert_eq!(
+        display,
+        render_with_opts(input, Opts::builder().display_mode(true).build().unwrap()).unwrap()
+    );
+    let inline = render_inline(input).unwrap();
+    assert!(!inline.contains("katex-display"));
+    assert_eq!(inline, render(input).unwrap());
+}
+
+#[test]
+fn test_render_lines() {
+    let input = "a + b\r\n\\frac{1}\n\n\\sqrt{2}";
+    let mut output = Vec::new();
+    render_lines(input.as_bytes(), &mut output, Opts::default()).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.split_terminator('\n').collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], render("a + b").unwrap());
+    assert!(lines[1].contains("katex-error"));
+    assert!(lines[2].is_empty());
+    assert_eq!(lines[3], render(r"\sqrt{2}").unwrap().replace('\n', " "));
+
+    let opts = Opts::builder().throw_on_error(true).build().unwrap();
+    let mut output = Vec::new();
+    let error = render_lines(input.as_bytes(), &mut output, &opts).unwrap_err();
+    assert!(matches!(error, Error::ParseError { .. }));
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        render("a + b").unwrap() + "\n"
+    );
+
+    let invalid_utf8: &[u8] = b"x\n\xff\n";
+    let error = render_lines(invalid_utf8, std::io::sink(), Opts::default()).unwrap_err();
+    assert!(matches!(error, Error::IoError(..)));
+}
+
+#[test]
+fn test_render_split() {
+    for display_mode in [false, true] {
+        let opts = Opts::builder().display_mode(display_mode).build().unwrap();
+        let input = r"\frac{a}{\sqrt{b}} < c";
+        let (html, mathml) = render_split(input, &opts).unwrap();
+        assert!(html.starts_with(r#"<span class="katex-html" aria-hidden="true">"#));
+        assert!(mathml.starts_with("<math") && mathml.ends_with("</math>"));
+        assert_eq!(
+            Some(mathml.clone()),
+            postprocess::extract_mathml(&render_with_opts(input, &opts).unwrap())
+        );
+
+        // Together, the halves are the content of the `katex` wrapper.
+        let combined = format!(
+            r#"<span class="katex"><span class="katex-mathml">{mathml}</span>{html}</span>"#
+        );
+        let full = render_with_opts(input, &opts).unwrap();
+        assert!(full.contains(&combined));
+    }
+
+    let opts = Opts::builder()
+        .output_type(OutputType::Html)
+        .mathml_alttext(true)
+        .build()
+        .unwrap();
+    let (_, mathml) = render_split("x < y", &opts).unwrap();
+    assert!(mathml.contains(r#"alttext="x &lt; y""#));
+
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    let (html, mathml) = render_split(r"\frac{1}", &opts).unwrap();
+    assert!(html.contains("katex-error"));
+    assert!(mathml.is_empty());
+    assert!(render_split(r"\frac{1}", Opts::default()).is_err());
+}
+
+#[test]
+fn test_opts_eq() {
+    let build = || {
+        Opts::builder()
+            .add_macro(r"\RR".to_owned(), r"\mathbb{R}".to_owned())
+            .display_mode(true)
+            .output_type(OutputType::Html)
+            .min_rule_thickness(0.05)
+            .max_size(Some(10.0))
+            .build()
+            .unwrap()
+    };
+    assert_eq!(build(), build());
+    assert_ne!(build(), Opts::default());
+
+    let mut other = build();
+    other.set_max_size(None);
+    assert_ne!(build(), other);
+
+    let opts = Opts::builder()
+        .trust_callback(|_: &opts::TrustContext| true)
+        .build()
+        .unwrap();
+    assert_eq!(opts, opts.clone());
+    let other = Opts::builder()
+        .trust_callback(|_: &opts::TrustContext| true)
+        .build()
+        .unwrap();
+    assert_ne!(opts, other);
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn test_caching_renderer() {
+    let renderer = std::sync::Arc::new(CachingRenderer::new(2));
+    let opts = Opts::default();
+    let html = renderer.render(r"\sqrt{x}", &opts).unwrap();
+    assert_eq!(renderer.stats(), CacheStats { hits: 0, misses: 1 });
+
+    // A thread which never initialized an engine gets the cached output.
+    let cached = std::thread::spawn({
+        let renderer = std::sync::Arc::clone(&renderer);
+        move || {
+            let html = renderer.render(r"\sqrt{x}", Opts::default()).unwrap();
+            (html, BUNDLE_LOADS.with(|loads| loads.get()))
+        }
+    });
+    assert_eq!(cached.join().unwrap(), (html, 0));
+    assert_eq!(renderer.stats(), CacheStats { hits: 1, misses: 1 });
+
+    let display = Opts::builder().display_mode(true).build().unwrap();
+    renderer.render(r"\sqrt{x}", &display).unwrap();
+    assert_eq!(renderer.stats().misses, 2);
+    assert_eq!(renderer.len(), 2);
+
+    // Evicts the least recently used entry.
+    renderer.render("y", &opts).unwrap();
+    assert_eq!(renderer.len(), 2);
+    renderer.render(r"\sqrt{x}", &opts).unwrap();
+    assert_eq!(renderer.stats(), CacheStats { hits: 1, misses: 4 });
+
+    assert!(renderer.render(r"\frac{", &opts).is_err());
+    assert!(renderer.render(r"\frac{", &opts).is_err());
+    assert_eq!(renderer.stats().misses, 6);
+
+    // Options with a `NaN` field are never cached, others always.
+    let stats = renderer.stats();
+    for nan in [
+        Opts::builder()
+            .min_rule_thickness(f64::NAN)
+            .build()
+            .unwrap(),
+        Opts::builder().max_size(Some(f64::NAN)).build().unwrap(),
+        Opts::builder()
+            .fleqn_indent(opts::Length::Em(f64::NAN))
+            .build()
+            .unwrap(),
+    ] {
+        assert!(nan.has_nan());
+        renderer.render("z", &nan).unwrap();
+        renderer.render("z", &nan).unwrap();
+    }
+    assert_eq!(renderer.stats().hits, stats.hits);
+    let finite = Opts::builder()
+        .min_rule_thickness(0.1)
+        .max_size(None)
+        .fleqn_indent(opts::Length::Em(1.0))
+        .trust_policy(opts::TrustPolicy::custom(|_: &opts::TrustContext| true))
+        .build()
+        .unwrap();
+    assert!(!finite.has_nan());
+    renderer.render("z", &finite).unwrap();
+    renderer.render("z", &finite).unwrap();
+    assert_eq!(renderer.stats().hits, stats.hits + 1);
+}
+
+#[test]
+fn test_render_dom_markup() {
+    for output_type in [OutputType::HtmlAndMathml, OutputType::Mathml] {
+        let opts = Opts::builder()
+            .display_mode(true)
+            .output_type(output_type)
+            .build()
+            .unwrap();
+        let html = render_dom_markup(r"\frac{a}{b}", &opts).unwrap();
+        assert!(!html.contains("<math"));
+        assert!(!html.contains("katex-mathml"));
+        assert!(html.starts_with(r#"<span class="katex-display">"#));
+        assert!(html.contains("katex-html"));
+        let mut html_opts = opts.clone();
+        html_opts.set_output_type(OutputType::Html);
+        assert_eq!(html, render_with_opts(r"\frac{a}{b}", &html_opts).unwrap());
+    }
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    let html = render_dom_markup(r"\frac{", &opts).unwrap();
+    assert!(html.contains("katex-error"));
+    assert!(render_dom_markup(r"\frac{", Opts::default()).is_err());
+}
+
+#[cfg(feature = "quick-js")]
+#[test]
+fn test_error_source() {
+    use std::error::Error as _;
+
+    let engine: Engine = init_katex().unwrap();
+    let error = engine.eval("throw new Error('boom')").unwrap_err();
+    assert!(matches!(error, Error::JsExecError(_, Some(_))));
+    let source = error
+        .source()
+        .unwrap()
+        .downcast_ref::<BackendError>()
+        .unwrap();
+    assert!(source.downcast_ref::<rquickjs::Error>().is_some());
+
+    // Errors classified from the recorded JS error keep no source.
+    let error = render_with_opts(r"\frac{", Opts::default()).unwrap_err();
+    assert!(error.source().is_none());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_render_async() {
+    let inputs: Vec<String> = (0..16).map(|i| format!(r"\frac{{{i}}}{{2}}")).collect();
+    let renders: Vec<_> = inputs
+        .iter()
+        .cloned()
+        .map(|input| tokio::spawn(async move { render_async(&input, Opts::default()).await }))
+        .collect();
+    for (handle, input) in renders.into_iter().zip(&inputs) {
+        assert_eq!(handle.await.unwrap().unwrap(), render(input).unwrap());
+    }
+    assert!(worker::async_pool().spawned() >= 1);
+    assert!(matches!(
+        render_async(r"\frac{", Opts::default()).await,
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_katex_pool() {
+    let pool = KatexPool::new(3);
+    assert_eq!((pool.max_size(), pool.size()), (3, 0));
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let pool = pool.clone();
+            std::thread::spawn(move || {
+                for j in 0..20 {
+                    let input = format!(r"\frac{{{i}}}{{{j}}}");
+                    let html = pool.render(&input, Opts::default()).unwrap();
+                    assert!(html.contains(&format!("<mn>{i}</mn>")));
+                }
+                // No engine was created on this thread.
+                BUNDLE_LOADS.with(|loads| loads.get())
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 0);
+    }
+    assert!((1..=3).contains(&pool.size()));
+    assert!(pool.available() <= pool.size());
+    assert!(matches!(
+        pool.render(r"\frac{", Opts::default()),
+        Err(Error::ParseError { .. })
+    ));
+}
+
+#[test]
+fn test_em() {
+    use opts::Em;
+
+    let raw = Opts::builder()
+        .min_rule_thickness(0.08)
+        .max_size(Some(2.0))
+        .build()
+        .unwrap();
+    let mut typed = Opts::default();
+    typed.set_min_rule_thickness_em(Em(0.08));
+    typed.set_max_size_em(Some(Em::from(2.0)));
+    assert_eq!(raw, typed);
+    let built = Opts::builder()
+        .min_rule_thickness(Em(0.08))
+        .max_size(Some(2.0))
+        .build()
+        .unwrap();
+    assert_eq!(raw, built);
+
+    let input = r"\rule{5em}{0.01em}";
+    assert_eq!(
+        render_with_opts(input, &raw).unwrap(),
+        render_with_opts(input, &typed).unwrap()
+    );
+    #[cfg(feature = "serde")]
+    assert_eq!(
+        serde_json::to_string(&raw).unwrap(),
+        serde_json::to_string(&typed).unwrap()
+    );
+
+    assert_eq!(Em(0.08).to_string(), "0.08em");
+    assert_eq!(f64::from(Em(0.08)), 0.08);
+}
+
+#[test]
+fn test_max_size_typed() {
+    use opts::{Em, MaxSize};
+
+    assert_eq!(MaxSize::Em(2.5).to_em(), 2.5);
+    assert_eq!(MaxSize::Points(20.0, 10.0).to_em(), 2.0);
+    assert_eq!(MaxSize::Points(18.0, 12.0).to_em(), 1.5);
+    assert_eq!(MaxSize::from(Em(3.0)), MaxSize::Em(3.0));
+    assert_eq!(f64::from(MaxSize::Points(72.0, 12.0)), 6.0);
+
+    let raw = Opts::builder().max_size(Some(2.0)).build().unwrap();
+    let built = Opts::builder()
+        .max_size(MaxSize::Points(24.0, 12.0))
+        .build()
+        .unwrap();
+    assert_eq!(raw, built);
+    let mut typed = Opts::default();
+    typed.set_max_size_typed(Some(MaxSize::Points(20.0, 10.0)));
+    assert_eq!(raw, typed);
+    typed.set_max_size_typed(None);
+    assert_eq!(typed, Opts::builder().max_size(None).build().unwrap());
+
+    let html = render_with_opts(r"\rule{1em}{50pt}", &built).unwrap();
+    assert!(html.contains("border-top-width:2em"), "{html}");
+
+    // Sizes in points need a positive and finite number of points per em.
+    assert_eq!(
+        MaxSize::points(24.0, 12.0).unwrap(),
+        MaxSize::Points(24.0, 12.0)
+    );
+    for points_per_em in [0.0, -12.0, f64::INFINITY, f64::NAN] {
+        assert!(matches!(
+            MaxSize::points(24.0, points_per_em),
+            Err(Error::InvalidOptions(_))
+        ));
+        assert!(MaxSize::Points(24.0, points_per_em).to_em().is_nan());
+        let opts = Opts::builder()
+            .max_size(MaxSize::Points(24.0, points_per_em))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            opts.validate(),
+            Err(Error::InvalidOptions(message)) if message.contains("max_size")
+        ));
+    }
+    assert!(built.validate().is_ok());
+    assert!(Opts::builder()
+        .max_size(-1.0)
+        .build()
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn test_render_both() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let (inline, display) = render_both(r"\int_0^1 f", &opts).unwrap();
+    assert!(!inline.contains("katex-display"));
+    assert!(display.contains("katex-display"));
+    assert_eq!(
+        inline,
+        render_with_opts(r"\int_0^1 f", Opts::default()).unwrap()
+    );
+    assert!(render_both(r"\frac{1}", &opts).is_err());
+}
+
+#[test]
+fn test_minify_html() {
+    let input = r"\text{a b} \sum_{i=1}^n x_i";
+    let html = render(input).unwrap();
+    assert_eq!(postprocess::minify_html(&html), html);
+
+    // Break lines between adjacent tags as a pretty-printer would.
+    let mut pretty = String::new();
+    let mut after_tag = false;
+    for token in postprocess::tokens(&html) {
+        let is_tag = !matches!(token, postprocess::Token::Text(_));
+        if after_tag && is_tag {
+            pretty.push_str("\n    ");
+        }
+        pretty.push_str(token.raw());
+        after_tag = is_tag;
+    }
+    let minified = postprocess::minify_html(&pretty);
+    assert!(minified.len() < pretty.len());
+    assert_eq!(minified, html);
+    assert!(minified.contains("a\u{a0}b"));
+
+    let html = html.replace(input, &format!("\n  {input}  \n"));
+    let minified = postprocess::minify_html(&html);
+    assert!(minified.contains(&format!("\n  {input}  \n</annotation>")));
+    let html = html.replace(&format!("\n  {input}  \n"), "  ");
+    assert_eq!(
+        postprocess::extract_source(&postprocess::minify_html(&html)).unwrap(),
+        "  "
+    );
+}
+
+#[test]
+fn test_prettify_html() {
+    // Without SVG, whose path data spans several lines.
+    let input = r"\text{a  b} \frac{x_i}{2} \\ \mathrm{d}";
+    for opts in [
+        Opts::default(),
+        Opts::builder().display_mode(true).build().unwrap(),
+        Opts::builder()
+            .output_type(OutputType::Mathml)
+            .build()
+            .unwrap(),
+    ] {
+        let html = render_with_opts(input, &opts).unwrap();
+        let pretty = postprocess::prettify_html(&html);
+        assert!(pretty.lines().count() > 10);
+        assert!(pretty.lines().all(|line| {
+            let content = line.trim_start_matches(' ');
+            (line.len() - content.len()) % 2 == 0 && content.starts_with('<')
+        }));
+        assert_eq!(postprocess::prettify_html(&pretty), pretty);
+        assert_eq!(postprocess::minify_html(&pretty), html);
+        assert_eq!(
+            postprocess::extract_source(&pretty),
+            postprocess::extract_source(&html)
+        );
+        assert_eq!(
+            postprocess::extract_mathml(&pretty).map(|mathml| postprocess::minify_html(&mathml)),
+            postprocess::extract_mathml(&html)
+        );
+    }
+
+    // Annotations keep their whitespace, leaves stay on one line.
+    let html = "<math><semantics><mrow><mi>x</mi><mspace width=\"1em\"/></mrow>\
+                <annotation encoding=\"application/x-tex\">\n x \n</annotation></semantics></math>";
+    let pretty = postprocess::prettify_html(html);
+    assert_eq!(
+        pretty,
+        concat!(
+            "<math>\n",
+            "  <semantics>\n",
+            "    <mrow>\n",
+            "      <mi>x</mi>\n",
+            "      <mspace width=\"1em\"/>\n",
+            "    </mrow>\n",
+            "    <annotation encoding=\"application/x-tex\">\n x \n</annotation>\n",
+            "  </semantics>\n",
+            "</math>"
+        )
+    );
+    assert_eq!(postprocess::prettify_html(&pretty), pretty);
+
+    // Text at the top level is left inline.
+    let html = render_mixed("a $x$ b", Opts::default()).unwrap();
+    assert_eq!(postprocess::prettify_html(&html), html);
+}
+
+#[test]
+fn test_render_with_transform() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let html = render_with_transform(r"e^{i\pi} = -1", &opts, |html| {
+        let caption = render_inline("e").unwrap();
+        format!("<figure>{html}<figcaption>Euler, {caption}</figcaption></figure>")
+    })
+    .unwrap();
+    let expected = render_with_opts(r"e^{i\pi} = -1", &opts).unwrap();
+    assert!(html.starts_with(&format!("<figure>{expected}<figcaption>")));
+    assert!(html.ends_with("</figcaption></figure>"));
+
+    let mut called = false;
+    let result = render_with_transform(r"\frac{1}", Opts::default(), |html| {
+        called = true;
+        html
+    });
+    assert!(result.is_err());
+    assert!(!called);
+}
+
+#[test]
+fn test_render_to_writer() {
+    let opts = Opts::builder().display_mode(true).build().unwrap();
+    let mut out = String::new();
+    render_to_writer(&mut out, r"\sqrt{x}", &opts).unwrap();
+    assert_eq!(out, render_with_opts(r"\sqrt{x}", &opts).unwrap());
+
+    render_to_writer(&mut out, "y", &opts).unwrap();
+    assert!(out.ends_with(&render_with_opts("y", &opts).unwrap()));
+
+    let len = out.len();
+    assert!(render_to_writer(&mut out, r"\frac{1}", &opts).is_err());
+    assert_eq!(out.len(), len);
+
+    struct Failing;
+    impl core::fmt::Write for Failing {
+        fn write_str(&mut self, _: &str) -> core::fmt::Result {
+            Err(core::fmt::Error)
+        }
+    }
+    assert!(matches!(
+        render_to_writer(&mut Failing, "x", &opts),
+        Err(Error::WriteError(_))
+    ));
+}
+
+#[test]
+fn test_render_all() {
+    let opts = Opts::builder()
+        .output_type(OutputType::Html)
+        .build()
+        .unwrap();
+    let rendered = render_all(r"\sqrt{x}", &opts).unwrap();
+    assert_eq!(rendered.html, render(r"\sqrt{x}").unwrap());
+    assert!(rendered.mathml.starts_with("<math") && rendered.mathml.contains("<msqrt>"));
+    #[cfg(feature = "speech")]
+    assert_eq!(rendered.speech, "square root of, x, end square root");
+
+    let opts = Opts::builder().throw_on_error(false).build().unwrap();
+    let result = render_all(r"\frac{1}", &opts);
+    #[cfg(feature = "speech")]
+    assert!(result.is_err());
+    #[cfg(not(feature = "speech"))]
+    {
+        let rendered = result.unwrap();
+        assert!(rendered.html.contains("katex-error"));
+        assert!(rendered.mathml.is_empty());
+    }
+}
+
+#[test]
+fn test_font_faces_css() {
+    let css = font_faces_css();
+    assert!(css.contains("font-family:KaTeX_Main;font-style:normal;font-weight:400;"));
+    assert!(css.contains(r#"url("fonts/KaTeX_Main-Regular.woff2") format("woff2")"#));
+    assert_eq!(css.matches("@font-face").count(), 20);
+    assert!(std::ptr::eq(css, font_faces_css()));
+
+    // Every font KaTeX has metrics for is declared.
+    let bundle = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/vendor/katex/katex.min.js"
+    ));
+    for font in [
+        "AMS-Regular",
+        "Main-BoldItalic",
+        "Math-Italic",
+        "SansSerif-Bold",
+        "Size4-Regular",
+    ] {
+        assert!(bundle.contains(&format!("\"{font}\"")), "{font}");
+        assert!(css.contains(&format!("/KaTeX_{font}.woff2")), "{font}");
+    }
+
+    for base in ["/static/katex", "/static/katex/"] {
+        let css = font_faces_css_with_base(base);
+        assert!(css.contains(r#"url("/static/katex/KaTeX_Size4-Regular.ttf") format("truetype")"#));
+    }
+    let css = font_faces_css_with_base("");
+    assert!(css.contains(r#"url("KaTeX_AMS-Regular.woff")"#));
+    let css = font_faces_css_with_base(r#"/a"b"#);
+    assert!(css.contains(r#"url("/a\"b/KaTeX_AMS-Regular.woff")"#));
+}
+
+#[test]
+fn test_bundled_fonts() {
+    let fonts = bundled_fonts();
+    assert_eq!(fonts.len(), 20);
+    let math_italic = fonts
+        .iter()
+        .find(|font| font.family == "KaTeX_Math" && font.style == "italic" && font.weight == "400")
+        .unwrap();
+    assert_eq!(math_italic.file, "KaTeX_Math-Italic.woff2");
+    for font in fonts {
+        assert!(
+            font_faces_css().contains(&format!("/{}\")", font.file)),
+            "{}",
+            font.file
+        );
+    }
+}
+
+#[test]
+fn test_stylesheet() {
+    let css = stylesheet();
+    assert!(!css.is_empty());
+    assert!(css.contains(".katex"));
+    assert!(css.contains(&format!(
+        r#".katex-version:after{{content:"{KATEX_VERSION}"}}"#
+    )));
+    for font in bundled_fonts() {
+        assert!(
+            css.contains(&format!("url(fonts/{})", font.file)),
+            "{}",
+            font.file
+        );
+    }
+
+    let opts = Opts::default();
+    let html = render_standalone("a + b", &opts).unwrap();
+    assert_eq!(
+        html,
+        format!(
+            "<style>{css}</style>{}",
+            render_with_opts("a + b", &opts).unwrap()
+        )
+    );
+    assert!(render_standalone(r"\frac{", &opts).is_err());
+}
+
+#[test]
+fn test_render_with_builder() {
+    let mut builder = Opts::builder();
+    builder.display_mode(true).throw_on_error(false);
+    let html = render_with_builder(r"\frac{1}{2}", &builder).unwrap();
+    assert_eq!(
+        html,
+        render_with_opts(r"\frac{1}{2}", builder.build().unwrap()).unwrap()
+    );
+    assert!(html.contains("katex-display"));
+
+    builder.display_mode(false);
+    let html = render_with_builder(r"\frac{1}", &builder).unwrap();
+    assert!(html.contains("katex-error"));
+}
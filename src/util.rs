@@ -0,0 +1,36 @@
+//! Helpers for building LaTeX input.
+//!
+//! These are plain string functions, independent of the JS engine.
+
+/// Escape the LaTeX special characters `#`, `$`, `%`, `&`, `_`, `{`, `}`,
+/// `~`, `^` and `\` in `text`, so it is typeset verbatim when embedded into
+/// `\text{...}` (or another text-mode argument such as `\textbf{...}`).
+///
+/// The first seven characters are escaped with a backslash, while `~`, `^`
+/// and `\` become `\textasciitilde{}`, `\textasciicircum{}` and
+/// `\textbackslash{}`, which are only valid in text mode. All other
+/// characters, including whitespace, are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// let name = katex::escape_latex("50% of {x_1}");
+/// assert_eq!(name, r"50\% of \{x\_1\}");
+/// let html = katex::render(&format!(r"\text{{{name}}}")).unwrap();
+/// ```
+pub fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
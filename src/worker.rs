@@ -0,0 +1,135 @@
+//! Threads each keeping a warmed engine, serving render jobs submitted from
+//! any thread, behind [`KatexPool`](crate::KatexPool) and `render_async`.
+//!
+//! The JS engines are not `Send`, so work is brought to them instead: jobs go
+//! through a shared queue to whichever worker is idle. Workers are spawned on
+//! demand, up to the size of the pool, and live as long as the pool.
+
+#[cfg(feature = "tokio")]
+use crate::{error::Error, error::Result, opts::Opts, render_with_opts};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+/// A unit of work run on a worker thread.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of up to `max` worker threads.
+pub(crate) struct WorkerPool {
+    max: usize,
+    jobs: mpsc::Sender<Job>,
+    queue: Arc<Mutex<mpsc::Receiver<Job>>>,
+    spawned: AtomicUsize,
+    idle: Arc<AtomicUsize>,
+}
+
+impl WorkerPool {
+    /// Create a pool of up to `max` (at least one) workers, none spawned yet.
+    pub(crate) fn new(max: usize) -> Self {
+        let (jobs, queue) = mpsc::channel();
+        Self {
+            max: max.max(1),
+            jobs,
+            queue: Arc::new(Mutex::new(queue)),
+            spawned: AtomicUsize::new(0),
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Queue `job`, spawning a worker for it if none is idle and the pool is
+    /// not full yet.
+    pub(crate) fn submit(&self, job: Job) {
+        // The queue lives as long as `self`, so sending cannot fail.
+        let _ = self.jobs.send(job);
+        if self.idle.load(Ordering::SeqCst) == 0 {
+            let spawned =
+                self.spawned
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |spawned| {
+                        (spawned < self.max).then_some(spawned + 1)
+                    });
+            if spawned.is_ok() {
+                self.spawn();
+            }
+        }
+    }
+
+    fn spawn(&self) {
+        let queue = Arc::clone(&self.queue);
+        let idle = Arc::clone(&self.idle);
+        thread::Builder::new()
+            .name("katex-worker".to_owned())
+            .spawn(move || {
+                // Errors surface again when rendering.
+                let _ = crate::warm_up();
+                loop {
+                    idle.fetch_add(1, Ordering::SeqCst);
+                    let job = queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                    idle.fetch_sub(1, Ordering::SeqCst);
+                    match job {
+                        // A panicking job drops its reply channel, which the
+                        // submitter notices; the worker carries on.
+                        Ok(job) => drop(panic::catch_unwind(AssertUnwindSafe(job))),
+                        Err(_) => break,
+                    }
+                }
+            })
+            .expect("failed to spawn katex worker thread");
+    }
+
+    /// Maximum number of workers.
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Number of workers spawned so far.
+    pub(crate) fn spawned(&self) -> usize {
+        self.spawned.load(Ordering::SeqCst)
+    }
+
+    /// Number of workers waiting for a job.
+    pub(crate) fn idle(&self) -> usize {
+        self.idle.load(Ordering::SeqCst)
+    }
+}
+
+/// The pool used by [`render_async`], sized to the available parallelism.
+#[cfg(feature = "tokio")]
+pub(crate) fn async_pool() -> &'static WorkerPool {
+    static POOL: std::sync::OnceLock<WorkerPool> = std::sync::OnceLock::new();
+    POOL.get_or_init(|| WorkerPool::new(thread::available_parallelism().map_or(1, |n| n.get())))
+}
+
+/// Render LaTeX equation to HTML with additional [options](`Opts`) without
+/// blocking the async runtime.
+///
+/// The render runs on one of a few dedicated worker threads, as many as the
+/// machine has cores, each keeping its engine warm across calls; concurrent
+/// calls are spread over them. The workers are spawned on demand and do not
+/// depend on a particular runtime: only a `tokio` channel is used to await the
+/// result.
+///
+/// # Examples
+///
+/// ```
+/// # async fn handler() -> katex::Result<String> {
+/// let html = katex::render_async(r"\sqrt{2}", katex::Opts::default()).await?;
+/// # Ok(html)
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn render_async(input: &str, opts: impl AsRef<Opts>) -> Result<String> {
+    let input = input.to_owned();
+    let opts = opts.as_ref().clone();
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    async_pool().submit(Box::new(move || {
+        let _ = sender.send(render_with_opts(&input, &opts));
+    }));
+    receiver
+        .await
+        .map_err(|_| Error::JsExecError("render worker panicked".to_owned(), None))?
+}
@@ -0,0 +1,25 @@
+//! The default options are process-wide, so they are changed in a test binary
+//! of their own, where no other test renders with them in parallel.
+
+use katex::{default_opts, render, render_with_opts, set_default_opts, Opts};
+
+#[test]
+fn test_default_opts() {
+    assert_eq!(default_opts(), Opts::default());
+    let opts = Opts::builder()
+        .add_macro(r"\defaultoptsmacro".to_owned(), r"\mathbb{D}".to_owned())
+        .build()
+        .unwrap();
+    set_default_opts(opts.clone());
+    assert_eq!(default_opts(), opts);
+    let html = render(r"\defaultoptsmacro").unwrap();
+    assert!(html.contains("mathbb"));
+    let html = std::thread::spawn(|| render(r"\defaultoptsmacro").unwrap())
+        .join()
+        .unwrap();
+    assert!(html.contains("mathbb"));
+    assert!(render_with_opts(r"\defaultoptsmacro", Opts::default()).is_err());
+
+    set_default_opts(Opts::default());
+    assert!(render(r"\defaultoptsmacro").is_err());
+}